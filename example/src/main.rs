@@ -1,60 +1,85 @@
 use clap::Parser;
 use http::{
-    request::{Method, Request},
-    response::{Headers, Response, Status},
+    middleware::{
+        compression::Compression, conditional::ConditionalRequests, range::RangeRequests,
+    },
+    request::{body::BodyError, Request},
+    response::{Response, Status},
+    router::Router,
     server::Server,
+    session::Sessions,
+    static_files::static_files,
+    websocket::{self, Message},
 };
-use std::fs;
+use std::io;
+
+fn headers_form(_: &mut Request) -> Response {
+    Response::redirect(Status::Found, "/static/headers.html").unwrap()
+}
+
+fn visits(request: &mut Request) -> Response {
+    let session = request.session().unwrap();
+    let visits = session.get::<u32>("visits").unwrap_or_default() + 1;
+    session.insert("visits", visits);
+    Response::text(format!("Visits: {visits}"))
+}
+
+fn redirect(_: &mut Request) -> Response {
+    Response::redirect(Status::TemporaryRedirect, "/login").unwrap()
+}
 
 fn headers(request: &mut Request) -> Response {
-    match (request.path(), &request.method) {
-        ("/headers", Method::Get) => Response {
-            body: fs::read("example/src/static/headers.html").expect("ON"),
-            headers: Headers::new("X-Server: RustHTTP"),
-            status: Status::Ok,
+    let content_type = request.header("Content-Type").unwrap_or("None").to_string();
+
+    let body = match request.form() {
+        Ok(form) => form
+            .iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(BodyError::UnsupportedMediaType) => match multipart_fields(request) {
+            Ok(fields) => fields,
+            Err(BodyError::UnsupportedMediaType) => {
+                let body = request
+                    .body
+                    .as_mut()
+                    .map(|b| b.all_bytes())
+                    .unwrap_or_default();
+                String::from_utf8(body).unwrap_or_else(|_| "not utf8".to_string())
+            }
+            Err(error) => return error.into(),
         },
-        ("/redirect", Method::Get) => Response::new(
-            Status::TemporaryRedirect,
-            Headers::new("Location: /login"),
-            Vec::new(),
-        ),
-        ("/headers", Method::Post) => {
-            let body = request.body.as_mut().unwrap().all_bytes();
-            let body = String::from_utf8(body).unwrap_or_else(|_| "not utf8".to_string());
+        Err(error) => return error.into(),
+    };
 
-            let content_type = request
-                .headers
-                .as_ref()
-                .and_then(|headers| headers.get("Content-Type"))
-                .map(|_type| _type.to_string())
-                .unwrap_or_else(|| "None".to_string());
+    let resp = format!(
+        "<h1>body</h1>
+        <pre><code>{}</code></pre>
+        <hr/>
+        Content-Type: <code>{}</code>",
+        body, content_type
+    );
 
-            let resp = format!(
-                "<h1>body</h1>
-                <pre><code>{}</code></pre>
-                <hr/>
-                Content-Type: <code>{}</code>",
-                body, content_type
-            );
+    Response::html(resp)
+}
+
+/// Describe every field of a `multipart/form-data` body, without keeping uploaded files.
+fn multipart_fields(request: &mut Request) -> Result<String, BodyError> {
+    let mut multipart = request.multipart()?.max_parts(20);
+    let mut fields = Vec::new();
 
-            Response {
-                body: resp.as_bytes().to_vec(),
-                headers: Headers {
-                    headers: String::new(),
-                },
-                status: Status::Ok,
+    while let Some(mut part) = multipart.next_part()? {
+        let name = part.name().unwrap_or_default().to_string();
+        match part.filename().map(String::from) {
+            Some(filename) => {
+                let size = io::copy(&mut part, &mut io::sink())?;
+                fields.push(format!("{name} = {filename} ({size} bytes)"));
             }
+            None => fields.push(format!("{name} = {}", part.text()?)),
         }
-        _ => Response {
-            body: format!("<h1>{} Not Found</h1>", request.path())
-                .as_bytes()
-                .to_vec(),
-            headers: Headers {
-                headers: String::new(),
-            },
-            status: Status::NotFound,
-        },
     }
+
+    Ok(fields.join("\n"))
 }
 
 #[derive(Parser, Debug)]
@@ -68,12 +93,37 @@ struct Args {
     threaded: bool,
 }
 
+/// Echo every text and binary message back.
+fn echo(request: &mut Request) -> Response {
+    websocket::upgrade(request, |mut socket| {
+        while let Ok(message) = socket.read() {
+            if let Message::Text(_) | Message::Binary(_) = message {
+                let _ = socket.send(message);
+            }
+        }
+    })
+    .unwrap_or_else(Response::from)
+}
+
 fn main() {
     let args = Args::parse();
+    let router = Router::new()
+        .get("/headers", headers_form)
+        .post("/headers", headers)
+        .get("/visits", visits)
+        .get("/redirect", redirect)
+        .get("/echo", echo)
+        .fallback(static_files("/static", "example/src/static").listings(true));
     let server = if args.threaded {
-        Server::threaded("0.0.0.0:4000", headers, args.threads_count)
+        Server::threaded("0.0.0.0:4000", router, args.threads_count)
     } else {
-        Server::new("0.0.0.0:4000", headers)
+        Server::new("0.0.0.0:4000", router)
     };
-    server.listen().unwrap();
+    server
+        .wrap(ConditionalRequests::new())
+        .wrap(Compression::new())
+        .wrap(RangeRequests)
+        .wrap(Sessions::default())
+        .listen()
+        .unwrap();
 }
@@ -0,0 +1,369 @@
+use super::Error;
+use crate::request::chunked::ChunkedDecoder;
+use crate::request::HttpVersion;
+use crate::response::{
+    framing, read_head, Body, ChunkedEncoder, Framing, Headers, Response, Status,
+};
+use std::collections::HashMap;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Connection settings shared by the requests of a client or a proxy.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Config {
+    pub(crate) connect_timeout: Duration,
+    pub(crate) timeout: Duration,
+    pub(crate) max_idle: usize,
+    /// Refuse to connect to addresses that aren't [public](is_public).
+    pub(crate) public_only: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(30),
+            max_idle: 8,
+            public_only: false,
+        }
+    }
+}
+
+/// Idle connections, by address.
+#[derive(Default)]
+pub(crate) struct Pool {
+    idle: Mutex<HashMap<String, Vec<TcpStream>>>,
+}
+
+impl Pool {
+    /// An idle connection to `addr` the server hasn't closed yet.
+    fn take(&self, addr: &str) -> Option<TcpStream> {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let connections = idle.get_mut(addr)?;
+        while let Some(stream) = connections.pop() {
+            if is_open(&stream) {
+                return Some(stream);
+            }
+        }
+        None
+    }
+
+    fn release(&self, addr: &str, stream: TcpStream, max_idle: usize) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let connections = idle.entry(addr.to_string()).or_default();
+        if connections.len() < max_idle {
+            connections.push(stream);
+        }
+    }
+}
+
+/// Whether an idle connection is still usable: the server may have closed it, or sent
+/// something it shouldn't have.
+fn is_open(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let open = matches!(
+        stream.peek(&mut [0]),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock
+    );
+    stream.set_nonblocking(false).is_ok() && open
+}
+
+pub(crate) fn connect(addr: &str, config: &Config) -> Result<TcpStream, Error> {
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "No address to connect to");
+    let mut refused = false;
+    for socket in addr.to_socket_addrs().map_err(Error::Connect)? {
+        // The resolved address is checked, whatever name or notation led to it.
+        if config.public_only && !is_public(socket.ip()) {
+            refused = true;
+            continue;
+        }
+        match TcpStream::connect_timeout(&socket, config.connect_timeout) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(config.timeout))?;
+                stream.set_write_timeout(Some(config.timeout))?;
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(e) => last = e,
+        }
+    }
+    if refused && last.kind() == io::ErrorKind::NotFound {
+        return Err(Error::AddressNotAllowed);
+    }
+    Err(match last.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::Timeout,
+        _ => Error::Connect(last),
+    })
+}
+
+/// Whether `ip` is a globally reachable unicast address, rather than a loopback, private,
+/// link-local, shared, documentation, multicast or unspecified one.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6890>
+pub(crate) fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // Shared address space, for carrier-grade NAT.
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// The body of a request, and whether it's sent chunked rather than with the
+/// `Content-Length` in the head.
+pub(crate) struct Outgoing<'b> {
+    pub(crate) reader: &'b mut dyn Read,
+    pub(crate) chunked: bool,
+}
+
+/// Send `head` and `body` to `addr` over a pooled connection, and read the response head.
+/// The response body is streamed from the connection, which goes back to the pool once
+/// it's read whole. The response to a `HEAD` request has an empty body.
+///
+/// A pooled connection that fails before the response is retried on a new one, unless
+/// the request has a body, which can't be sent twice.
+pub(crate) fn send(
+    pool: &Arc<Pool>,
+    config: &Config,
+    addr: &str,
+    head: &[u8],
+    mut body: Option<Outgoing>,
+    head_request: bool,
+) -> Result<Response, Error> {
+    let mut idle = pool.take(addr);
+    loop {
+        let reused = idle.is_some();
+        let stream = match idle.take() {
+            Some(stream) => stream,
+            None => connect(addr, config)?,
+        };
+        match exchange(stream, head, &mut body) {
+            Err(Error::Io(_)) if reused && body.is_none() => {}
+            Err(e) => return Err(e),
+            Ok((reader, keep_alive, status, headers)) => {
+                let release = Release {
+                    pool: pool.clone(),
+                    addr: addr.to_string(),
+                    max_idle: config.max_idle,
+                    keep_alive,
+                };
+                return response(reader, status, headers, head_request, release);
+            }
+        }
+    }
+}
+
+/// Write the request and read the head of its final response.
+fn exchange(
+    stream: TcpStream,
+    head: &[u8],
+    body: &mut Option<Outgoing>,
+) -> Result<(BufReader<TcpStream>, bool, Status, Headers), Error> {
+    let mut writer = BufWriter::new(&stream);
+    writer.write_all(head)?;
+    match body {
+        Some(Outgoing {
+            reader,
+            chunked: true,
+        }) => {
+            let mut encoder = ChunkedEncoder::new(&mut writer);
+            io::copy(reader, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Some(Outgoing { reader, .. }) => {
+            io::copy(reader, &mut writer)?;
+        }
+        None => {}
+    }
+    writer.flush()?;
+    drop(writer);
+
+    let mut reader = BufReader::new(stream);
+    loop {
+        let head = read_head(&mut reader)?;
+        // Interim responses, e.g. `100 Continue`, are skipped.
+        if head.status.is_informational() {
+            continue;
+        }
+        let keep_alive = head.version == HttpVersion::V1_1
+            && !head
+                .headers
+                .get_all("Connection")
+                .flat_map(|value| value.split(','))
+                .any(|token| token.trim().eq_ignore_ascii_case("close"));
+        return Ok((reader, keep_alive, head.status, head.headers));
+    }
+}
+
+/// Build the response, framing its body the way the server did.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-6.3>
+fn response(
+    reader: BufReader<TcpStream>,
+    status: Status,
+    headers: Headers,
+    head_request: bool,
+    release: Release,
+) -> Result<Response, Error> {
+    let body = match framing(status.code(), &headers, head_request)? {
+        Framing::Empty => {
+            release.release(reader);
+            Body::empty()
+        }
+        Framing::Length(length) => Body::sized(
+            Incoming {
+                delimiter: Delimiter::Length(reader, length),
+                chunk: Vec::new(),
+                position: 0,
+                release,
+            },
+            length,
+        ),
+        Framing::Chunked => Body::stream(Incoming {
+            delimiter: Delimiter::Chunked(ChunkedDecoder::new(reader)),
+            chunk: Vec::new(),
+            position: 0,
+            release,
+        }),
+        // The connection can't be reused.
+        Framing::Close => Body::stream(reader),
+    };
+    Ok(Response::new(status, headers, body))
+}
+
+/// Gives a connection back to the pool once its response is read.
+struct Release {
+    pool: Arc<Pool>,
+    addr: String,
+    max_idle: usize,
+    keep_alive: bool,
+}
+
+impl Release {
+    fn release(&self, reader: BufReader<TcpStream>) {
+        // Bytes past the response mean the connection is out of step.
+        if self.keep_alive && reader.buffer().is_empty() {
+            self.pool
+                .release(&self.addr, reader.into_inner(), self.max_idle);
+        }
+    }
+}
+
+enum Delimiter {
+    Length(BufReader<TcpStream>, u64),
+    Chunked(ChunkedDecoder<BufReader<TcpStream>>),
+    Done,
+}
+
+/// A response body read from a pooled connection.
+struct Incoming {
+    delimiter: Delimiter,
+    chunk: Vec<u8>,
+    position: usize,
+    release: Release,
+}
+
+impl Read for Incoming {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.delimiter {
+            Delimiter::Done => Ok(0),
+            Delimiter::Length(reader, remaining) => {
+                let limit = buf
+                    .len()
+                    .min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+                let read = reader.read(&mut buf[..limit])?;
+                if read == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                *remaining -= read as u64;
+                if *remaining == 0 {
+                    if let Delimiter::Length(reader, _) =
+                        std::mem::replace(&mut self.delimiter, Delimiter::Done)
+                    {
+                        self.release.release(reader);
+                    }
+                }
+                Ok(read)
+            }
+            Delimiter::Chunked(decoder) => {
+                while self.position == self.chunk.len() {
+                    match decoder.next() {
+                        None => return Err(io::ErrorKind::UnexpectedEof.into()),
+                        Some(Err(e)) => {
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, e))
+                        }
+                        // The last chunk.
+                        Some(Ok(chunk)) if chunk.buf.is_empty() => {
+                            if let Delimiter::Chunked(decoder) =
+                                std::mem::replace(&mut self.delimiter, Delimiter::Done)
+                            {
+                                self.release.release(decoder.into_inner());
+                            }
+                            return Ok(0);
+                        }
+                        Some(Ok(chunk)) => {
+                            self.chunk = chunk.buf;
+                            self.position = 0;
+                        }
+                    }
+                }
+                let n = buf.len().min(self.chunk.len() - self.position);
+                buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+                self.position += n;
+                Ok(n)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::is_public;
+
+    #[test]
+    fn it_tells_public_addresses_apart() {
+        for ip in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
@@ -0,0 +1,605 @@
+//! A blocking HTTP/1.1 client, sending requests to `http://` URLs and streaming their
+//! responses.
+//!
+//! Connections are kept open and reused once a response body is read whole. Redirects
+//! are followed, up to 10 by default.
+//!
+//! ```no_run
+//! use http::client::Client;
+//!
+//! let client = Client::new();
+//! let response = client
+//!     .post("http://127.0.0.1:4000/notes")
+//!     .header("Content-Type", "text/plain")
+//!     .body("hello")
+//!     .send()
+//!     .unwrap();
+//! let body = response.body.into_bytes().unwrap();
+//! println!("{:?} {}", response.status, String::from_utf8_lossy(&body));
+//! ```
+
+pub(crate) mod connection;
+
+use crate::request::uri::{TargetForm, Uri};
+use crate::request::Method;
+use crate::response::{Body, Headers, Response, ResponseError};
+use connection::{Config, Outgoing, Pool};
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The largest redirect body read so that its connection can be reused.
+const MAX_DISCARDED_BODY: u64 = 64 * 1024;
+
+/// Why a request failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The URL isn't an absolute `http://` URL.
+    InvalidUrl(&'static str),
+    /// The server couldn't be reached.
+    Connect(io::Error),
+    /// The server didn't accept or send any data in time.
+    Timeout,
+    /// The connection failed.
+    Io(io::Error),
+    /// The server's response isn't valid HTTP/1.x.
+    Malformed(&'static str),
+    /// The server redirected more times than the client follows.
+    TooManyRedirects,
+    /// The server's address is one connections aren't allowed to, e.g. a loopback
+    /// address for a forward proxy.
+    AddressNotAllowed,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidUrl(reason) => write!(f, "Invalid URL: {reason}"),
+            Error::Connect(e) => write!(f, "Couldn't connect to the server: {e}"),
+            Error::Timeout => write!(f, "The server timed out"),
+            Error::Io(e) => write!(f, "The connection failed: {e}"),
+            Error::Malformed(reason) => {
+                write!(f, "The server sent an invalid response: {reason}")
+            }
+            Error::TooManyRedirects => write!(f, "Too many redirects"),
+            Error::AddressNotAllowed => write!(f, "The server's address isn't allowed"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Error::Timeout,
+            _ => Error::Io(error),
+        }
+    }
+}
+
+impl From<ResponseError> for Error {
+    fn from(error: ResponseError) -> Self {
+        match error {
+            ResponseError::Io(e) => Error::from(e),
+            ResponseError::Malformed(reason) => Error::Malformed(reason),
+            ResponseError::VersionNotSupported => Error::Malformed("Unsupported version"),
+        }
+    }
+}
+
+/// An HTTP/1.1 client. Clones share their idle connections.
+#[derive(Clone)]
+pub struct Client {
+    config: Config,
+    pool: Arc<Pool>,
+    max_redirects: usize,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client {
+            config: Config::default(),
+            pool: Arc::default(),
+            max_redirects: 10,
+        }
+    }
+}
+
+impl Client {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long to wait for a connection to a server, 5 seconds by default.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// How long to wait for a server to accept or send any data, 30 seconds by default.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// How many idle connections to keep open per server, 8 by default.
+    #[must_use]
+    pub fn max_idle(mut self, connections: usize) -> Self {
+        self.config.max_idle = connections;
+        self
+    }
+
+    /// How many redirects to follow before failing with [`Error::TooManyRedirects`], 10
+    /// by default. With `0`, redirects are returned as they are.
+    #[must_use]
+    pub fn max_redirects(mut self, redirects: usize) -> Self {
+        self.max_redirects = redirects;
+        self
+    }
+
+    /// A request for the absolute `http://` URL `url`.
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder<'_> {
+        RequestBuilder {
+            client: self,
+            method,
+            url: url.to_string(),
+            headers: Headers::default(),
+            body: None,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Get, url)
+    }
+
+    pub fn head(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Head, url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Post, url)
+    }
+
+    pub fn put(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Put, url)
+    }
+
+    pub fn patch(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Patch, url)
+    }
+
+    pub fn delete(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Delete, url)
+    }
+}
+
+/// A request being built, sent with [`send`](RequestBuilder::send).
+#[must_use = "requests are only sent by `send`"]
+pub struct RequestBuilder<'c> {
+    client: &'c Client,
+    method: Method,
+    url: String,
+    headers: Headers,
+    body: Option<Body>,
+}
+
+impl RequestBuilder<'_> {
+    /// Add a header. `Host` is set from the URL, and the body's framing headers from the
+    /// body, unless they're set here.
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Send a body. Buffered, seekable and sized bodies are sent with a
+    /// `Content-Length`, streaming ones with chunked transfer coding.
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Send the request, following redirects, and read the head of the response. The body
+    /// is streamed from the connection as it's read.
+    ///
+    /// `303 See Other`, and `301` or `302` after a `POST`, are followed with a `GET`
+    /// without the body. `307` and `308` repeat the request, unless its body was streamed
+    /// and can't be sent again: the redirect is returned then. Credentials and cookies
+    /// aren't sent to other servers.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-15.4>
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the URL isn't an absolute `http://` URL, the server can't
+    /// be reached, is too slow or sends an invalid response, or redirects too many times.
+    pub fn send(self) -> Result<Response, Error> {
+        let RequestBuilder {
+            client,
+            mut method,
+            url,
+            mut headers,
+            mut body,
+        } = self;
+        let mut url = parse_url(&url)?;
+        let mut redirects = 0;
+        loop {
+            let response = client.send(&method, &url, &headers, body.as_mut())?;
+            let location = match response.status.code() {
+                301 | 302 | 303 | 307 | 308 if client.max_redirects > 0 => {
+                    response.headers.get("Location")
+                }
+                _ => None,
+            };
+            let Some(location) = location else {
+                return Ok(response);
+            };
+            if redirects == client.max_redirects {
+                return Err(Error::TooManyRedirects);
+            }
+            let next = resolve(&url, location)?;
+
+            let get = match response.status.code() {
+                303 => method != Method::Head,
+                301 | 302 => method == Method::Post,
+                _ => false,
+            };
+            if get {
+                method = Method::Get;
+                body = None;
+                for name in ["Content-Length", "Content-Type", "Transfer-Encoding"] {
+                    headers.remove(name);
+                }
+            } else if body
+                .as_ref()
+                .is_some_and(|body| !matches!(body, Body::Bytes(_)))
+            {
+                return Ok(response);
+            }
+            if next.authority() != url.authority() {
+                for name in ["Authorization", "Cookie", "Proxy-Authorization"] {
+                    headers.remove(name);
+                }
+            }
+
+            discard(response.body);
+            url = next;
+            redirects += 1;
+        }
+    }
+}
+
+impl Client {
+    /// Send a single request to `url`, an absolute `http://` URL.
+    fn send(
+        &self,
+        method: &Method,
+        url: &Uri,
+        headers: &Headers,
+        body: Option<&mut Body>,
+    ) -> Result<Response, Error> {
+        let authority = url.authority().unwrap_or_default();
+        let addr = if authority.ends_with(']') || !authority.contains(':') {
+            format!("{authority}:80")
+        } else {
+            authority.to_string()
+        };
+
+        let mut headers = headers.clone();
+        if !headers.contains("Host") {
+            headers.insert("Host", authority);
+        }
+        let mut reader = body.map(|body| -> (Box<dyn Read + '_>, Option<u64>) {
+            match body {
+                Body::Bytes(bytes) => {
+                    let length = bytes.len() as u64;
+                    (Box::new(bytes.as_slice()), Some(length))
+                }
+                Body::Stream(reader) => (Box::new(reader), None),
+                Body::Seekable { reader, length } => {
+                    (Box::new(Read::take(reader, *length)), Some(*length))
+                }
+                Body::Sized { reader, length } => {
+                    (Box::new(Read::take(reader, *length)), Some(*length))
+                }
+            }
+        });
+        let chunked = match &reader {
+            Some((_, Some(length))) => {
+                if !headers.contains("Content-Length") {
+                    headers.insert("Content-Length", length.to_string());
+                }
+                false
+            }
+            Some((_, None)) if !headers.contains("Content-Length") => {
+                headers.insert("Transfer-Encoding", "chunked");
+                true
+            }
+            _ => false,
+        };
+
+        let target = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        let head = format!("{method} {target} HTTP/1.1\r\n{headers}\r\n").into_bytes();
+        let body = reader.as_mut().map(|(reader, _)| Outgoing {
+            reader: reader.as_mut(),
+            chunked,
+        });
+        connection::send(
+            &self.pool,
+            &self.config,
+            &addr,
+            &head,
+            body,
+            *method == Method::Head,
+        )
+    }
+}
+
+/// Parse an absolute `http://` URL.
+fn parse_url(url: &str) -> Result<Uri, Error> {
+    let uri = Uri::parse(url).map_err(|_| Error::InvalidUrl("Malformed URL"))?;
+    if uri.form() != TargetForm::Absolute || uri.scheme() != Some("http") {
+        return Err(Error::InvalidUrl(
+            "Only absolute http:// URLs are supported",
+        ));
+    }
+    if uri
+        .authority()
+        .is_some_and(|authority| authority.contains('@'))
+    {
+        return Err(Error::InvalidUrl("Credentials in URLs aren't supported"));
+    }
+    Ok(uri)
+}
+
+/// Resolve the `Location` of a redirect against the URL that was requested.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc3986#section-5.2>
+fn resolve(base: &Uri, location: &str) -> Result<Uri, Error> {
+    // The fragment is the client's business.
+    let location = location
+        .split_once('#')
+        .map_or(location, |(location, _)| location);
+    let authority = base.authority().unwrap_or_default();
+
+    let has_scheme = location.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+    });
+    let url = if has_scheme {
+        location.to_string()
+    } else if location.starts_with("//") {
+        format!("http:{location}")
+    } else {
+        let (path, query) = match location.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (location, None),
+        };
+        let (path, query) = if path.is_empty() {
+            (base.path().to_string(), query.or(base.query()))
+        } else if path.starts_with('/') {
+            (path.to_string(), query)
+        } else {
+            let directory = base.path().rfind('/').map_or("/", |i| &base.path()[..=i]);
+            (format!("{directory}{path}"), query)
+        };
+        let query = query.map(|query| format!("?{query}")).unwrap_or_default();
+        format!("http://{authority}{}{query}", remove_dot_segments(&path))
+    };
+    parse_url(&url)
+}
+
+/// Resolve the `.` and `..` segments of an absolute path.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc3986#section-5.2.4>
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut directory = false;
+    for segment in path.split('/').skip(1) {
+        directory = matches!(segment, "." | "..");
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let mut path = format!("/{}", segments.join("/"));
+    if directory && !segments.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+/// Read the rest of a small body, so that its connection can be reused.
+fn discard(body: Body) {
+    if body
+        .content_length()
+        .is_some_and(|length| length <= MAX_DISCARDED_BODY)
+    {
+        let _ = body.into_bytes();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{parse_url, remove_dot_segments, resolve, Client, Error};
+    use crate::request::Request;
+    use crate::response::{Body, Headers, Response, Status};
+    use crate::server::{Handler, Server};
+    use std::fmt::Write as _;
+    use std::io::{BufRead, BufReader, Cursor, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Serve `handler` on a free port, returning its base URL.
+    fn serve<H: Handler + 'static>(handler: H) -> String {
+        let server = Server::new("127.0.0.1:0", handler);
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.listen());
+        format!("http://{addr}")
+    }
+
+    fn echo(request: &mut Request) -> String {
+        let mut echo = format!("{} {}", request.method, request.uri());
+        for name in ["Content-Length", "Transfer-Encoding", "Authorization"] {
+            if let Some(value) = request.header(name) {
+                write!(echo, " {name}={value}").unwrap();
+            }
+        }
+        let mut body = String::new();
+        request.body_reader().read_to_string(&mut body).unwrap();
+        format!("{echo} {body}")
+    }
+
+    #[test]
+    fn it_sends_requests_and_streams_responses() {
+        let url = serve(|request: &mut Request| {
+            let echo = echo(request);
+            Response::new(
+                Status::Ok,
+                Headers::default(),
+                Body::stream(Cursor::new(echo)),
+            )
+        });
+        let client = Client::new();
+
+        let response = client
+            .post(&format!("{url}/a?b"))
+            .body(Body::stream(Cursor::new("hello")))
+            .send()
+            .unwrap();
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(Some("chunked"), response.headers.get("Transfer-Encoding"));
+        assert_eq!(
+            b"POST /a?b Transfer-Encoding=chunked hello".to_vec(),
+            response.body.into_bytes().unwrap()
+        );
+
+        let response = client.put(&url).body("hello").send().unwrap();
+        assert_eq!(
+            b"PUT / Content-Length=5 hello".to_vec(),
+            response.body.into_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn it_follows_redirects() {
+        let url = serve(|request: &mut Request| {
+            let (status, location) = match request.path() {
+                "/see-other" => (Status::SeeOther, "/echo"),
+                "/temporary" => (Status::TemporaryRedirect, "echo"),
+                "/loop" => (Status::Found, "/loop"),
+                _ => return Response::new(Status::Ok, Headers::default(), echo(request)),
+            };
+            let headers = Headers::new(&format!("Location: {location}"));
+            Response::new(status, headers, "Redirecting")
+        });
+        let client = Client::new().max_redirects(3);
+
+        for (path, body, expected) in [
+            ("/see-other", Body::from("x"), "GET /echo Authorization=a "),
+            (
+                "/temporary",
+                Body::from("x"),
+                "POST /echo Content-Length=1 Authorization=a x",
+            ),
+        ] {
+            let response = client
+                .post(&format!("{url}{path}"))
+                .header("Authorization", "a")
+                .body(body)
+                .send()
+                .unwrap();
+            assert_eq!(Status::Ok, response.status);
+            assert_eq!(expected.as_bytes(), response.body.into_bytes().unwrap());
+        }
+
+        // A streamed body can't be sent twice.
+        let response = client
+            .post(&format!("{url}/temporary"))
+            .body(Body::stream(Cursor::new("x")))
+            .send()
+            .unwrap();
+        assert_eq!(Status::TemporaryRedirect, response.status);
+
+        let loops = client.get(&format!("{url}/loop")).send();
+        assert!(matches!(loops, Err(Error::TooManyRedirects)));
+        let response = Client::new()
+            .max_redirects(0)
+            .get(&format!("{url}/loop"))
+            .send()
+            .unwrap();
+        assert_eq!(Status::Found, response.status);
+    }
+
+    #[test]
+    fn it_reads_every_body_framing_over_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // Serves three requests on a single connection, closing it after the last one.
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            for response in [
+                "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none",
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\ntw\r\n1\r\no\r\n0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nthree",
+            ] {
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                (&stream).write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let client = Client::new().timeout(Duration::from_millis(500));
+        for expected in ["one", "two", "three"] {
+            let response = client.get(&url).send().unwrap();
+            assert_eq!(expected.as_bytes(), response.body.into_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    fn it_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let client = Client::new().timeout(Duration::from_millis(100));
+        assert!(matches!(client.get(&url).send(), Err(Error::Timeout)));
+        assert!(matches!(
+            client.get("https://example.com/").send(),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn it_resolves_redirect_locations() {
+        let base = parse_url("http://a.test/b/c?d").unwrap();
+        for (location, expected) in [
+            ("e", "http://a.test/b/e"),
+            ("../e?f", "http://a.test/e?f"),
+            ("/e#g", "http://a.test/e"),
+            ("?f", "http://a.test/b/c?f"),
+            ("", "http://a.test/b/c?d"),
+            ("//other.test/x", "http://other.test/x"),
+            ("http://other.test", "http://other.test/"),
+        ] {
+            let url = resolve(&base, location).unwrap();
+            assert_eq!(expected, url.to_string(), "{location}");
+        }
+        assert!(resolve(&base, "https://a.test/").is_err());
+        assert_eq!("/a/", remove_dot_segments("/a/./b/../c/.."));
+        assert_eq!("/", remove_dot_segments("/.."));
+    }
+}
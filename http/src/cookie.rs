@@ -0,0 +1,567 @@
+use crate::date;
+use crate::request::is_tchar;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::time::{Duration, SystemTime};
+
+/// The cookies a client sent in its `Cookie` header.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6265#section-5.4>
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Cookies {
+    cookies: HashMap<String, String>,
+}
+
+impl Cookies {
+    /// Parse a `Cookie` header value, such as `a=1; b="2"`.
+    ///
+    /// Pairs without a name are ignored. When a name is repeated the first value wins,
+    /// since clients send the cookie with the most specific path first.
+    #[must_use]
+    pub fn parse(header: &str) -> Self {
+        let mut cookies = HashMap::new();
+        for pair in header.split(';') {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            cookies
+                .entry(name.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+        Cookies { cookies }
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.cookies.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+}
+
+/// The `SameSite` attribute.
+/// Draft: <https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-4.1.2.7>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    /// Sent on cross-site requests too, browsers require `Secure` along with it.
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            }
+        )
+    }
+}
+
+/// A `Set-Cookie` header value.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6265#section-4.1>
+///
+/// Bytes of the name that aren't token characters, and of the value that aren't
+/// cookie-octets (whitespace, controls, `"`, `,`, `;` and `\`), are percent-encoded, so
+/// neither can add attributes or headers. So is `%`, so that decoding gives back exactly
+/// what was set, e.g. with [`percent_decode`](crate::request::uri::percent_decode).
+///
+/// ```
+/// use http::cookie::{SameSite, SetCookie};
+/// use http::response::Headers;
+/// use std::time::Duration;
+///
+/// let cookie = SetCookie::new("theme", "dark")
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .http_only(true)
+///     .same_site(SameSite::Lax);
+/// assert_eq!(
+///     "theme=dark; Path=/; Max-Age=3600; HttpOnly; SameSite=Lax",
+///     cookie.to_string()
+/// );
+///
+/// let mut headers = Headers::default();
+/// headers.append("Set-Cookie", cookie.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl SetCookie {
+    #[must_use]
+    pub fn new(name: &str, value: &str) -> Self {
+        SetCookie {
+            name: percent_encode(name, is_tchar),
+            value: percent_encode(value, is_cookie_octet),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// A cookie that makes the client delete the cookie `name`.
+    ///
+    /// The path and domain must match the ones the cookie was set with.
+    #[must_use]
+    pub fn removal(name: &str) -> Self {
+        SetCookie::new(name, "")
+            .max_age(Duration::ZERO)
+            .expires(SystemTime::UNIX_EPOCH)
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if `path` has a `;` or a control character.
+    #[must_use]
+    pub fn path(mut self, path: &str) -> Self {
+        assert!(is_attribute_value(path), "Invalid cookie path {path:?}");
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if `domain` has a `;` or a control character.
+    #[must_use]
+    pub fn domain(mut self, domain: &str) -> Self {
+        assert!(
+            is_attribute_value(domain),
+            "Invalid cookie domain {domain:?}"
+        );
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// How long the cookie lives, in whole seconds. Takes precedence over `Expires`.
+    #[must_use]
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    #[must_use]
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    #[must_use]
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    #[must_use]
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    #[must_use]
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    #[cfg(any(feature = "signed-cookies", feature = "private-cookies"))]
+    fn with_value(mut self, value: String) -> Self {
+        self.value = value;
+        self
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", date::format(expires))?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={same_site}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `true` if `byte` can be part of a cookie value unquoted.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6265#section-4.1.1>
+fn is_cookie_octet(byte: u8) -> bool {
+    matches!(byte, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+/// Returns `true` if `value` can be the value of an attribute, such as `Path`.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6265#section-4.1.1>
+pub(crate) fn is_attribute_value(value: &str) -> bool {
+    !value.chars().any(|c| c == ';' || c.is_control())
+}
+
+/// Encode `%` and the bytes of `input` that aren't `allowed` as `%XX`.
+fn percent_encode(input: &str, allowed: fn(u8) -> bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for &byte in input.as_bytes() {
+        if byte != b'%' && allowed(byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Signs cookie values with HMAC-SHA256 so tampering can be detected. Values stay
+/// readable by the client.
+///
+/// The signature covers the cookie name too, so a value signed for one cookie is rejected
+/// for another.
+#[cfg(feature = "signed-cookies")]
+pub struct SignedJar {
+    key: Vec<u8>,
+}
+
+#[cfg(feature = "signed-cookies")]
+impl SignedJar {
+    /// Length of the base64 encoded signature prefixed to values.
+    const SIGNATURE_LENGTH: usize = 43;
+
+    /// `key` should be at least 32 random bytes, kept secret.
+    #[must_use]
+    pub fn new(key: &[u8]) -> Self {
+        SignedJar { key: key.to_vec() }
+    }
+
+    fn mac(&self, name: &str, value: &str) -> hmac::Hmac<sha2::Sha256> {
+        use hmac::Mac;
+
+        #[expect(clippy::expect_used)]
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(&self.key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    /// Prefix the cookie's value with its signature.
+    #[must_use]
+    pub fn sign(&self, cookie: SetCookie) -> SetCookie {
+        use base64::Engine;
+        use hmac::Mac;
+
+        let signature = self
+            .mac(&cookie.name, &cookie.value)
+            .finalize()
+            .into_bytes();
+        let value = format!(
+            "{}{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(signature),
+            cookie.value
+        );
+        cookie.with_value(value)
+    }
+
+    /// Returns the original value of a signed cookie, or `None` if it was tampered with.
+    #[must_use]
+    pub fn verify(&self, name: &str, value: &str) -> Option<String> {
+        use base64::Engine;
+        use hmac::Mac;
+
+        if !value.is_char_boundary(Self::SIGNATURE_LENGTH) {
+            return None;
+        }
+        let (signature, value) = value.split_at(Self::SIGNATURE_LENGTH);
+        let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(signature)
+            .ok()?;
+        self.mac(name, value)
+            .verify_slice(&signature)
+            .ok()
+            .map(|()| value.to_string())
+    }
+
+    /// Returns the verified value of the cookie `name`.
+    #[must_use]
+    pub fn get(&self, cookies: &Cookies, name: &str) -> Option<String> {
+        self.verify(name, cookies.get(name)?)
+    }
+}
+
+/// Encrypts cookie values with AES-256-GCM, so the client can neither read nor tamper
+/// with them.
+///
+/// The cookie name is authenticated along with the value, so a value encrypted for one
+/// cookie is rejected for another.
+#[cfg(feature = "private-cookies")]
+pub struct PrivateJar {
+    cipher: aes_gcm::Aes256Gcm,
+}
+
+#[cfg(feature = "private-cookies")]
+impl PrivateJar {
+    const NONCE_LENGTH: usize = 12;
+
+    /// `key` must be random and kept secret.
+    #[must_use]
+    pub fn new(key: &[u8; 32]) -> Self {
+        use aes_gcm::KeyInit;
+
+        PrivateJar {
+            cipher: aes_gcm::Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// Replace the cookie's value with its encryption.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the system's random number generator fails.
+    #[must_use]
+    pub fn encrypt(&self, cookie: SetCookie) -> SetCookie {
+        use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+        use base64::Engine;
+
+        let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+        #[expect(clippy::expect_used)]
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: cookie.value.as_bytes(),
+                    aad: cookie.name.as_bytes(),
+                },
+            )
+            .expect("Encrypting a cookie-sized value can't fail");
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        let value = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(sealed);
+        cookie.with_value(value)
+    }
+
+    /// Returns the original value of an encrypted cookie, or `None` if it was tampered
+    /// with.
+    #[must_use]
+    pub fn decrypt(&self, name: &str, value: &str) -> Option<String> {
+        use aes_gcm::aead::{Aead, Payload};
+        use base64::Engine;
+
+        let sealed = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(value)
+            .ok()?;
+        if sealed.len() < Self::NONCE_LENGTH {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(Self::NONCE_LENGTH);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad: name.as_bytes(),
+                },
+            )
+            .ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    /// Returns the decrypted value of the cookie `name`.
+    #[must_use]
+    pub fn get(&self, cookies: &Cookies, name: &str) -> Option<String> {
+        self.decrypt(name, cookies.get(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::duration_suboptimal_units)]
+    #![allow(clippy::unwrap_used)]
+    use super::{Cookies, SameSite, SetCookie};
+    use crate::request::uri::percent_decode;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_parses_cookies() {
+        let cookies = Cookies::parse("a=1; b=\"quoted\";c=; =nameless; novalue; a=2");
+
+        assert_eq!(3, cookies.len());
+        assert_eq!(Some("1"), cookies.get("a"));
+        assert_eq!(Some("quoted"), cookies.get("b"));
+        assert_eq!(Some(""), cookies.get("c"));
+        assert_eq!(None, cookies.get("novalue"));
+        assert!(Cookies::parse("").is_empty());
+    }
+
+    #[test]
+    fn it_builds_set_cookie_headers() {
+        let cookie = SetCookie::new("id", "a3fWa")
+            .path("/docs")
+            .domain("example.com")
+            .max_age(Duration::from_mins(1))
+            .expires(UNIX_EPOCH + Duration::from_secs(1_445_412_480))
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Strict);
+
+        assert_eq!(
+            "id=a3fWa; Path=/docs; Domain=example.com; Max-Age=60; \
+            Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure; HttpOnly; SameSite=Strict",
+            cookie.to_string()
+        );
+        assert_eq!(
+            "id=; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            SetCookie::removal("id").to_string()
+        );
+    }
+
+    #[test]
+    fn it_encodes_names_and_values_that_could_inject_attributes() {
+        let cookie =
+            SetCookie::new("a b;", "1; Domain=evil.example\r\nX-Injected: \"1\"");
+        assert_eq!(
+            "a%20b%3B=1%3B%20Domain=evil.example%0D%0AX-Injected:%20%221%22",
+            cookie.to_string()
+        );
+    }
+
+    #[test]
+    fn it_encodes_percent_signs_so_values_round_trip() {
+        assert_eq!("100%25", SetCookie::new("n", "100%").value());
+        assert_eq!("a%25b", SetCookie::new("a%b", "1").name());
+        for value in ["100%", "%41", "%zz", "50% off; today"] {
+            let encoded = SetCookie::new("n", value).value().to_string();
+            assert_eq!(value.as_bytes(), percent_decode(&encoded).unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic = "Invalid cookie path"]
+    fn it_rejects_paths_that_could_inject_attributes() {
+        let _ = SetCookie::new("id", "1").path("/; Domain=evil.example");
+    }
+
+    #[test]
+    #[should_panic = "Invalid cookie domain"]
+    fn it_rejects_domains_that_could_inject_headers() {
+        let _ = SetCookie::new("id", "1").domain("example.com\r\nX-Injected: 1");
+    }
+
+    #[cfg(feature = "signed-cookies")]
+    #[test]
+    fn it_detects_tampered_signed_cookies() {
+        use super::SignedJar;
+
+        let jar = SignedJar::new(&[7; 32]);
+        let signed = jar.sign(SetCookie::new("user", "42"));
+        assert_ne!("42", signed.value());
+
+        let cookies = Cookies::parse(&signed.to_string());
+        assert_eq!(Some("42".to_string()), jar.get(&cookies, "user"));
+
+        let tampered = signed.value().replace("42", "43");
+        assert_eq!(None, jar.verify("user", &tampered));
+        assert_eq!(None, jar.verify("admin", signed.value()));
+        assert_eq!(
+            None,
+            SignedJar::new(&[8; 32]).verify("user", signed.value())
+        );
+        assert_eq!(None, jar.verify("user", "short"));
+    }
+
+    #[cfg(feature = "private-cookies")]
+    #[test]
+    fn it_detects_tampered_private_cookies() {
+        use super::PrivateJar;
+
+        let jar = PrivateJar::new(&[7; 32]);
+        let encrypted = jar.encrypt(SetCookie::new("user", "42"));
+        assert_ne!("42", encrypted.value());
+        assert_eq!(
+            Some("42".to_string()),
+            jar.decrypt("user", encrypted.value())
+        );
+        assert_eq!(
+            None,
+            PrivateJar::new(&[8; 32]).decrypt("user", encrypted.value())
+        );
+        // Every encryption has a fresh nonce.
+        assert_ne!(
+            encrypted.value(),
+            jar.encrypt(SetCookie::new("user", "42")).value()
+        );
+
+        let cookies = Cookies::parse(&encrypted.to_string());
+        assert_eq!(Some("42".to_string()), jar.get(&cookies, "user"));
+
+        let mut tampered = encrypted.value().to_string();
+        let last = if tampered.ends_with('A') { "B" } else { "A" };
+        tampered.replace_range(tampered.len() - 1.., last);
+        assert_eq!(None, jar.decrypt("user", &tampered));
+        assert_eq!(None, jar.decrypt("admin", encrypted.value()));
+        assert_eq!(None, jar.decrypt("user", "AAAA"));
+    }
+}
@@ -0,0 +1,211 @@
+//! HTTP-dates, as found in `Date`, `Last-Modified` or `If-Modified-Since` headers.
+//!
+//! ```
+//! use http::date;
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+//! assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT", date::format(time));
+//! assert_eq!(Some(time), date::parse("Sunday, 06-Nov-94 08:49:37 GMT"));
+//! ```
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format `time` as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+///
+/// Times before the epoch are formatted as the epoch.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-5.6.7>
+#[must_use]
+pub fn format(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let days = seconds / 86400;
+    let (year, month, day) = civil_from_days(days);
+    let seconds = seconds % 86400;
+    // The epoch was a Thursday.
+    let weekday = DAYS[(days % 7) as usize];
+    #[expect(clippy::cast_possible_truncation)]
+    let month = MONTHS[month as usize - 1];
+
+    format!(
+        "{weekday}, {day:02} {month} {year} {hour:02}:{minute:02}:{second:02} GMT",
+        hour = seconds / 3600,
+        minute = seconds % 3600 / 60,
+        second = seconds % 60,
+    )
+}
+
+/// The current time as an IMF-fixdate, formatted at most once a second.
+#[must_use]
+pub fn now() -> String {
+    static CACHE: Mutex<(u64, String)> = Mutex::new((u64::MAX, String::new()));
+    let now = SystemTime::now();
+    let second = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    if cache.0 != second {
+        *cache = (second, format(now));
+    }
+    cache.1.clone()
+}
+
+/// Parse an HTTP-date in any of the three formats recipients must accept: IMF-fixdate,
+/// the obsolete RFC 850 format and ANSI C's `asctime()` format.
+///
+/// Returns `None` for invalid dates and dates before the epoch.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-5.6.7>
+#[must_use]
+pub fn parse(input: &str) -> Option<SystemTime> {
+    let input = input.trim();
+    let (day, month, year, time) = match input.split_once(", ") {
+        // `Sun, 06 Nov 1994 08:49:37 GMT` or `Sunday, 06-Nov-94 08:49:37 GMT`
+        Some((_, rest)) => match *rest.split([' ', '-']).collect::<Vec<_>>() {
+            [day, month, year, time, "GMT"] => (day, month, year, time),
+            _ => return None,
+        },
+        // `Sun Nov  6 08:49:37 1994`
+        None => match *input.split_whitespace().collect::<Vec<_>>() {
+            [_, month, day, time, year] => (day, month, year, time),
+            _ => return None,
+        },
+    };
+
+    let year: u64 = match year.len() {
+        4 => year.parse().ok()?,
+        // Two digit years are in the past century if they'd be too far in the future.
+        2 => match year.parse::<u64>().ok()? {
+            year @ 0..70 => 2000 + year,
+            year => 1900 + year,
+        },
+        _ => return None,
+    };
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let day: u64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let (hour, minute, second) = match *time.split(':').collect::<Vec<_>>() {
+        [hour, minute, second] if [hour, minute, second].iter().all(|t| t.len() == 2) => {
+            (
+                hour.parse::<u64>().ok().filter(|hour| *hour < 24)?,
+                minute.parse::<u64>().ok().filter(|minute| *minute < 60)?,
+                // 60 is a leap second.
+                second.parse::<u64>().ok().filter(|second| *second <= 60)?,
+            )
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day)?;
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Convert days since the epoch to a `(year, month, day)` date in the proleptic Gregorian
+/// calendar.
+/// Algorithm: <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Convert a date in the proleptic Gregorian calendar to days since the epoch, `None` if
+/// it's before the epoch.
+/// Algorithm: <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era).checked_sub(719_468)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::duration_suboptimal_units)]
+    use super::{format, now, parse};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn it_formats_imf_fixdates() {
+        assert_eq!("Thu, 01 Jan 1970 00:00:00 GMT", format(UNIX_EPOCH));
+        assert_eq!(
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            format(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(
+            "Tue, 29 Feb 2000 23:59:59 GMT",
+            format(UNIX_EPOCH + Duration::from_secs(951_868_799))
+        );
+        assert_eq!(
+            "Sat, 01 Jan 2000 00:00:00 GMT",
+            format(UNIX_EPOCH + Duration::from_secs(946_684_800))
+        );
+    }
+
+    #[test]
+    fn it_formats_the_current_time() {
+        let before = format(SystemTime::now());
+        let now = now();
+        let after = format(SystemTime::now());
+        assert!(now == before || now == after, "{now}");
+        assert!(parse(&now).is_some());
+    }
+
+    #[test]
+    fn it_parses_all_http_date_formats() {
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(784_111_777));
+        assert_eq!(expected, parse("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(expected, parse("Sunday, 06-Nov-94 08:49:37 GMT"));
+        assert_eq!(expected, parse("Sun Nov  6 08:49:37 1994"));
+
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(951_868_799)),
+            parse("Tue, 29 Feb 2000 23:59:59 GMT")
+        );
+        let now = UNIX_EPOCH + Duration::from_secs(1_760_000_000);
+        assert_eq!(Some(now), parse(&format(now)));
+    }
+
+    #[test]
+    fn it_rejects_invalid_dates() {
+        for date in [
+            "",
+            "Sun, 06 Nov 1994 08:49:37",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "Sun, 32 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 8:49:37 GMT",
+            "Wed, 31 Dec 1969 23:59:59 GMT",
+        ] {
+            assert_eq!(None, parse(date), "{date}");
+        }
+    }
+}
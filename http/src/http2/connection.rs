@@ -0,0 +1,841 @@
+use super::frame::{self, Error, ErrorCode, Frame, Setting};
+use super::hpack::{DecodeError, Decoder, Encoder, DEFAULT_TABLE_SIZE};
+use super::Upgrade;
+use crate::middleware::{Middleware, Next};
+use crate::request::body::{BodyDecoder, Chunk};
+use crate::request::uri::{TargetForm, Uri};
+use crate::request::{is_token, HttpVersion, Method, Request, TlsInfo};
+use crate::response::{Body, Response, Status};
+use crate::server::Handler;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{self, BufReader, Cursor, Read, Write};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, Scope};
+
+/// The most streams a client may have open at once. Each one has a thread of its own,
+/// see [`Server::http2`](crate::server::Server::http2), so it's kept low.
+const MAX_CONCURRENT_STREAMS: u32 = 16;
+
+/// The receive window of every stream, how much of a request body is buffered until the
+/// handler reads it.
+const INITIAL_WINDOW_SIZE: u32 = 256 * 1024;
+
+/// The largest header list of a request, counted like HPACK table entries. Larger
+/// requests are answered with `431 Request Header Fields Too Large`.
+const MAX_HEADER_LIST_SIZE: u32 = 64 * 1024;
+
+/// Headers that only make sense for a single HTTP/1 connection.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-8.2.2>
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+type BodySender = mpsc::Sender<Result<Vec<u8>, &'static str>>;
+
+/// What the reader and the stream threads of a connection share.
+struct Shared<W> {
+    state: Mutex<State<W>>,
+    /// Notified when windows grow, streams end or the connection closes.
+    changed: Condvar,
+}
+
+struct State<W> {
+    writer: W,
+    encoder: Encoder,
+    /// The connection's send window.
+    window: i64,
+    /// The send window new streams start with, from the client's settings.
+    initial_window: i64,
+    max_frame_size: u32,
+    streams: HashMap<u32, Stream>,
+    /// Nothing can be sent any more, after a `GOAWAY` or a failed write.
+    closed: bool,
+    /// The client won't send anything more, so windows won't grow.
+    eof: bool,
+}
+
+struct Stream {
+    send_window: i64,
+    recv_window: i64,
+    /// Feeds the request body, until the client ends the stream.
+    body: Option<BodySender>,
+    reset: bool,
+}
+
+/// The pseudo-headers and headers of a request.
+struct Parts {
+    method: Method,
+    uri: Uri,
+    headers: HashMap<String, String>,
+}
+
+impl<W: Write> State<W> {
+    fn send(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        frame.write(&mut self.writer).inspect_err(|_| {
+            self.closed = true;
+        })
+    }
+
+    /// End a stream with `RST_STREAM`, failing its request body if it's still being read.
+    fn reset(&mut self, id: u32, code: ErrorCode) -> io::Result<()> {
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.reset = true;
+            if let Some(body) = stream.body.take() {
+                let _ = body.send(Err("Stream was reset"));
+            }
+        }
+        self.send(&Frame::RstStream { stream: id, code })
+    }
+
+    fn apply(&mut self, settings: &[Setting]) -> Result<(), Error> {
+        for setting in settings {
+            match *setting {
+                Setting::HeaderTableSize(size) => {
+                    self.encoder.set_max_table_size(size as usize);
+                }
+                Setting::InitialWindowSize(size) => {
+                    // RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-6.9.2>
+                    let delta = i64::from(size) - self.initial_window;
+                    self.initial_window = i64::from(size);
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                        if stream.send_window > i64::from(frame::MAX_WINDOW_SIZE) {
+                            return Err(Error::Connection(ErrorCode::FlowControlError));
+                        }
+                    }
+                }
+                Setting::MaxFrameSize(size) => self.max_frame_size = size,
+                Setting::EnablePush(_)
+                | Setting::MaxConcurrentStreams(_)
+                | Setting::MaxHeaderListSize(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Shared<W> {
+    fn lock(&self) -> MutexGuard<'_, State<W>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Send the response of stream `id`, waiting for flow-control windows as needed.
+    fn respond(&self, id: u32, mut response: Response, head: bool) -> io::Result<()> {
+        let may_have_body = response.may_have_body();
+        if may_have_body {
+            match response.body.content_length() {
+                Some(length) => response
+                    .headers
+                    .insert("Content-Length", length.to_string()),
+                None => {
+                    response.headers.remove("Content-Length");
+                }
+            }
+        } else {
+            response.remove_framing();
+        }
+        let status = response.status.code().to_string();
+        let mut fields = vec![(":status".to_string(), status)];
+        for (name, value) in response.headers.iter() {
+            let name = name.to_ascii_lowercase();
+            if !CONNECTION_HEADERS.contains(&name.as_str()) {
+                fields.push((name, value.to_string()));
+            }
+        }
+
+        let (mut reader, mut remaining): (Box<dyn Read + Send>, Option<u64>) =
+            match response.body {
+                _ if head || !may_have_body => (Box::new(io::empty()), Some(0)),
+                Body::Bytes(bytes) => {
+                    let length = bytes.len() as u64;
+                    (Box::new(Cursor::new(bytes)), Some(length))
+                }
+                Body::Stream(reader) => (reader, None),
+                Body::Seekable { reader, length } => {
+                    (Box::new(Read::take(reader, length)), Some(length))
+                }
+                Body::Sized { reader, length } => {
+                    (Box::new(Read::take(reader, length)), Some(length))
+                }
+            };
+
+        {
+            let mut state = self.lock();
+            if state.streams.get(&id).is_none_or(|stream| stream.reset) {
+                return Ok(());
+            }
+            let block = state.encoder.encode(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            );
+            let fragments = block
+                .chunks(state.max_frame_size as usize)
+                .collect::<Vec<_>>();
+            for (i, fragment) in fragments.iter().enumerate() {
+                let end_headers = i == fragments.len() - 1;
+                let frame = if i == 0 {
+                    Frame::Headers {
+                        stream: id,
+                        block: fragment.to_vec(),
+                        end_stream: remaining == Some(0),
+                        end_headers,
+                    }
+                } else {
+                    Frame::Continuation {
+                        stream: id,
+                        block: fragment.to_vec(),
+                        end_headers,
+                    }
+                };
+                state.send(&frame)?;
+            }
+        }
+
+        let mut buf = vec![0; frame::DEFAULT_MAX_FRAME_SIZE as usize];
+        while remaining != Some(0) {
+            let read = match reader.read(&mut buf) {
+                Ok(0) if remaining.is_some() => Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Body ended before its length",
+                )),
+                Ok(read) => Ok(read),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let read = match read {
+                Ok(read) => read,
+                Err(e) => {
+                    self.lock().reset(id, ErrorCode::InternalError)?;
+                    return Err(e);
+                }
+            };
+            remaining = remaining.map(|remaining| remaining - read as u64);
+            let end = read == 0 || remaining == Some(0);
+            self.send_data(id, &buf[..read], end)?;
+            if end {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send `data` on stream `id` in as many frames as the windows allow.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-5.2>
+    fn send_data(&self, id: u32, mut data: &[u8], end_stream: bool) -> io::Result<()> {
+        let mut state = self.lock();
+        loop {
+            let window = match state.streams.get(&id) {
+                _ if state.closed => return Err(io::ErrorKind::BrokenPipe.into()),
+                Some(stream) if !stream.reset => stream.send_window.min(state.window),
+                _ => return Err(io::ErrorKind::ConnectionReset.into()),
+            };
+            if window <= 0 && !data.is_empty() {
+                if state.eof {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                state = self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+
+            // Windows are at most 2^31 - 1.
+            #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let length = data.len().min(window.max(0) as usize) as u32;
+            let (sent, rest) = data.split_at(length as usize);
+            state.window -= i64::from(length);
+            if let Some(stream) = state.streams.get_mut(&id) {
+                stream.send_window -= i64::from(length);
+            }
+            state.send(&Frame::Data {
+                stream: id,
+                data: sent.to_vec(),
+                end_stream: end_stream && rest.is_empty(),
+                length,
+            })?;
+            if rest.is_empty() {
+                return Ok(());
+            }
+            data = rest;
+        }
+    }
+
+    /// Give the client back the window of `length` bytes of stream `id` the handler read.
+    fn consumed(&self, id: u32, length: u32) {
+        let mut state = self.lock();
+        let open = state
+            .streams
+            .get_mut(&id)
+            .filter(|stream| stream.body.is_some() && length > 0);
+        if let Some(stream) = open {
+            stream.recv_window += i64::from(length);
+            let _ = state.send(&Frame::WindowUpdate {
+                stream: id,
+                increment: length,
+            });
+        }
+    }
+
+    /// Forget stream `id` once its response is sent, resetting it if the client is still
+    /// sending the request body.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-8.1>
+    fn finish(&self, id: u32) {
+        let mut state = self.lock();
+        if let Some(stream) = state.streams.remove(&id) {
+            if stream.body.is_some() && !stream.reset {
+                let _ = state.send(&Frame::RstStream {
+                    stream: id,
+                    code: ErrorCode::NoError,
+                });
+            }
+        }
+        self.changed.notify_all();
+    }
+}
+
+/// The request body of a stream, fed by the connection as `DATA` frames arrive.
+struct RequestBody<'s, W: Write> {
+    shared: &'s Shared<W>,
+    stream: u32,
+    data: mpsc::Receiver<Result<Vec<u8>, &'static str>>,
+}
+
+impl<W: Write> Iterator for RequestBody<'_, W> {
+    type Item = Result<Chunk, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.data.recv() {
+            Ok(Ok(data)) => {
+                // The data of a single frame, at most 16 KiB.
+                #[expect(clippy::cast_possible_truncation)]
+                self.shared.consumed(self.stream, data.len() as u32);
+                Some(Ok(Chunk {
+                    buf: data,
+                    extension: String::new(),
+                }))
+            }
+            Ok(Err(e)) => Some(Err(e)),
+            Err(_) => None,
+        }
+    }
+}
+
+impl<W: Write> BodyDecoder for RequestBody<'_, W> {}
+
+/// Reads the frames of a connection and starts a thread for every request.
+struct Connection<'s, W> {
+    shared: &'s Shared<W>,
+    handler: &'s dyn Handler,
+    middlewares: &'s [Arc<dyn Middleware>],
+    tls: Option<Arc<TlsInfo>>,
+    remote_addr: Option<SocketAddr>,
+    decoder: Decoder,
+    /// The highest stream the client opened.
+    last_stream: u32,
+}
+
+/// Serve an HTTP/2 connection until the client closes it. The client preface is read from
+/// `reader` first. `tls` is the connection requests were received on, if secure, and
+/// `remote_addr` the client's address.
+///
+/// Each request runs in its own thread, responses are interleaved as windows allow.
+pub(crate) fn serve<R: Read, W: Write + Send>(
+    handler: &dyn Handler,
+    middlewares: &[Arc<dyn Middleware>],
+    reader: R,
+    writer: W,
+    upgrade: Option<Upgrade>,
+    tls: Option<Arc<TlsInfo>>,
+    remote_addr: Option<SocketAddr>,
+) -> io::Result<()> {
+    let shared = Shared {
+        state: Mutex::new(State {
+            writer,
+            encoder: Encoder::new(),
+            window: i64::from(frame::DEFAULT_WINDOW_SIZE),
+            initial_window: i64::from(frame::DEFAULT_WINDOW_SIZE),
+            max_frame_size: frame::DEFAULT_MAX_FRAME_SIZE,
+            streams: HashMap::new(),
+            closed: false,
+            eof: false,
+        }),
+        changed: Condvar::new(),
+    };
+    let mut connection = Connection {
+        shared: &shared,
+        handler,
+        middlewares,
+        tls,
+        remote_addr,
+        decoder: Decoder::new(DEFAULT_TABLE_SIZE)
+            .max_list_size(MAX_HEADER_LIST_SIZE as usize),
+        last_stream: 0,
+    };
+
+    {
+        let mut state = shared.lock();
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-3.4>
+        state.send(&Frame::Settings {
+            ack: false,
+            settings: vec![
+                Setting::MaxConcurrentStreams(MAX_CONCURRENT_STREAMS),
+                Setting::InitialWindowSize(INITIAL_WINDOW_SIZE),
+                Setting::MaxHeaderListSize(MAX_HEADER_LIST_SIZE),
+            ],
+        })?;
+        if let Some(upgrade) = &upgrade {
+            if state.apply(&upgrade.settings).is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    let mut reader = BufReader::new(reader);
+    let mut preface = [0; frame::PREFACE.len()];
+    reader.read_exact(&mut preface)?;
+    if preface != *frame::PREFACE {
+        return shared.lock().send(&Frame::GoAway {
+            last_stream: 0,
+            code: ErrorCode::ProtocolError,
+        });
+    }
+
+    thread::scope(|scope| {
+        if let Some(upgrade) = upgrade {
+            // The upgraded request is stream 1, its body was the whole HTTP/1.1 request.
+            // RFC: <https://datatracker.ietf.org/doc/html/rfc7540#section-3.2>
+            connection.last_stream = 1;
+            let parts = Parts {
+                method: upgrade.method,
+                uri: upgrade.uri,
+                headers: upgrade.headers,
+            };
+            connection.open(scope, 1, Ok(parts), true);
+        }
+
+        let result = connection.run(scope, &mut reader);
+        let mut state = shared.lock();
+        if let Err(Error::Connection(code)) = result {
+            let _ = state.send(&Frame::GoAway {
+                last_stream: connection.last_stream,
+                code,
+            });
+            state.closed = true;
+        }
+        state.eof = true;
+        for stream in state.streams.values_mut() {
+            if let Some(body) = stream.body.take() {
+                let _ = body.send(Err("Connection closed"));
+            }
+        }
+        shared.changed.notify_all();
+    });
+    Ok(())
+}
+
+impl<'s, W: Write + Send> Connection<'s, W> {
+    /// Handle frames until the client closes the connection or breaks the protocol.
+    fn run<'scope, R: Read>(
+        &mut self,
+        scope: &'scope Scope<'scope, '_>,
+        reader: &mut R,
+    ) -> Result<(), Error>
+    where
+        's: 'scope,
+    {
+        // A header block being received, continued by `CONTINUATION` frames.
+        let mut headers: Option<(u32, Vec<u8>, bool)> = None;
+        let mut first = true;
+
+        loop {
+            let frame = match Frame::read(&mut *reader, frame::DEFAULT_MAX_FRAME_SIZE) {
+                Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                frame => frame?,
+            };
+            // RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-3.4>
+            if first && !matches!(frame, Frame::Settings { ack: false, .. }) {
+                return Err(Error::Connection(ErrorCode::ProtocolError));
+            }
+            first = false;
+
+            // RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-6.10>
+            match (&mut headers, frame) {
+                (
+                    Some((id, block, end_stream)),
+                    Frame::Continuation {
+                        stream,
+                        block: fragment,
+                        end_headers,
+                    },
+                ) if *id == stream => {
+                    block.extend(fragment);
+                    if block.len() > MAX_HEADER_LIST_SIZE as usize {
+                        return Err(Error::Connection(ErrorCode::EnhanceYourCalm));
+                    }
+                    if end_headers {
+                        let (id, block, end_stream) =
+                            (*id, std::mem::take(block), *end_stream);
+                        headers = None;
+                        self.headers(scope, id, &block, end_stream)?;
+                    }
+                }
+                (Some(_), _)
+                | (None, Frame::Continuation { .. } | Frame::PushPromise { .. }) => {
+                    return Err(Error::Connection(ErrorCode::ProtocolError));
+                }
+                (
+                    None,
+                    Frame::Headers {
+                        stream,
+                        block,
+                        end_stream,
+                        end_headers,
+                    },
+                ) => {
+                    if end_headers {
+                        self.headers(scope, stream, &block, end_stream)?;
+                    } else {
+                        headers = Some((stream, block, end_stream));
+                    }
+                }
+                (None, frame) => self.frame(frame)?,
+            }
+        }
+    }
+
+    /// Handle a frame other than the ones carrying header blocks.
+    fn frame(&mut self, frame: Frame) -> Result<(), Error> {
+        let mut state = self.shared.lock();
+        match frame {
+            Frame::Data {
+                stream: id,
+                data,
+                end_stream,
+                length,
+            } => {
+                // The connection window is given back right away, streams are limited by
+                // their own windows.
+                if length > 0 {
+                    state.send(&Frame::WindowUpdate {
+                        stream: 0,
+                        increment: length,
+                    })?;
+                }
+                let Some(stream) = state.streams.get_mut(&id) else {
+                    return self.idle(id);
+                };
+                let Some(body) = &stream.body else {
+                    state.reset(id, ErrorCode::StreamClosed)?;
+                    return Ok(());
+                };
+                if i64::from(length) > stream.recv_window {
+                    state.reset(id, ErrorCode::FlowControlError)?;
+                    return Ok(());
+                }
+                stream.recv_window -= i64::from(length);
+                if !data.is_empty() {
+                    let _ = body.send(Ok(data));
+                }
+                if end_stream {
+                    stream.body = None;
+                }
+            }
+            Frame::RstStream { stream: id, .. } => {
+                match state.streams.get_mut(&id) {
+                    Some(stream) => {
+                        stream.reset = true;
+                        if let Some(body) = stream.body.take() {
+                            let _ = body.send(Err("Stream was reset"));
+                        }
+                    }
+                    None => return self.idle(id),
+                }
+                self.shared.changed.notify_all();
+            }
+            Frame::WindowUpdate {
+                stream: 0,
+                increment,
+            } => {
+                state.window += i64::from(increment);
+                if increment == 0 || state.window > i64::from(frame::MAX_WINDOW_SIZE) {
+                    let code = if increment == 0 {
+                        ErrorCode::ProtocolError
+                    } else {
+                        ErrorCode::FlowControlError
+                    };
+                    return Err(Error::Connection(code));
+                }
+                self.shared.changed.notify_all();
+            }
+            Frame::WindowUpdate {
+                stream: id,
+                increment,
+            } => {
+                let Some(stream) = state.streams.get_mut(&id) else {
+                    return self.idle(id);
+                };
+                stream.send_window += i64::from(increment);
+                if increment == 0 {
+                    state.reset(id, ErrorCode::ProtocolError)?;
+                } else if stream.send_window > i64::from(frame::MAX_WINDOW_SIZE) {
+                    state.reset(id, ErrorCode::FlowControlError)?;
+                }
+                self.shared.changed.notify_all();
+            }
+            Frame::Settings {
+                ack: false,
+                settings,
+            } => {
+                state.apply(&settings)?;
+                state.send(&Frame::Settings {
+                    ack: true,
+                    settings: Vec::new(),
+                })?;
+                self.shared.changed.notify_all();
+            }
+            Frame::Ping { ack: false, data } => {
+                state.send(&Frame::Ping { ack: true, data })?;
+            }
+            // Priorities are advisory, and a client `GOAWAY` only means it won't open any
+            // more streams.
+            Frame::Settings { ack: true, .. }
+            | Frame::Ping { ack: true, .. }
+            | Frame::Priority { .. }
+            | Frame::GoAway { .. }
+            | Frame::Unknown { .. }
+            | Frame::Headers { .. }
+            | Frame::Continuation { .. }
+            | Frame::PushPromise { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Frames of streams that aren't open are ignored, unless the stream was never
+    /// opened.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-5.1>
+    fn idle(&self, id: u32) -> Result<(), Error> {
+        if id > self.last_stream {
+            Err(Error::Connection(ErrorCode::ProtocolError))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Handle a complete header block: a new request, or the trailers of an open one.
+    fn headers<'scope>(
+        &mut self,
+        scope: &'scope Scope<'scope, '_>,
+        id: u32,
+        block: &[u8],
+        end_stream: bool,
+    ) -> Result<(), Error>
+    where
+        's: 'scope,
+    {
+        // The block has to be decoded even if the stream is refused, to keep the table in
+        // sync.
+        let headers = match self.decoder.decode(block) {
+            Err(DecodeError::Compression(_)) => {
+                return Err(Error::Connection(ErrorCode::CompressionError));
+            }
+            Err(DecodeError::HeaderListTooLarge) => {
+                Err(Status::RequestHeaderFieldsTooLarge)
+            }
+            Ok(headers) => Ok(headers),
+        };
+        if id.is_multiple_of(2) {
+            return Err(Error::Connection(ErrorCode::ProtocolError));
+        }
+
+        if id <= self.last_stream {
+            // Trailers, which are dropped, must end the stream.
+            let mut state = self.shared.lock();
+            let open = state.streams.get(&id).map(|stream| stream.body.is_some());
+            match open {
+                Some(true) if end_stream => {
+                    if let Some(stream) = state.streams.get_mut(&id) {
+                        stream.body = None;
+                    }
+                }
+                Some(true) => state.reset(id, ErrorCode::ProtocolError)?,
+                Some(false) => state.reset(id, ErrorCode::StreamClosed)?,
+                None => {}
+            }
+            return Ok(());
+        }
+
+        self.last_stream = id;
+        {
+            let mut state = self.shared.lock();
+            if state.streams.len() >= MAX_CONCURRENT_STREAMS as usize {
+                state.send(&Frame::RstStream {
+                    stream: id,
+                    code: ErrorCode::RefusedStream,
+                })?;
+                return Ok(());
+            }
+        }
+        let parts =
+            headers.and_then(|headers| parts(headers).map_err(|_| Status::BadRequest));
+        self.open(scope, id, parts, end_stream);
+        Ok(())
+    }
+
+    /// Open stream `id` and handle its request in a new thread, or answer with `status`
+    /// if the request is invalid.
+    fn open<'scope>(
+        &self,
+        scope: &'scope Scope<'scope, '_>,
+        id: u32,
+        parts: Result<Parts, Status>,
+        end_stream: bool,
+    ) where
+        's: 'scope,
+    {
+        let (sender, receiver) = if end_stream {
+            (None, None)
+        } else {
+            let (sender, receiver) = mpsc::channel();
+            (Some(sender), Some(receiver))
+        };
+        {
+            let mut state = self.shared.lock();
+            let send_window = state.initial_window;
+            state.streams.insert(
+                id,
+                Stream {
+                    send_window,
+                    recv_window: i64::from(INITIAL_WINDOW_SIZE),
+                    body: sender,
+                    reset: false,
+                },
+            );
+        }
+
+        let (shared, handler, middlewares, tls, remote_addr) = (
+            self.shared,
+            self.handler,
+            self.middlewares,
+            self.tls.clone(),
+            self.remote_addr,
+        );
+        scope.spawn(move || {
+            let (response, head) = match parts {
+                Ok(parts) => {
+                    let body = receiver.map(|data| {
+                        Box::new(RequestBody {
+                            shared,
+                            stream: id,
+                            data,
+                        }) as Box<dyn BodyDecoder>
+                    });
+                    let mut request = Request::new(
+                        parts.method,
+                        parts.uri,
+                        HttpVersion::V2_0,
+                        parts.headers,
+                        body,
+                    );
+                    request.tls = tls;
+                    request.remote_addr = remote_addr;
+                    let response = Next::new(handler, middlewares).run(&mut request);
+                    (response, request.method == Method::Head)
+                }
+                Err(status) => (Response::from_status(status), false),
+            };
+            if response.on_upgrade.is_some() {
+                // Switching protocols and tunnels take over an HTTP/1.1 connection, ask
+                // the client to retry with one.
+                // RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-8.6>
+                let _ = shared.lock().reset(id, ErrorCode::Http11Required);
+            } else {
+                let _ = shared.respond(id, response, head);
+            }
+            shared.finish(id);
+        });
+    }
+}
+
+/// Validate the headers of a request and split off its pseudo-headers.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-8.3>
+fn parts(headers: Vec<(String, String)>) -> Result<Parts, &'static str> {
+    let (mut method, mut scheme, mut path, mut authority) = (None, None, None, None);
+    let mut map: HashMap<String, String> = HashMap::new();
+
+    for (name, value) in headers {
+        if let Some(pseudo) = name.strip_prefix(':') {
+            if !map.is_empty() {
+                return Err("Pseudo-header after a header");
+            }
+            let slot = match pseudo {
+                "method" => &mut method,
+                "scheme" => &mut scheme,
+                "path" => &mut path,
+                "authority" => &mut authority,
+                _ => return Err("Unknown pseudo-header"),
+            };
+            if slot.replace(value).is_some() {
+                return Err("Duplicate pseudo-header");
+            }
+            continue;
+        }
+
+        if !is_token(&name) || name.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err("Invalid header name");
+        }
+        if CONNECTION_HEADERS.contains(&name.as_str())
+            || (name == "te" && value != "trailers")
+        {
+            return Err("Connection-specific header");
+        }
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-8.2.3>
+        match map.entry(name) {
+            Entry::Occupied(mut entry) => {
+                let separator = if entry.key() == "cookie" { "; " } else { ", " };
+                entry.get_mut().push_str(separator);
+                entry.get_mut().push_str(&value);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+
+    let method = Method::from_str(&method.ok_or("Missing :method")?)?;
+    if method == Method::Connect {
+        return Err("CONNECT is not supported over HTTP/2");
+    }
+    scheme.ok_or("Missing :scheme")?;
+    let uri = Uri::parse(&path.ok_or("Missing :path")?).map_err(|_| "Invalid :path")?;
+    let valid = match uri.form() {
+        TargetForm::Origin => true,
+        TargetForm::Asterisk => method == Method::Options,
+        TargetForm::Absolute | TargetForm::Authority => false,
+    };
+    if !valid {
+        return Err("Invalid :path");
+    }
+    if let Some(authority) = authority {
+        map.entry("host".to_string()).or_insert(authority);
+    }
+
+    Ok(Parts {
+        method,
+        uri,
+        headers: map,
+    })
+}
@@ -0,0 +1,542 @@
+//! HTTP/2 frames.
+//! RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-4>
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// What a client sends before its first frame.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-3.4>
+pub const PREFACE: &[u8; 24] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The largest frame payload an endpoint must accept, until it says otherwise.
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16_384;
+
+/// The flow-control window of every stream and connection, until `SETTINGS` say
+/// otherwise.
+pub const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+/// The largest flow-control window.
+pub const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-7>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoError = 0x0,
+    ProtocolError = 0x1,
+    InternalError = 0x2,
+    FlowControlError = 0x3,
+    SettingsTimeout = 0x4,
+    StreamClosed = 0x5,
+    FrameSizeError = 0x6,
+    RefusedStream = 0x7,
+    Cancel = 0x8,
+    CompressionError = 0x9,
+    ConnectError = 0xa,
+    EnhanceYourCalm = 0xb,
+    InadequateSecurity = 0xc,
+    Http11Required = 0xd,
+}
+
+impl From<u32> for ErrorCode {
+    /// Unknown codes are treated as `INTERNAL_ERROR`.
+    fn from(code: u32) -> Self {
+        match code {
+            0x0 => ErrorCode::NoError,
+            0x1 => ErrorCode::ProtocolError,
+            0x3 => ErrorCode::FlowControlError,
+            0x4 => ErrorCode::SettingsTimeout,
+            0x5 => ErrorCode::StreamClosed,
+            0x6 => ErrorCode::FrameSizeError,
+            0x7 => ErrorCode::RefusedStream,
+            0x8 => ErrorCode::Cancel,
+            0x9 => ErrorCode::CompressionError,
+            0xa => ErrorCode::ConnectError,
+            0xb => ErrorCode::EnhanceYourCalm,
+            0xc => ErrorCode::InadequateSecurity,
+            0xd => ErrorCode::Http11Required,
+            _ => ErrorCode::InternalError,
+        }
+    }
+}
+
+/// A setting of a `SETTINGS` frame. Unknown settings are dropped when reading.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-6.5.2>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    HeaderTableSize(u32),
+    EnablePush(bool),
+    MaxConcurrentStreams(u32),
+    InitialWindowSize(u32),
+    MaxFrameSize(u32),
+    MaxHeaderListSize(u32),
+}
+
+impl Setting {
+    fn id(self) -> u16 {
+        match self {
+            Setting::HeaderTableSize(_) => 0x1,
+            Setting::EnablePush(_) => 0x2,
+            Setting::MaxConcurrentStreams(_) => 0x3,
+            Setting::InitialWindowSize(_) => 0x4,
+            Setting::MaxFrameSize(_) => 0x5,
+            Setting::MaxHeaderListSize(_) => 0x6,
+        }
+    }
+
+    fn value(self) -> u32 {
+        match self {
+            Setting::EnablePush(enabled) => u32::from(enabled),
+            Setting::HeaderTableSize(value)
+            | Setting::MaxConcurrentStreams(value)
+            | Setting::InitialWindowSize(value)
+            | Setting::MaxFrameSize(value)
+            | Setting::MaxHeaderListSize(value) => value,
+        }
+    }
+
+    /// Parse the payload of a `SETTINGS` frame, also sent in the `HTTP2-Settings` header.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the payload isn't made of 6-byte settings, or a setting is out of
+    /// range.
+    pub fn parse_all(payload: &[u8]) -> Result<Vec<Setting>, Error> {
+        if !payload.len().is_multiple_of(6) {
+            return Err(Error::Connection(ErrorCode::FrameSizeError));
+        }
+        let mut settings = Vec::new();
+        for setting in payload.chunks_exact(6) {
+            let id = u16::from_be_bytes([setting[0], setting[1]]);
+            let value =
+                u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+            settings.push(match id {
+                0x1 => Setting::HeaderTableSize(value),
+                0x2 if value <= 1 => Setting::EnablePush(value == 1),
+                0x3 => Setting::MaxConcurrentStreams(value),
+                0x4 if value <= MAX_WINDOW_SIZE => Setting::InitialWindowSize(value),
+                0x4 => return Err(Error::Connection(ErrorCode::FlowControlError)),
+                0x5 if (DEFAULT_MAX_FRAME_SIZE..=0x00ff_ffff).contains(&value) => {
+                    Setting::MaxFrameSize(value)
+                }
+                0x6 => Setting::MaxHeaderListSize(value),
+                0x2 | 0x5 => return Err(Error::Connection(ErrorCode::ProtocolError)),
+                _ => continue,
+            });
+        }
+        Ok(settings)
+    }
+}
+
+/// A frame, with padding and priority information already dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Data {
+        stream: u32,
+        data: Vec<u8>,
+        end_stream: bool,
+        /// The payload length including padding, all of which counts against flow
+        /// control.
+        length: u32,
+    },
+    Headers {
+        stream: u32,
+        /// A header block fragment, continued by `CONTINUATION` frames unless
+        /// `end_headers`.
+        block: Vec<u8>,
+        end_stream: bool,
+        end_headers: bool,
+    },
+    Priority {
+        stream: u32,
+    },
+    RstStream {
+        stream: u32,
+        code: ErrorCode,
+    },
+    Settings {
+        ack: bool,
+        settings: Vec<Setting>,
+    },
+    PushPromise {
+        stream: u32,
+    },
+    Ping {
+        ack: bool,
+        data: [u8; 8],
+    },
+    GoAway {
+        last_stream: u32,
+        code: ErrorCode,
+    },
+    WindowUpdate {
+        stream: u32,
+        increment: u32,
+    },
+    Continuation {
+        stream: u32,
+        block: Vec<u8>,
+        end_headers: bool,
+    },
+    /// A frame of an unknown type, to be ignored.
+    Unknown {
+        kind: u8,
+        stream: u32,
+    },
+}
+
+/// Why a frame couldn't be read.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The peer broke the protocol, the connection must be closed with a `GOAWAY`.
+    Connection(ErrorCode),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{error}"),
+            Error::Connection(code) => write!(f, "HTTP/2 connection error: {code:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl Frame {
+    /// Read a frame whose payload is at most `max_frame_size` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`Error::Io`] if reading fails, and [`Error::Connection`] if the
+    /// frame is too large or malformed.
+    pub fn read<R: Read>(mut reader: R, max_frame_size: u32) -> Result<Frame, Error> {
+        let mut header = [0; 9];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([0, header[0], header[1], header[2]]);
+        let (kind, flags) = (header[3], header[4]);
+        let stream = u32::from_be_bytes([header[5], header[6], header[7], header[8]])
+            & MAX_WINDOW_SIZE;
+
+        if length > max_frame_size {
+            return Err(Error::Connection(ErrorCode::FrameSizeError));
+        }
+        let mut payload = vec![0; length as usize];
+        reader.read_exact(&mut payload)?;
+
+        let protocol_error = Error::Connection(ErrorCode::ProtocolError);
+        let frame_size_error = Error::Connection(ErrorCode::FrameSizeError);
+        // Frames of streams that must not be sent on the connection, and vice versa.
+        match (kind, stream) {
+            (0x0..=0x3 | 0x5 | 0x9, 0) | (0x4 | 0x6 | 0x7, 1..) => {
+                return Err(protocol_error)
+            }
+            _ => {}
+        }
+
+        Ok(match kind {
+            0x0 => {
+                let data = unpad(payload, flags)?;
+                Frame::Data {
+                    stream,
+                    data,
+                    end_stream: flags & END_STREAM != 0,
+                    length,
+                }
+            }
+            0x1 => {
+                let mut block = unpad(payload, flags)?;
+                if flags & PRIORITY != 0 {
+                    if block.len() < 5 {
+                        return Err(frame_size_error);
+                    }
+                    block.drain(..5);
+                }
+                Frame::Headers {
+                    stream,
+                    block,
+                    end_stream: flags & END_STREAM != 0,
+                    end_headers: flags & END_HEADERS != 0,
+                }
+            }
+            0x2 if length == 5 => Frame::Priority { stream },
+            0x3 if length == 4 => Frame::RstStream {
+                stream,
+                code: ErrorCode::from(u32::from_be_bytes([
+                    payload[0], payload[1], payload[2], payload[3],
+                ])),
+            },
+            0x4 if flags & ACK != 0 && length > 0 => return Err(frame_size_error),
+            0x4 => Frame::Settings {
+                ack: flags & ACK != 0,
+                settings: Setting::parse_all(&payload)?,
+            },
+            0x5 => Frame::PushPromise { stream },
+            0x6 if length == 8 => {
+                let mut data = [0; 8];
+                data.copy_from_slice(&payload);
+                Frame::Ping {
+                    ack: flags & ACK != 0,
+                    data,
+                }
+            }
+            0x7 if length >= 8 => Frame::GoAway {
+                last_stream: u32::from_be_bytes([
+                    payload[0], payload[1], payload[2], payload[3],
+                ]) & MAX_WINDOW_SIZE,
+                code: ErrorCode::from(u32::from_be_bytes([
+                    payload[4], payload[5], payload[6], payload[7],
+                ])),
+            },
+            0x8 if length == 4 => Frame::WindowUpdate {
+                stream,
+                increment: u32::from_be_bytes([
+                    payload[0], payload[1], payload[2], payload[3],
+                ]) & MAX_WINDOW_SIZE,
+            },
+            0x9 => Frame::Continuation {
+                stream,
+                block: payload,
+                end_headers: flags & END_HEADERS != 0,
+            },
+            0x2 | 0x3 | 0x6 | 0x7 | 0x8 => return Err(frame_size_error),
+            kind => Frame::Unknown { kind, stream },
+        })
+    }
+
+    /// Write the frame in a single `write_all`. Frames are written without padding.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to `writer` fails.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut payload = Vec::new();
+        let (kind, flags, stream) = match self {
+            Frame::Data {
+                stream,
+                data,
+                end_stream,
+                ..
+            } => {
+                payload.extend_from_slice(data);
+                (0x0, flag(*end_stream, END_STREAM), *stream)
+            }
+            Frame::Headers {
+                stream,
+                block,
+                end_stream,
+                end_headers,
+            } => {
+                payload.extend_from_slice(block);
+                let flags =
+                    flag(*end_stream, END_STREAM) | flag(*end_headers, END_HEADERS);
+                (0x1, flags, *stream)
+            }
+            Frame::Priority { stream } => {
+                payload.extend_from_slice(&[0, 0, 0, 0, 15]);
+                (0x2, 0, *stream)
+            }
+            Frame::RstStream { stream, code } => {
+                payload.extend_from_slice(&(*code as u32).to_be_bytes());
+                (0x3, 0, *stream)
+            }
+            Frame::Settings { ack, settings } => {
+                for setting in settings {
+                    payload.extend_from_slice(&setting.id().to_be_bytes());
+                    payload.extend_from_slice(&setting.value().to_be_bytes());
+                }
+                (0x4, flag(*ack, ACK), 0)
+            }
+            Frame::PushPromise { stream } => (0x5, 0, *stream),
+            Frame::Ping { ack, data } => {
+                payload.extend_from_slice(data);
+                (0x6, flag(*ack, ACK), 0)
+            }
+            Frame::GoAway { last_stream, code } => {
+                payload.extend_from_slice(&last_stream.to_be_bytes());
+                payload.extend_from_slice(&(*code as u32).to_be_bytes());
+                (0x7, 0, 0)
+            }
+            Frame::WindowUpdate { stream, increment } => {
+                payload.extend_from_slice(&increment.to_be_bytes());
+                (0x8, 0, *stream)
+            }
+            Frame::Continuation {
+                stream,
+                block,
+                end_headers,
+            } => {
+                payload.extend_from_slice(block);
+                (0x9, flag(*end_headers, END_HEADERS), *stream)
+            }
+            Frame::Unknown { kind, stream } => (*kind, 0, *stream),
+        };
+
+        #[expect(clippy::cast_possible_truncation)]
+        let length = (payload.len() as u32).to_be_bytes();
+        let mut frame = Vec::with_capacity(9 + payload.len());
+        frame.extend_from_slice(&length[1..]);
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend(payload);
+        writer.write_all(&frame)
+    }
+}
+
+fn flag(set: bool, flag: u8) -> u8 {
+    if set {
+        flag
+    } else {
+        0
+    }
+}
+
+/// Drop the padding of a `DATA` or `HEADERS` payload.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9113#section-6.1>
+fn unpad(mut payload: Vec<u8>, flags: u8) -> Result<Vec<u8>, Error> {
+    if flags & PADDED == 0 {
+        return Ok(payload);
+    }
+    let Some(&padding) = payload.first() else {
+        return Err(Error::Connection(ErrorCode::FrameSizeError));
+    };
+    let padding = usize::from(padding);
+    if padding >= payload.len() {
+        return Err(Error::Connection(ErrorCode::ProtocolError));
+    }
+    payload.truncate(payload.len() - padding);
+    payload.remove(0);
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{Error, ErrorCode, Frame, Setting, DEFAULT_MAX_FRAME_SIZE};
+    use std::io::Cursor;
+
+    fn round_trip(frame: &Frame) -> Frame {
+        let mut bytes = Vec::new();
+        frame.write(&mut bytes).unwrap();
+        Frame::read(Cursor::new(bytes), DEFAULT_MAX_FRAME_SIZE).unwrap()
+    }
+
+    #[test]
+    fn it_round_trips_frames() {
+        let frames = [
+            Frame::Data {
+                stream: 1,
+                data: b"hello".to_vec(),
+                end_stream: true,
+                length: 5,
+            },
+            Frame::Headers {
+                stream: 3,
+                block: vec![0x82],
+                end_stream: false,
+                end_headers: true,
+            },
+            Frame::Settings {
+                ack: false,
+                settings: vec![
+                    Setting::MaxConcurrentStreams(100),
+                    Setting::EnablePush(false),
+                ],
+            },
+            Frame::Ping {
+                ack: true,
+                data: *b"12345678",
+            },
+            Frame::GoAway {
+                last_stream: 7,
+                code: ErrorCode::ProtocolError,
+            },
+            Frame::WindowUpdate {
+                stream: 0,
+                increment: 1024,
+            },
+            Frame::RstStream {
+                stream: 5,
+                code: ErrorCode::Cancel,
+            },
+        ];
+        for frame in frames {
+            assert_eq!(frame, round_trip(&frame));
+        }
+    }
+
+    #[test]
+    fn it_drops_padding_and_priority() {
+        // HEADERS with PADDED and PRIORITY: pad length 2, 5 priority bytes, block,
+        // padding.
+        let bytes = [
+            0, 0, 9, 0x1, 0x2d, 0, 0, 0, 1, 2, 0, 0, 0, 0, 16, 0x82, 0, 0,
+        ];
+        assert_eq!(
+            Frame::Headers {
+                stream: 1,
+                block: vec![0x82],
+                end_stream: true,
+                end_headers: true,
+            },
+            Frame::read(Cursor::new(bytes), DEFAULT_MAX_FRAME_SIZE).unwrap()
+        );
+
+        let bytes = [0, 0, 4, 0x0, 0x8, 0, 0, 0, 1, 2, b'a', 0, 0];
+        assert_eq!(
+            Frame::Data {
+                stream: 1,
+                data: b"a".to_vec(),
+                end_stream: false,
+                length: 4,
+            },
+            Frame::read(Cursor::new(bytes), DEFAULT_MAX_FRAME_SIZE).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_rejects_malformed_frames() {
+        let error = |bytes: &[u8]| match Frame::read(
+            Cursor::new(bytes),
+            DEFAULT_MAX_FRAME_SIZE,
+        ) {
+            Err(Error::Connection(code)) => code,
+            other => panic!("{other:?}"),
+        };
+        // DATA on the connection, SETTINGS on a stream.
+        assert_eq!(
+            ErrorCode::ProtocolError,
+            error(&[0, 0, 0, 0x0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            ErrorCode::ProtocolError,
+            error(&[0, 0, 0, 0x4, 0, 0, 0, 0, 1])
+        );
+        // A PING of 4 bytes, and a frame over the maximum size.
+        assert_eq!(
+            ErrorCode::FrameSizeError,
+            error(&[0, 0, 4, 0x6, 0, 0, 0, 0, 0, 1, 2, 3, 4])
+        );
+        assert_eq!(
+            ErrorCode::FrameSizeError,
+            error(&[0, 0x40, 1, 0x0, 0, 0, 0, 0, 1])
+        );
+        // An initial window size over 2^31-1.
+        assert_eq!(
+            ErrorCode::FlowControlError,
+            error(&[0, 0, 6, 0x4, 0, 0, 0, 0, 0, 0, 4, 0x80, 0, 0, 0])
+        );
+    }
+}
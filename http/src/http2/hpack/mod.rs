@@ -0,0 +1,544 @@
+//! HPACK, the header compression of HTTP/2.
+//! RFC: <https://datatracker.ietf.org/doc/html/rfc7541>
+//!
+//! ```
+//! use http::http2::hpack::{Decoder, Encoder};
+//!
+//! let mut encoder = Encoder::new();
+//! let mut decoder = Decoder::new(4096);
+//! let block = encoder.encode([(":status", "200"), ("content-type", "text/plain")]);
+//! assert_eq!(
+//!     vec![
+//!         (":status".to_string(), "200".to_string()),
+//!         ("content-type".to_string(), "text/plain".to_string()),
+//!     ],
+//!     decoder.decode(&block).unwrap()
+//! );
+//! ```
+
+pub mod huffman;
+
+use std::collections::VecDeque;
+use std::fmt;
+
+/// The size of the dynamic table both ends start with.
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#appendix-A>
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Headers that are never added to the dynamic table, so they can't be guessed by
+/// observing the compressed size of later requests.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-7.1.3>
+const SENSITIVE: [&str; 3] = ["authorization", "cookie", "set-cookie"];
+
+/// Why a header block couldn't be decoded.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The block is malformed, the connection can't be used any more.
+    Compression(&'static str),
+    /// The block was decoded, but its headers exceed the limit given to the decoder.
+    HeaderListTooLarge,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Compression(reason) => write!(f, "{reason}"),
+            DecodeError::HeaderListTooLarge => write!(f, "Header list too large"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// The dynamic table, newest entries first.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-2.3.2>
+#[derive(Debug, Default)]
+struct Table {
+    entries: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Table {
+    fn new(max_size: usize) -> Self {
+        Table {
+            max_size,
+            ..Table::default()
+        }
+    }
+
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-4.1>
+    fn entry_size(name: &str, value: &str) -> usize {
+        name.len() + value.len() + 32
+    }
+
+    /// The entry at a 1-based `index` of the static table followed by the dynamic one.
+    fn get(&self, index: usize) -> Option<(&str, &str)> {
+        match index {
+            0 => None,
+            1..=61 => Some(STATIC_TABLE[index - 1]),
+            _ => self
+                .entries
+                .get(index - 62)
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        }
+    }
+
+    /// The index of an entry with `name` and `value`, or else with `name` alone.
+    fn find(&self, name: &str, value: &str) -> Option<(usize, bool)> {
+        let entries = STATIC_TABLE
+            .iter()
+            .copied()
+            .chain(self.entries.iter().map(|(n, v)| (n.as_str(), v.as_str())));
+        let mut by_name = None;
+        for (index, (n, v)) in (1..).zip(entries) {
+            if n == name {
+                if v == value {
+                    return Some((index, true));
+                }
+                by_name.get_or_insert((index, false));
+            }
+        }
+        by_name
+    }
+
+    fn insert(&mut self, name: String, value: String) {
+        let size = Self::entry_size(&name, &value);
+        self.size += size;
+        self.entries.push_front((name, value));
+        // An entry larger than the table empties it.
+        self.evict();
+    }
+
+    fn resize(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            if let Some((name, value)) = self.entries.pop_back() {
+                self.size -= Self::entry_size(&name, &value);
+            }
+        }
+    }
+}
+
+/// Decodes header blocks, keeping the dynamic table of the peer's [`Encoder`] in sync.
+#[derive(Debug)]
+pub struct Decoder {
+    table: Table,
+    /// The largest table size the peer may switch to, our `SETTINGS_HEADER_TABLE_SIZE`.
+    max_table_size: usize,
+    /// The largest header list decoded, by the size of its entries.
+    max_list_size: usize,
+}
+
+impl Decoder {
+    /// A decoder allowing the peer a dynamic table of up to `max_table_size` bytes.
+    #[must_use]
+    pub fn new(max_table_size: usize) -> Self {
+        Decoder {
+            table: Table::new(max_table_size),
+            max_table_size,
+            max_list_size: usize::MAX,
+        }
+    }
+
+    /// Fail blocks whose headers add up to more than `size`, counted like table entries.
+    #[must_use]
+    pub fn max_list_size(mut self, size: usize) -> Self {
+        self.max_list_size = size;
+        self
+    }
+
+    /// Decode a complete header block into `(name, value)` pairs, in order.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`DecodeError::Compression`] if the block is malformed or has a
+    /// header that isn't UTF-8, and with [`DecodeError::HeaderListTooLarge`] if the
+    /// headers exceed the limit. The dynamic table is still updated in the latter
+    /// case.
+    pub fn decode(
+        &mut self,
+        mut block: &[u8],
+    ) -> Result<Vec<(String, String)>, DecodeError> {
+        let mut headers = Vec::new();
+        let mut list_size = 0;
+        let mut fields = false;
+
+        while let Some(&first) = block.first() {
+            // RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-6>
+            let (name, value) = if first & 0x80 != 0 {
+                let index = decode_integer(&mut block, 7)?;
+                let (name, value) = self
+                    .table
+                    .get(index)
+                    .ok_or(DecodeError::Compression("Invalid table index"))?;
+                (name.to_string(), value.to_string())
+            } else if first & 0xe0 == 0x20 {
+                let size = decode_integer(&mut block, 5)?;
+                if fields || size > self.max_table_size {
+                    return Err(DecodeError::Compression("Invalid table size update"));
+                }
+                self.table.resize(size);
+                continue;
+            } else {
+                let index = first & 0x40 != 0;
+                let index_name = decode_integer(&mut block, if index { 6 } else { 4 })?;
+                let name = if index_name == 0 {
+                    decode_string(&mut block)?
+                } else {
+                    self.table
+                        .get(index_name)
+                        .ok_or(DecodeError::Compression("Invalid table index"))?
+                        .0
+                        .to_string()
+                };
+                let value = decode_string(&mut block)?;
+                if index {
+                    self.table.insert(name.clone(), value.clone());
+                }
+                (name, value)
+            };
+
+            fields = true;
+            list_size += Table::entry_size(&name, &value);
+            if list_size <= self.max_list_size {
+                headers.push((name, value));
+            }
+        }
+
+        if list_size > self.max_list_size {
+            return Err(DecodeError::HeaderListTooLarge);
+        }
+        Ok(headers)
+    }
+}
+
+/// Encodes header blocks, indexing headers in a dynamic table.
+#[derive(Debug)]
+pub struct Encoder {
+    table: Table,
+    /// The smallest and the final size the table was set to since the last block,
+    /// announced at the start of the next one.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-4.2>
+    size_update: Option<(usize, usize)>,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Encoder::new()
+    }
+}
+
+impl Encoder {
+    #[must_use]
+    pub fn new() -> Self {
+        Encoder {
+            table: Table::new(DEFAULT_TABLE_SIZE),
+            size_update: None,
+        }
+    }
+
+    /// Resize the dynamic table, e.g. when the peer's `SETTINGS_HEADER_TABLE_SIZE`
+    /// changes. The table is never made larger than the default size.
+    pub fn set_max_table_size(&mut self, size: usize) {
+        let size = size.min(DEFAULT_TABLE_SIZE);
+        if size == self.table.max_size && self.size_update.is_none() {
+            return;
+        }
+        let smallest = self
+            .size_update
+            .map_or(size, |(smallest, _)| smallest.min(size));
+        self.size_update = Some((smallest, size));
+        self.table.resize(size);
+    }
+
+    /// Encode `headers` into a header block. Names should be lower-case.
+    pub fn encode<'a>(
+        &mut self,
+        headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Vec<u8> {
+        let mut block = Vec::new();
+        if let Some((smallest, size)) = self.size_update.take() {
+            if smallest < size {
+                encode_integer(&mut block, 0x20, 5, smallest);
+            }
+            encode_integer(&mut block, 0x20, 5, size);
+        }
+
+        for (name, value) in headers {
+            let found = self.table.find(name, value);
+            if let Some((index, true)) = found {
+                encode_integer(&mut block, 0x80, 7, index);
+                continue;
+            }
+
+            let sensitive = SENSITIVE.contains(&name);
+            // Indexing an entry larger than the table would just empty it.
+            let index =
+                !sensitive && Table::entry_size(name, value) <= self.table.max_size;
+            let (flags, prefix) = match (sensitive, index) {
+                (true, _) => (0x10, 4),
+                (false, true) => (0x40, 6),
+                (false, false) => (0x00, 4),
+            };
+            if let Some((index, _)) = found {
+                encode_integer(&mut block, flags, prefix, index);
+            } else {
+                encode_integer(&mut block, flags, prefix, 0);
+                encode_string(&mut block, name.as_bytes());
+            }
+            encode_string(&mut block, value.as_bytes());
+            if index {
+                self.table.insert(name.to_string(), value.to_string());
+            }
+        }
+        block
+    }
+}
+
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-5.1>
+fn encode_integer(output: &mut Vec<u8>, flags: u8, prefix: u8, mut value: usize) {
+    let max = (1 << prefix) - 1;
+    if value < max {
+        #[expect(clippy::cast_possible_truncation)]
+        output.push(flags | value as u8);
+        return;
+    }
+    #[expect(clippy::cast_possible_truncation)]
+    output.push(flags | max as u8);
+    value -= max;
+    while value >= 0x80 {
+        #[expect(clippy::cast_possible_truncation)]
+        output.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    #[expect(clippy::cast_possible_truncation)]
+    output.push(value as u8);
+}
+
+fn decode_integer(input: &mut &[u8], prefix: u8) -> Result<usize, DecodeError> {
+    let truncated = DecodeError::Compression("Truncated integer");
+    let (&first, rest) = input.split_first().ok_or(truncated)?;
+    *input = rest;
+
+    let max = (1 << prefix) - 1;
+    let mut value = usize::from(first & max);
+    if value < usize::from(max) {
+        return Ok(value);
+    }
+    // Larger values than 2^28 are never needed, and would risk overflowing.
+    for shift in (0..28).step_by(7) {
+        let (&byte, rest) = input
+            .split_first()
+            .ok_or(DecodeError::Compression("Truncated integer"))?;
+        *input = rest;
+        value += usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::Compression("Integer too large"))
+}
+
+/// Write a string literal, Huffman-encoded when that's shorter.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7541#section-5.2>
+fn encode_string(output: &mut Vec<u8>, string: &[u8]) {
+    let length = huffman::encoded_len(string);
+    if length < string.len() {
+        encode_integer(output, 0x80, 7, length);
+        output.extend(huffman::encode(string));
+    } else {
+        encode_integer(output, 0x00, 7, string.len());
+        output.extend_from_slice(string);
+    }
+}
+
+fn decode_string(input: &mut &[u8]) -> Result<String, DecodeError> {
+    let huffman = input.first().is_some_and(|first| first & 0x80 != 0);
+    let length = decode_integer(input, 7)?;
+    if length > input.len() {
+        return Err(DecodeError::Compression("Truncated string"));
+    }
+    let (string, rest) = input.split_at(length);
+    *input = rest;
+
+    let string = if huffman {
+        huffman::decode(string).map_err(DecodeError::Compression)?
+    } else {
+        string.to_vec()
+    };
+    String::from_utf8(string).map_err(|_| DecodeError::Compression("Header is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{DecodeError, Decoder, Encoder};
+
+    fn hex(string: &str) -> Vec<u8> {
+        let digits = string.replace(' ', "");
+        (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn pairs(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn it_decodes_the_rfc_requests() {
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc7541#appendix-C.4>
+        let mut decoder = Decoder::new(4096);
+        assert_eq!(
+            pairs(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ]),
+            decoder
+                .decode(&hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff"))
+                .unwrap()
+        );
+        assert_eq!(
+            pairs(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+                ("cache-control", "no-cache"),
+            ]),
+            decoder
+                .decode(&hex("8286 84be 5886 a8eb 1064 9cbf"))
+                .unwrap()
+        );
+        assert_eq!(
+            pairs(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/index.html"),
+                (":authority", "www.example.com"),
+                ("custom-key", "custom-value"),
+            ]),
+            decoder
+                .decode(&hex(
+                    "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf"
+                ))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn it_round_trips_with_the_dynamic_table() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new(4096);
+        let headers = [
+            (":status", "200"),
+            ("content-type", "text/html; charset=utf-8"),
+            ("x-custom", "value"),
+            ("set-cookie", "id=secret"),
+        ];
+        let first = encoder.encode(headers);
+        assert_eq!(pairs(&headers), decoder.decode(&first).unwrap());
+        let second = encoder.encode(headers);
+        assert!(second.len() < first.len());
+        assert_eq!(pairs(&headers), decoder.decode(&second).unwrap());
+
+        encoder.set_max_table_size(0);
+        let third = encoder.encode(headers);
+        assert_eq!(0x20, third[0]);
+        assert_eq!(pairs(&headers), decoder.decode(&third).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_malformed_blocks() {
+        let mut decoder = Decoder::new(4096);
+        // Index 0, an index past the tables, and a size update above the limit.
+        assert!(decoder.decode(&[0x80]).is_err());
+        assert!(decoder.decode(&[0xff, 0x00]).is_err());
+        assert!(decoder.decode(&hex("3fe2 1f")).is_err());
+        // A size update after a header.
+        assert!(decoder.decode(&hex("8220")).is_err());
+        // A truncated string.
+        assert!(decoder.decode(&hex("4005 6162")).is_err());
+
+        let mut decoder = Decoder::new(4096).max_list_size(64);
+        assert_eq!(
+            Err(DecodeError::HeaderListTooLarge),
+            decoder.decode(&Encoder::new().encode([("a", "b"), ("c", &"d".repeat(64))]))
+        );
+    }
+}
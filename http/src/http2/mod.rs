@@ -0,0 +1,365 @@
+//! HTTP/2 over cleartext TCP (h2c), and over TLS when the client picks `h2` with ALPN.
+//!
+//! The [`Server`](crate::server::Server) switches to HTTP/2 when a connection starts with
+//! the client preface, or when a request without a body asks to with `Upgrade: h2c`.
+//! Requests are then handled exactly like HTTP/1 ones, with `http_version` set to
+//! [`HttpVersion::V2_0`](crate::request::HttpVersion::V2_0).
+//!
+//! Try it with `curl --http2-prior-knowledge` or `nghttp`.
+//! RFC: <https://datatracker.ietf.org/doc/html/rfc9113>
+
+mod connection;
+pub mod frame;
+pub mod hpack;
+
+pub(crate) use connection::serve;
+
+use crate::request::uri::Uri;
+use crate::request::{HttpVersion, Method, Request};
+use base64::alphabet::URL_SAFE;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use frame::Setting;
+use std::collections::HashMap;
+
+/// A request that asked to switch to HTTP/2, answered on stream 1 after the switch.
+pub(crate) struct Upgrade {
+    method: Method,
+    uri: Uri,
+    headers: HashMap<String, String>,
+    settings: Vec<Setting>,
+}
+
+/// Returns the upgrade if `request` asks to switch to h2c with valid `HTTP2-Settings`.
+///
+/// Requests with a body stay on HTTP/1.1, since it would have to be read before
+/// switching.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7540#section-3.2>
+pub(crate) fn upgrade(request: &mut Request) -> Option<Upgrade> {
+    if request.http_version != HttpVersion::V1_1
+        || request.body.is_some()
+        || !request.header_lists("Upgrade", "h2c")
+        || !request.header_lists("Connection", "Upgrade")
+        || !request.header_lists("Connection", "HTTP2-Settings")
+    {
+        return None;
+    }
+    let settings = request
+        .header("HTTP2-Settings")
+        .and_then(|settings| BASE64URL.decode(settings).ok())
+        .and_then(|payload| Setting::parse_all(&payload).ok())?;
+
+    // Headers of the HTTP/1.1 connection don't carry over.
+    let mut headers = request.headers.take().unwrap_or_default();
+    let connection = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Connection"))
+        .map(|(_, value)| value.to_lowercase())
+        .unwrap_or_default();
+    headers.retain(|name, _| {
+        let name = name.to_lowercase();
+        !matches!(
+            name.as_str(),
+            "connection"
+                | "upgrade"
+                | "http2-settings"
+                | "keep-alive"
+                | "transfer-encoding"
+        ) && !connection.split(',').any(|listed| listed.trim() == name)
+    });
+    let headers = headers
+        .into_iter()
+        .map(|(name, value)| (name.to_lowercase(), value))
+        .collect();
+
+    Some(Upgrade {
+        method: request.method.clone(),
+        uri: request.uri().clone(),
+        headers,
+        settings,
+    })
+}
+
+/// Unpadded base64url, the encoding of `HTTP2-Settings`. Padding is tolerated.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7540#section-3.2.1>
+const BASE64URL: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::frame::{ErrorCode, Frame, Setting, DEFAULT_MAX_FRAME_SIZE, PREFACE};
+    use super::hpack::{Decoder, Encoder};
+    use super::{serve, upgrade, BASE64URL};
+    use crate::request::{HttpVersion, Method, Request};
+    use crate::response::{Headers, Response, Status};
+    use base64::Engine;
+    use std::io::{Cursor, Read};
+
+    fn handler(request: &mut Request) -> Response {
+        let mut body = Vec::new();
+        request.body_reader().read_to_end(&mut body).unwrap();
+        let text = format!(
+            "{} {} {:?} {}",
+            request.method,
+            request.path(),
+            request.http_version,
+            String::from_utf8(body).unwrap()
+        );
+        let mut response =
+            Response::new(Status::Ok, Headers::new("Connection: close"), text);
+        if let Some(host) = request.header("Host") {
+            response.headers.insert("X-Host", host);
+        }
+        response
+    }
+
+    fn client(frames: &[Frame]) -> Vec<u8> {
+        let mut bytes = PREFACE.to_vec();
+        for frame in frames {
+            frame.write(&mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    /// Serve `input`, returning the frames the server sent.
+    fn exchange(input: Vec<u8>) -> Vec<Frame> {
+        let mut output = Vec::new();
+        serve(
+            &handler,
+            &[],
+            Cursor::new(input),
+            &mut output,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let mut output = Cursor::new(output);
+        let mut frames = Vec::new();
+        while let Ok(frame) = Frame::read(&mut output, DEFAULT_MAX_FRAME_SIZE) {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    fn request(stream: u32, method: &str, path: &str, end_stream: bool) -> Frame {
+        Frame::Headers {
+            stream,
+            block: Encoder::new().encode([
+                (":method", method),
+                (":scheme", "http"),
+                (":path", path),
+                (":authority", "localhost"),
+            ]),
+            end_stream,
+            end_headers: true,
+        }
+    }
+
+    /// The headers and the body sent on `stream`.
+    fn response(frames: &[Frame], stream: u32) -> (Vec<(String, String)>, String) {
+        let mut decoder = Decoder::new(4096);
+        let (mut headers, mut body) = (Vec::new(), Vec::new());
+        for frame in frames {
+            match frame {
+                Frame::Headers {
+                    stream: id, block, ..
+                } => {
+                    let fields = decoder.decode(block).unwrap();
+                    if *id == stream {
+                        headers = fields;
+                    }
+                }
+                Frame::Data {
+                    stream: id, data, ..
+                } if *id == stream => body.extend(data),
+                _ => {}
+            }
+        }
+        (headers, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn it_refuses_streams_past_the_concurrency_limit() {
+        let streams: Vec<u32> = (0..17).map(|i| 1 + 2 * i).collect();
+        let mut frames = vec![Frame::Settings {
+            ack: false,
+            settings: vec![],
+        }];
+        frames.extend(streams.iter().map(|id| request(*id, "POST", "/", false)));
+        frames.extend(streams.iter().map(|id| Frame::Data {
+            stream: *id,
+            data: Vec::new(),
+            end_stream: true,
+            length: 0,
+        }));
+        let frames = exchange(client(&frames));
+
+        assert!(frames.contains(&Frame::RstStream {
+            stream: 33,
+            code: ErrorCode::RefusedStream,
+        }));
+        assert_eq!("POST / V2_0 ", response(&frames, 31).1);
+    }
+
+    #[test]
+    fn it_serves_requests_on_streams() {
+        let frames = exchange(client(&[
+            Frame::Settings {
+                ack: false,
+                settings: vec![],
+            },
+            request(1, "GET", "/a", true),
+            request(3, "POST", "/b", false),
+            Frame::Data {
+                stream: 3,
+                data: b"hello".to_vec(),
+                end_stream: true,
+                length: 5,
+            },
+            Frame::Ping {
+                ack: false,
+                data: *b"pingpong",
+            },
+        ]));
+
+        assert!(matches!(frames[0], Frame::Settings { ack: false, .. }));
+        assert!(frames.contains(&Frame::Settings {
+            ack: true,
+            settings: vec![]
+        }));
+        assert!(frames.contains(&Frame::Ping {
+            ack: true,
+            data: *b"pingpong"
+        }));
+
+        let (headers, body) = response(&frames, 1);
+        assert_eq!("GET /a V2_0 ", body);
+        assert_eq!((":status".to_string(), "200".to_string()), headers[0]);
+        assert!(headers.contains(&("x-host".to_string(), "localhost".to_string())));
+        assert!(headers.contains(&("content-length".to_string(), "12".to_string())));
+        assert!(!headers.iter().any(|(name, _)| name == "connection"));
+        assert_eq!("POST /b V2_0 hello", response(&frames, 3).1);
+    }
+
+    #[test]
+    fn it_respects_flow_control() {
+        let big = |request: &mut Request| {
+            let length = if request.method == Method::Head {
+                0
+            } else {
+                100_000
+            };
+            Response::new(Status::Ok, Headers::default(), "a".repeat(length))
+        };
+        let input = client(&[
+            Frame::Settings {
+                ack: false,
+                settings: vec![Setting::InitialWindowSize(10)],
+            },
+            request(1, "GET", "/", true),
+        ]);
+        let mut output = Vec::new();
+        serve(&big, &[], Cursor::new(input), &mut output, None, None, None).unwrap();
+        let mut output = Cursor::new(output);
+        let mut sent = 0;
+        while let Ok(frame) = Frame::read(&mut output, DEFAULT_MAX_FRAME_SIZE) {
+            if let Frame::Data { data, .. } = frame {
+                sent += data.len();
+            }
+        }
+        // The client never opened the window further, and then left.
+        assert_eq!(10, sent);
+    }
+
+    #[test]
+    fn it_rejects_protocol_errors() {
+        // A request before SETTINGS.
+        let frames = exchange(client(&[request(1, "GET", "/", true)]));
+        assert!(matches!(frames.last(), Some(Frame::GoAway { .. })));
+
+        // A malformed request is answered on its stream, the connection carries on.
+        let mut encoder = Encoder::new();
+        let frames = exchange(client(&[
+            Frame::Settings {
+                ack: false,
+                settings: vec![],
+            },
+            Frame::Headers {
+                stream: 1,
+                block: encoder.encode([(":method", "GET"), ("Upper", "case")]),
+                end_stream: true,
+                end_headers: true,
+            },
+        ]));
+        assert_eq!(
+            (":status".to_string(), "400".to_string()),
+            response(&frames, 1).0[0]
+        );
+        assert!(!frames
+            .iter()
+            .any(|frame| matches!(frame, Frame::GoAway { .. })));
+    }
+
+    #[test]
+    fn it_upgrades_h2c_requests() {
+        let raw = "GET /up HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\nHTTP2-Settings: AAMAAABkAAQAAP__\r\n\r\n";
+        let mut request = Request::from(Cursor::new(raw)).unwrap();
+        let h2c = upgrade(&mut request).unwrap();
+        assert_eq!(
+            vec![
+                Setting::MaxConcurrentStreams(100),
+                Setting::InitialWindowSize(65535)
+            ],
+            h2c.settings
+        );
+        assert_eq!(
+            Some("localhost"),
+            h2c.headers.get("host").map(String::as_str)
+        );
+        assert!(!h2c.headers.contains_key("upgrade"));
+
+        let input = client(&[Frame::Settings {
+            ack: false,
+            settings: vec![],
+        }]);
+        let mut output = Vec::new();
+        serve(
+            &handler,
+            &[],
+            Cursor::new(input),
+            &mut output,
+            Some(h2c),
+            None,
+            None,
+        )
+        .unwrap();
+        let mut output = Cursor::new(output);
+        let mut frames = Vec::new();
+        while let Ok(frame) = Frame::read(&mut output, DEFAULT_MAX_FRAME_SIZE) {
+            frames.push(frame);
+        }
+        assert_eq!("GET /up V2_0 ", response(&frames, 1).1);
+
+        let raw = "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: h2c\r\n\r\n";
+        let mut request = Request::from(Cursor::new(raw)).unwrap();
+        assert!(upgrade(&mut request).is_none());
+        assert_eq!(HttpVersion::V1_1, request.http_version);
+    }
+
+    #[test]
+    fn it_decodes_base64url() {
+        assert_eq!(
+            Some(b"\x00\x03\x00\x00\x00\x64".to_vec()),
+            BASE64URL.decode("AAMAAABk").ok()
+        );
+        assert_eq!(Some(vec![0xff, 0xff]), BASE64URL.decode("__8").ok());
+        assert_eq!(Some(vec![0xff, 0xff]), BASE64URL.decode("__8=").ok());
+        assert!(BASE64URL.decode("a+b/").is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+/// Objects and arrays nested deeper than this are rejected, so a hostile body can't
+/// overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc8259>
+///
+/// ```
+/// use http::json::Value;
+///
+/// let value: Value = r#"{"name": "Jane", "tags": ["a", "b"]}"#.parse().unwrap();
+/// assert_eq!(Some("Jane"), value.get("name").and_then(Value::as_str));
+/// assert_eq!(r#"{"name":"Jane","tags":["a","b"]}"#, value.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Returns the member `key` if this is an object.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    #[must_use]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_object(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = &'static str;
+
+    fn from_str(input: &str) -> Result<Value, Self::Err> {
+        let mut parser = Parser {
+            input: input.as_bytes(),
+            position: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.position == input.len() {
+            Ok(value)
+        } else {
+            Err("Unexpected trailing characters")
+        }
+    }
+}
+
+/// Serializes the value as compact JSON.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            // JSON has no representation for NaN or infinities.
+            Value::Number(n) if !n.is_finite() => f.write_str("null"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Value::Object(members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl<V: Into<Value>> From<Vec<V>> for Value {
+    fn from(values: Vec<V>) -> Self {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect_literal(
+        &mut self,
+        literal: &str,
+        value: Value,
+    ) -> Result<Value, &'static str> {
+        if self.input[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err("Invalid literal")
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, &'static str> {
+        if depth > MAX_DEPTH {
+            return Err("JSON is nested too deeply");
+        }
+        self.skip_whitespace();
+        match self.peek().ok_or("Unexpected end of JSON")? {
+            b'n' => self.expect_literal("null", Value::Null),
+            b't' => self.expect_literal("true", Value::Bool(true)),
+            b'f' => self.expect_literal("false", Value::Bool(false)),
+            b'"' => self.string().map(Value::String),
+            b'[' => self.array(depth),
+            b'{' => self.object(depth),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err("Unexpected character"),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, &'static str> {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => {}
+                Some(b']') => return Ok(Value::Array(values)),
+                _ => return Err("Expected `,` or `]`"),
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, &'static str> {
+        self.position += 1;
+        let mut members = BTreeMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err("Expected an object key");
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.next() != Some(b':') {
+                return Err("Expected `:`");
+            }
+            members.insert(key, self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => {}
+                Some(b'}') => return Ok(Value::Object(members)),
+                _ => return Err("Expected `,` or `}`"),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, &'static str> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            let start = parser.position;
+            while parser.peek().is_some_and(|b| b.is_ascii_digit()) {
+                parser.position += 1;
+            }
+            parser.position - start
+        };
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+        // Leading zeros are not allowed.
+        if self.peek() == Some(b'0') {
+            self.position += 1;
+        } else if digits(self) == 0 {
+            return Err("Invalid number");
+        }
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if digits(self) == 0 {
+                return Err("Invalid number");
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.position += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.position += 1;
+            }
+            if digits(self) == 0 {
+                return Err("Invalid number");
+            }
+        }
+
+        std::str::from_utf8(&self.input[start..self.position])
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .map(Value::Number)
+            .ok_or("Invalid number")
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        self.position += 1;
+        let mut string = Vec::new();
+        loop {
+            match self.next().ok_or("Unterminated string")? {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.next().ok_or("Unterminated string")? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err("Invalid escape"),
+                    };
+                    string.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1f => return Err("Control character in string"),
+                byte => string.push(byte),
+            }
+        }
+        String::from_utf8(string).map_err(|_| "String is not UTF-8")
+    }
+
+    /// Parse the `XXXX` of a `\uXXXX` escape, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, &'static str> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.next() != Some(b'\\') || self.next() != Some(b'u') {
+                return Err("Unpaired surrogate");
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err("Unpaired surrogate");
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or("Unpaired surrogate")
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        let hex = self
+            .input
+            .get(self.position..self.position + 4)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or("Invalid unicode escape")?;
+        self.position += 4;
+        Ok(hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::Value;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn it_parses_values() {
+        let value: Value =
+            r#" {"a": [1, -2.5e2, true, null], "b": {"c": "d"}, "e": ""} "#
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            Value::Object(BTreeMap::from([
+                (
+                    "a".to_string(),
+                    Value::Array(vec![
+                        Value::Number(1.0),
+                        Value::Number(-250.0),
+                        Value::Bool(true),
+                        Value::Null
+                    ])
+                ),
+                (
+                    "b".to_string(),
+                    Value::Object(BTreeMap::from([("c".to_string(), "d".into())]))
+                ),
+                ("e".to_string(), "".into()),
+            ])),
+            value
+        );
+    }
+
+    #[test]
+    fn it_parses_string_escapes() {
+        assert_eq!(
+            Ok(Value::String("a\"b\\c/\n\u{e9}\u{1f600}".to_string())),
+            r#""a\"b\\c\/\n\u00e9\ud83d\ude00""#.parse()
+        );
+        assert!(r#""\ud83d""#.parse::<Value>().is_err());
+        assert!("\"a\nb\"".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_json() {
+        for input in [
+            "",
+            "{",
+            "[1,]",
+            "{\"a\" 1}",
+            "01",
+            "1.",
+            "-",
+            "tru",
+            "[1] 2",
+            "{'a': 1}",
+        ] {
+            assert!(input.parse::<Value>().is_err(), "{input} should be invalid");
+        }
+        assert!("[".repeat(200).parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn it_round_trips() {
+        let input = r#"{"a":[1,2.5,false,null],"b":"line\nbreak \"quoted\" \u0001"}"#;
+        assert_eq!(input, input.parse::<Value>().unwrap().to_string());
+    }
+}
@@ -1,12 +1,25 @@
-#![feature(test)]
-#![feature(substr_range)]
-#![feature(stmt_expr_attributes)]
+#![cfg_attr(test, feature(test, stmt_expr_attributes))]
 #![warn(clippy::pedantic)]
 #![warn(clippy::unwrap_used)]
 #![warn(clippy::expect_used)]
 #![warn(clippy::perf)]
 
+pub mod client;
+pub mod cookie;
+pub mod date;
+pub mod http2;
+pub mod json;
+pub mod middleware;
+pub mod proxy;
+mod random;
 pub mod request;
 pub mod response;
+pub mod router;
 pub mod server;
+pub mod session;
+pub mod sse;
+pub mod static_files;
 pub mod threadpool;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod websocket;
@@ -0,0 +1,418 @@
+use super::conditional::ETag;
+use super::{Middleware, Next};
+use crate::request::Request;
+use crate::response::{Body, Response, Status};
+use flate2::read::{GzEncoder, ZlibEncoder};
+use std::io::{Read, Write};
+
+/// A content coding the server can compress responses with.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-8.4.1>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    /// The zlib format, which is what `deflate` means in HTTP.
+    Deflate,
+}
+
+impl Encoding {
+    /// Supported encodings, in order of preference when the client rates them equally.
+    const SUPPORTED: [Encoding; 2] = [Encoding::Gzip, Encoding::Deflate];
+
+    fn matches(self, coding: &str) -> bool {
+        match self {
+            Encoding::Gzip => {
+                coding.eq_ignore_ascii_case("gzip")
+                    || coding.eq_ignore_ascii_case("x-gzip")
+            }
+            Encoding::Deflate => coding.eq_ignore_ascii_case("deflate"),
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Encoding::Gzip => "gzip",
+                Encoding::Deflate => "deflate",
+            }
+        )
+    }
+}
+
+/// Pick the encoding the client prefers from an `Accept-Encoding` header value.
+///
+/// Returns `None` when the client accepts none of the supported encodings, in which case
+/// the response is sent as-is.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-12.5.3>
+#[must_use]
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let codings: Vec<(&str, u16)> = accept_encoding
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let coding = params.next()?.trim();
+            if coding.is_empty() {
+                return None;
+            }
+            let mut quality = 1000;
+            for param in params {
+                if let Some((key, value)) = param.split_once('=') {
+                    if key.trim().eq_ignore_ascii_case("q") {
+                        quality = parse_qvalue(value.trim())?;
+                    }
+                }
+            }
+            Some((coding, quality))
+        })
+        .collect();
+
+    let quality_of = |encoding: Encoding| {
+        codings
+            .iter()
+            .find(|(coding, _)| encoding.matches(coding))
+            .or_else(|| codings.iter().find(|(coding, _)| *coding == "*"))
+            .map_or(0, |(_, quality)| *quality)
+    };
+
+    Encoding::SUPPORTED
+        .into_iter()
+        .map(|encoding| (encoding, quality_of(encoding)))
+        .filter(|(_, quality)| *quality > 0)
+        // `max_by_key` returns the last maximum, reverse to keep the preferred order on
+        // ties.
+        .rev()
+        .max_by_key(|(_, quality)| *quality)
+        .map(|(encoding, _)| encoding)
+}
+
+/// Parse a weight into thousandths, so `q=0.5` is 500.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-12.4.2>
+fn parse_qvalue(value: &str) -> Option<u16> {
+    let (int, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let thousandths = format!("{fraction:0<3}").parse::<u16>().ok()?;
+    match int {
+        "0" => Some(thousandths),
+        "1" if thousandths == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+/// A middleware that compresses responses with gzip or deflate, depending on the
+/// request's `Accept-Encoding`.
+///
+/// Only responses whose `Content-Type` is in the allowlist are compressed, and buffered
+/// bodies smaller than the minimum size are left alone. Streaming bodies are always
+/// compressed on the fly since their size is unknown.
+///
+/// ```no_run
+/// use http::middleware::compression::Compression;
+/// use http::request::Request;
+/// use http::response::{Headers, Response, Status};
+/// use http::server::Server;
+///
+/// fn handler(_: &mut Request) -> Response {
+///     Response::new(Status::Ok, Headers::new("Content-Type: text/plain"), "hello")
+/// }
+///
+/// Server::new("0.0.0.0:4000", handler)
+///     .wrap(Compression::new().min_size(512))
+///     .listen()
+///     .unwrap();
+/// ```
+pub struct Compression {
+    min_size: usize,
+    content_types: Vec<String>,
+    level: flate2::Compression,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression {
+            min_size: 1024,
+            content_types: [
+                "text/*",
+                "application/json",
+                "application/javascript",
+                "application/xml",
+                "image/svg+xml",
+            ]
+            .map(String::from)
+            .to_vec(),
+            level: flate2::Compression::default(),
+        }
+    }
+}
+
+impl Compression {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffered bodies smaller than `bytes` are sent uncompressed.
+    #[must_use]
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    /// Replace the allowlist of compressible content types. An entry like `text/*`
+    /// matches every subtype.
+    #[must_use]
+    pub fn content_types(mut self, content_types: &[&str]) -> Self {
+        self.content_types = content_types.iter().map(|t| t.to_lowercase()).collect();
+        self
+    }
+
+    /// Compression level, from 0 (none) to 9 (best).
+    #[must_use]
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = flate2::Compression::new(level.min(9));
+        self
+    }
+
+    fn is_compressible(&self, content_type: &str) -> bool {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        // Events have to reach the client as they're sent, not when the compressor
+        // flushes.
+        if media_type == "text/event-stream" {
+            return false;
+        }
+        self.content_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix('*') {
+                Some(prefix) => media_type.starts_with(prefix),
+                None => media_type == *allowed,
+            })
+    }
+
+    fn compress(&self, encoding: Encoding, body: Body) -> std::io::Result<Body> {
+        Ok(match body {
+            Body::Bytes(bytes) => Body::Bytes(match encoding {
+                Encoding::Gzip => {
+                    let mut encoder =
+                        flate2::write::GzEncoder::new(Vec::new(), self.level);
+                    encoder.write_all(&bytes)?;
+                    encoder.finish()?
+                }
+                Encoding::Deflate => {
+                    let mut encoder =
+                        flate2::write::ZlibEncoder::new(Vec::new(), self.level);
+                    encoder.write_all(&bytes)?;
+                    encoder.finish()?
+                }
+            }),
+            Body::Stream(reader) => match encoding {
+                Encoding::Gzip => Body::stream(GzEncoder::new(reader, self.level)),
+                Encoding::Deflate => Body::stream(ZlibEncoder::new(reader, self.level)),
+            },
+            Body::Seekable { reader, length } => {
+                let reader = Read::take(reader, length);
+                match encoding {
+                    Encoding::Gzip => Body::stream(GzEncoder::new(reader, self.level)),
+                    Encoding::Deflate => {
+                        Body::stream(ZlibEncoder::new(reader, self.level))
+                    }
+                }
+            }
+            Body::Sized { reader, length } => {
+                let reader = Read::take(reader, length);
+                match encoding {
+                    Encoding::Gzip => Body::stream(GzEncoder::new(reader, self.level)),
+                    Encoding::Deflate => {
+                        Body::stream(ZlibEncoder::new(reader, self.level))
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let encoding = request.header("Accept-Encoding").and_then(negotiate);
+        let mut response = next.run(request);
+
+        let has_body = response.may_have_body();
+        let compressible = response
+            .headers
+            .get("Content-Type")
+            .is_some_and(|content_type| self.is_compressible(content_type));
+        if !has_body || !compressible || response.headers.contains("Content-Encoding") {
+            return response;
+        }
+
+        // The representation depends on `Accept-Encoding` whether or not this request got
+        // a compressed one, caches need to know that.
+        response.headers.add_to_list("Vary", "Accept-Encoding");
+
+        // The offsets of a partial response refer to the uncompressed body.
+        if response.status == Status::PartialContent {
+            return response;
+        }
+        let Some(encoding) = encoding else {
+            return response;
+        };
+        if response
+            .body
+            .content_length()
+            .is_some_and(|length| length < self.min_size as u64)
+        {
+            return response;
+        }
+
+        let body = std::mem::take(&mut response.body);
+        match self.compress(encoding, body) {
+            Ok(body) => {
+                response.body = body;
+                response
+                    .headers
+                    .insert("Content-Encoding", encoding.to_string());
+                // The compressed bytes differ from the ones a strong tag was computed
+                // for.
+                if let Some(etag) = response.headers.get("ETag").and_then(ETag::parse) {
+                    if !etag.is_weak() {
+                        response
+                            .headers
+                            .insert("ETag", ETag::weak(etag.tag()).to_string());
+                    }
+                }
+                response
+            }
+            Err(_) => Response::new(
+                Status::InternalServerError,
+                crate::response::Headers::default(),
+                Body::empty(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{negotiate, Compression, Encoding};
+    use crate::middleware::{Middleware, Next};
+    use crate::request::Request;
+    use crate::response::{Body, Headers, Response, Status};
+    use flate2::read::{GzDecoder, ZlibDecoder};
+    use std::io::{Cursor, Read};
+
+    fn text(_: &mut Request) -> Response {
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/plain; charset=utf-8\r\nETag: \"abc\""),
+            "a".repeat(2048),
+        )
+    }
+
+    fn streamed_text(_: &mut Request) -> Response {
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/plain"),
+            Body::stream(Cursor::new("streamed")),
+        )
+    }
+
+    fn png(_: &mut Request) -> Response {
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: image/png"),
+            vec![0; 2048],
+        )
+    }
+
+    fn run(
+        middleware: &Compression,
+        handler: fn(&mut Request) -> Response,
+        accept: &str,
+    ) -> Response {
+        let raw = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: {accept}\r\n\r\n"
+        );
+        let mut request = Request::from(Cursor::new(raw)).unwrap();
+        middleware.handle(&mut request, Next::new(&handler, &[]))
+    }
+
+    #[test]
+    fn it_negotiates_the_encoding() {
+        assert_eq!(Some(Encoding::Gzip), negotiate("gzip, deflate, br"));
+        assert_eq!(Some(Encoding::Gzip), negotiate("deflate, gzip"));
+        assert_eq!(Some(Encoding::Deflate), negotiate("gzip;q=0.5, deflate"));
+        assert_eq!(Some(Encoding::Deflate), negotiate("gzip;q=0, *"));
+        assert_eq!(Some(Encoding::Gzip), negotiate("x-gzip"));
+        assert_eq!(Some(Encoding::Gzip), negotiate("*;q=0.1"));
+        assert_eq!(None, negotiate("br, identity"));
+        assert_eq!(None, negotiate("gzip;q=0, deflate;q=0.000"));
+        assert_eq!(None, negotiate(""));
+    }
+
+    #[test]
+    fn it_ignores_codings_with_an_invalid_qvalue() {
+        assert_eq!(
+            Some(Encoding::Deflate),
+            negotiate("gzip;q=2, deflate;q=0.1")
+        );
+        assert_eq!(
+            Some(Encoding::Deflate),
+            negotiate("gzip;q=0.5555, deflate;q=0.1")
+        );
+    }
+
+    #[test]
+    fn it_compresses_buffered_bodies() {
+        let response = run(&Compression::new(), text, "gzip");
+        assert_eq!(Some("gzip"), response.headers.get("Content-Encoding"));
+        assert_eq!(Some("Accept-Encoding"), response.headers.get("Vary"));
+        assert_eq!(Some("W/\"abc\""), response.headers.get("ETag"));
+
+        let mut decoded = String::new();
+        GzDecoder::new(response.body.as_bytes().unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!("a".repeat(2048), decoded);
+    }
+
+    #[test]
+    fn it_compresses_streaming_bodies() {
+        let response = run(&Compression::new(), streamed_text, "deflate");
+        assert_eq!(Some("deflate"), response.headers.get("Content-Encoding"));
+
+        let mut decoded = String::new();
+        ZlibDecoder::new(Cursor::new(response.body.into_bytes().unwrap()))
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!("streamed", decoded);
+    }
+
+    #[test]
+    fn it_skips_small_and_disallowed_bodies() {
+        let response = run(&Compression::new().min_size(4096), text, "gzip");
+        assert_eq!(None, response.headers.get("Content-Encoding"));
+        assert_eq!(Some("Accept-Encoding"), response.headers.get("Vary"));
+        assert_eq!(2048, response.body.as_bytes().unwrap().len());
+        assert_eq!(Some("\"abc\""), response.headers.get("ETag"));
+
+        let response = run(&Compression::new(), png, "gzip");
+        assert_eq!(None, response.headers.get("Content-Encoding"));
+        assert_eq!(None, response.headers.get("Vary"));
+    }
+
+    #[test]
+    fn it_skips_clients_that_accept_no_encoding() {
+        let response = run(&Compression::new(), text, "identity");
+        assert_eq!(None, response.headers.get("Content-Encoding"));
+        assert_eq!(Some("Accept-Encoding"), response.headers.get("Vary"));
+    }
+}
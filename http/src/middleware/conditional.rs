@@ -0,0 +1,397 @@
+use super::{Middleware, Next};
+use crate::date;
+use crate::request::{Method, Request};
+use crate::response::{Body, Response, Status};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// An entity tag, identifying one version of a representation.
+///
+/// Strong tags change whenever the bytes change, weak tags only when the meaning does.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-8.8.3>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag {
+    weak: bool,
+    tag: String,
+}
+
+impl ETag {
+    /// A strong tag, `tag` must not contain `"`.
+    #[must_use]
+    pub fn strong(tag: &str) -> Self {
+        ETag {
+            weak: false,
+            tag: tag.to_string(),
+        }
+    }
+
+    /// A weak tag, `tag` must not contain `"`.
+    #[must_use]
+    pub fn weak(tag: &str) -> Self {
+        ETag {
+            weak: true,
+            tag: tag.to_string(),
+        }
+    }
+
+    /// A strong tag derived from a hash of `bytes`. The hash is 64-bit FNV-1a, so tags
+    /// stay the same across restarts, builds and servers.
+    /// Draft: <https://datatracker.ietf.org/doc/html/draft-eastlake-fnv>
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        ETag::strong(&format!("{:x}-{hash:016x}", bytes.len()))
+    }
+
+    /// A strong tag derived from a file's size and modification time.
+    #[must_use]
+    pub fn from_metadata(length: u64, modified: SystemTime) -> Self {
+        let modified = modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        ETag::strong(&format!("{length:x}-{modified:x}"))
+    }
+
+    /// Parse a single entity tag, e.g. `"xyzzy"` or `W/"xyzzy"`.
+    #[must_use]
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (weak, quoted) = match input.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, input),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        // etagc = %x21 / %x23-7E / obs-text
+        if tag.bytes().any(|b| b == b'"' || b <= b' ' || b == 0x7f) {
+            return None;
+        }
+        Some(ETag {
+            weak,
+            tag: tag.to_string(),
+        })
+    }
+
+    #[must_use]
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Both tags are strong and identical, required by `If-Match` and ranges.
+    #[must_use]
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// The tags are identical regardless of weakness, used by `If-None-Match`.
+    #[must_use]
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// Returns `true` if the `If-Match` or `If-None-Match` value `header` lists a tag equal
+/// to `etag` under `eq`. `*` matches any current representation.
+fn matches_any(header: &str, etag: Option<&ETag>, eq: fn(&ETag, &ETag) -> bool) -> bool {
+    if header.trim() == "*" {
+        return true;
+    }
+    let Some(etag) = etag else {
+        return false;
+    };
+    header
+        .split(',')
+        .filter_map(ETag::parse)
+        .any(|candidate| eq(&candidate, etag))
+}
+
+/// Evaluate the request's preconditions against the current `etag` and `last_modified`
+/// time of the target.
+///
+/// Returns `Some(Status::NotModified)` or `Some(Status::PreconditionFailed)` if the
+/// request shouldn't be performed, `None` if it should. Handlers of unsafe methods should
+/// call this before changing anything.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-13.2.2>
+#[must_use]
+pub fn evaluate(
+    request: &Request,
+    etag: Option<&ETag>,
+    last_modified: Option<SystemTime>,
+) -> Option<Status> {
+    // HTTP-dates have a one second resolution.
+    let last_modified = last_modified.map(|time| {
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    });
+    let is_get_or_head = matches!(request.method, Method::Get | Method::Head);
+
+    if let Some(if_match) = request.header("If-Match") {
+        if !matches_any(if_match, etag, ETag::strong_eq) {
+            return Some(Status::PreconditionFailed);
+        }
+    } else if let Some(since) =
+        request.header("If-Unmodified-Since").and_then(date::parse)
+    {
+        if last_modified.is_some_and(|modified| modified > since) {
+            return Some(Status::PreconditionFailed);
+        }
+    }
+
+    if let Some(if_none_match) = request.header("If-None-Match") {
+        if matches_any(if_none_match, etag, ETag::weak_eq) {
+            return Some(if is_get_or_head {
+                Status::NotModified
+            } else {
+                Status::PreconditionFailed
+            });
+        }
+    } else if let Some(since) = request.header("If-Modified-Since").and_then(date::parse)
+    {
+        if is_get_or_head && last_modified.is_some_and(|modified| modified <= since) {
+            return Some(Status::NotModified);
+        }
+    }
+
+    None
+}
+
+/// A middleware that answers conditional requests with `304 Not Modified` or
+/// `412 Precondition Failed`, based on the `ETag` and `Last-Modified` headers of
+/// successful responses.
+///
+/// Responses without an `ETag` can be given one from a hash of their body with
+/// [`hash_bodies`](ConditionalRequests::hash_bodies).
+///
+/// The handler runs before the preconditions are evaluated, so handlers of unsafe methods
+/// should call [`evaluate`] themselves to avoid changing state when a precondition fails.
+/// Add it before [`Compression`](super::compression::Compression), so a `304` keeps the
+/// `Vary` header of the response it replaces.
+///
+/// ```no_run
+/// use http::middleware::conditional::ConditionalRequests;
+/// use http::server::Server;
+/// use http::static_files::static_files;
+///
+/// Server::new("0.0.0.0:4000", static_files("/", "public"))
+///     .wrap(ConditionalRequests::new())
+///     .listen()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct ConditionalRequests {
+    hash_bodies: bool,
+}
+
+impl ConditionalRequests {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give buffered responses without an `ETag` one from a hash of their body.
+    #[must_use]
+    pub fn hash_bodies(mut self, hash_bodies: bool) -> Self {
+        self.hash_bodies = hash_bodies;
+        self
+    }
+}
+
+impl Middleware for ConditionalRequests {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let mut response = next.run(request);
+        // Preconditions only apply to responses that would have been successful.
+        if !response.status.is_success() {
+            return response;
+        }
+
+        if self.hash_bodies && !response.headers.contains("ETag") {
+            if let Some(bytes) = response.body.as_bytes() {
+                let etag = ETag::from_bytes(bytes);
+                response.headers.insert("ETag", etag.to_string());
+            }
+        }
+
+        let etag = response.headers.get("ETag").and_then(ETag::parse);
+        let last_modified = response.headers.get("Last-Modified").and_then(date::parse);
+        match evaluate(request, etag.as_ref(), last_modified) {
+            Some(status) if status == Status::NotModified => {
+                response.status = status;
+                response.body = Body::empty();
+                // A 304 keeps the validators and caching headers, but describes no
+                // content.
+                // RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-15.4.5>
+                let content_headers = response
+                    .headers
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .filter(|name| {
+                        name.to_ascii_lowercase().starts_with("content-")
+                            && !name.eq_ignore_ascii_case("Content-Location")
+                    })
+                    .collect::<Vec<_>>();
+                for name in content_headers {
+                    response.headers.remove(&name);
+                }
+                response
+            }
+            Some(status) => Response::from_status(status),
+            None => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{evaluate, ConditionalRequests, ETag};
+    use crate::middleware::{Middleware, Next};
+    use crate::request::Request;
+    use crate::response::{Headers, Response, Status};
+    use std::io::Cursor;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn request(method: &str, headers: &str) -> Request<'static> {
+        let raw = format!("{method} / HTTP/1.1\r\nHost: localhost\r\n{headers}\r\n");
+        Request::from(Cursor::new(raw)).unwrap()
+    }
+
+    #[test]
+    fn it_parses_and_compares_etags() {
+        let strong = ETag::parse("\"xyzzy\"").unwrap();
+        let weak = ETag::parse(" W/\"xyzzy\" ").unwrap();
+        assert_eq!(ETag::strong("xyzzy"), strong);
+        assert_eq!("W/\"xyzzy\"", weak.to_string());
+        assert!(weak.is_weak());
+
+        assert!(strong.strong_eq(&strong));
+        assert!(!strong.strong_eq(&weak));
+        assert!(strong.weak_eq(&weak));
+
+        for invalid in ["xyzzy", "\"xy\"zzy\"", "w/\"xyzzy\"", "\"xy zzy\""] {
+            assert_eq!(None, ETag::parse(invalid), "{invalid}");
+        }
+        assert_eq!(ETag::from_bytes(b"hello"), ETag::from_bytes(b"hello"));
+        assert_ne!(ETag::from_bytes(b"hello"), ETag::from_bytes(b"world"));
+        assert_eq!(
+            "\"5-a430d84680aabd0b\"",
+            ETag::from_bytes(b"hello").to_string()
+        );
+    }
+
+    #[test]
+    fn it_evaluates_preconditions_in_order() {
+        let etag = ETag::strong("v2");
+        let modified = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let check = |method, headers| {
+            evaluate(&request(method, headers), Some(&etag), Some(modified))
+        };
+
+        assert_eq!(None, check("GET", ""));
+        assert_eq!(None, check("PUT", "If-Match: \"v1\", \"v2\"\r\n"));
+        assert_eq!(
+            Some(Status::PreconditionFailed),
+            check("PUT", "If-Match: W/\"v2\"\r\n")
+        );
+        assert_eq!(None, check("PUT", "If-Match: *\r\n"));
+        assert_eq!(
+            Some(Status::PreconditionFailed),
+            check(
+                "PUT",
+                "If-Unmodified-Since: Sun, 06 Nov 1994 08:49:36 GMT\r\n"
+            )
+        );
+        // If-Match takes precedence over If-Unmodified-Since.
+        assert_eq!(
+            None,
+            check(
+                "PUT",
+                "If-Match: \"v2\"\r\nIf-Unmodified-Since: Sun, 06 Nov 1994 08:49:36 GMT\r\n"
+            )
+        );
+
+        assert_eq!(
+            Some(Status::NotModified),
+            check("GET", "If-None-Match: W/\"v2\"\r\n")
+        );
+        assert_eq!(
+            Some(Status::PreconditionFailed),
+            check("POST", "If-None-Match: *\r\n")
+        );
+        assert_eq!(
+            Some(Status::NotModified),
+            check(
+                "GET",
+                "If-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n"
+            )
+        );
+        assert_eq!(
+            None,
+            check(
+                "GET",
+                "If-Modified-Since: Sun, 06 Nov 1994 08:49:36 GMT\r\n"
+            )
+        );
+        // If-None-Match takes precedence over If-Modified-Since.
+        assert_eq!(
+            None,
+            check(
+                "GET",
+                "If-None-Match: \"v1\"\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n"
+            )
+        );
+        assert_eq!(None, check("GET", "If-Modified-Since: yesterday\r\n"));
+    }
+
+    fn handler(_: &mut Request) -> Response {
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/plain\r\nCache-Control: max-age=60"),
+            "hello",
+        )
+    }
+
+    #[test]
+    fn it_answers_not_modified_with_hashed_etags() {
+        let middleware = ConditionalRequests::new().hash_bodies(true);
+        let response =
+            middleware.handle(&mut request("GET", ""), Next::new(&handler, &[]));
+        assert_eq!(Status::Ok, response.status);
+        let etag = response.headers.get("ETag").unwrap().to_string();
+
+        let headers = format!("If-None-Match: {etag}\r\n");
+        let response =
+            middleware.handle(&mut request("GET", &headers), Next::new(&handler, &[]));
+        assert_eq!(Status::NotModified, response.status);
+        assert_eq!(Some(etag.as_str()), response.headers.get("ETag"));
+        assert_eq!(Some("max-age=60"), response.headers.get("Cache-Control"));
+        assert_eq!(None, response.headers.get("Content-Type"));
+        assert_eq!(Some(0), response.body.content_length());
+    }
+
+    #[test]
+    fn it_ignores_preconditions_without_validators() {
+        let middleware = ConditionalRequests::new();
+        let mut request = request("GET", "If-None-Match: \"abc\"\r\n");
+        let response = middleware.handle(&mut request, Next::new(&handler, &[]));
+        assert_eq!(Status::Ok, response.status);
+    }
+}
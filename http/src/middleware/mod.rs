@@ -0,0 +1,59 @@
+pub mod compression;
+pub mod conditional;
+pub mod range;
+
+use crate::request::Request;
+use crate::response::Response;
+use crate::server::Handler;
+use std::sync::Arc;
+
+/// Code that runs around the request handler, able to inspect or rewrite the request
+/// before it's handled and the response after.
+///
+/// ```
+/// use http::middleware::{Middleware, Next};
+/// use http::request::Request;
+/// use http::response::Response;
+///
+/// struct PoweredBy;
+///
+/// impl Middleware for PoweredBy {
+///     fn handle(&self, request: &mut Request, next: Next) -> Response {
+///         let mut response = next.run(request);
+///         response.headers.insert("X-Powered-By", "Rust");
+///         response
+///     }
+/// }
+/// ```
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: &mut Request, next: Next) -> Response;
+}
+
+/// The rest of the middleware chain, ending with the request handler.
+pub struct Next<'a> {
+    handler: &'a dyn Handler,
+    middlewares: &'a [Arc<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        handler: &'a dyn Handler,
+        middlewares: &'a [Arc<dyn Middleware>],
+    ) -> Self {
+        Next {
+            handler,
+            middlewares,
+        }
+    }
+
+    /// Pass the request to the next middleware, or to the handler if this is the last
+    /// one.
+    pub fn run(self, request: &mut Request) -> Response {
+        match self.middlewares.split_first() {
+            Some((middleware, rest)) => {
+                middleware.handle(request, Next::new(self.handler, rest))
+            }
+            None => self.handler.handle(request),
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use super::conditional::ETag;
+use super::{Middleware, Next};
+use crate::date;
+use crate::random;
+use crate::request::{Method, Request};
+use crate::response::{Body, Headers, ReadSeek, Response, Status};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// The outcome of matching a `Range` header against a representation.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRanges {
+    /// The header is invalid, uses another unit or asks for too many ranges: send the
+    /// whole representation.
+    Ignored,
+    /// None of the ranges overlap the representation.
+    Unsatisfiable,
+    /// The satisfiable ranges in the order they were requested, with exclusive ends.
+    Satisfiable(Vec<Range<u64>>),
+}
+
+impl ByteRanges {
+    /// More ranges than this are ignored, so a client can't make the server seek all over
+    /// a file for one request.
+    const MAX_RANGES: usize = 32;
+
+    /// Parse a `Range` header value, e.g. `bytes=0-499, -500`, for a representation of
+    /// `length` bytes.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-14.1.2>
+    #[must_use]
+    pub fn parse(header: &str, length: u64) -> Self {
+        let Some((unit, specs)) = header.split_once('=') else {
+            return ByteRanges::Ignored;
+        };
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return ByteRanges::Ignored;
+        }
+
+        let specs = specs
+            .split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .collect::<Vec<_>>();
+        if specs.is_empty() || specs.len() > Self::MAX_RANGES {
+            return ByteRanges::Ignored;
+        }
+
+        let mut ranges = Vec::new();
+        for spec in specs {
+            let Some((first, last)) = spec.split_once('-') else {
+                return ByteRanges::Ignored;
+            };
+            let parse = |n: &str| {
+                n.bytes()
+                    .all(|b| b.is_ascii_digit())
+                    .then(|| n.parse::<u64>().ok())
+                    .flatten()
+            };
+            let range = match (first, last) {
+                // `-500`, the last 500 bytes.
+                ("", suffix) => match parse(suffix) {
+                    Some(suffix) => length.saturating_sub(suffix)..length,
+                    None => return ByteRanges::Ignored,
+                },
+                // `500-`, everything from byte 500.
+                (first, "") => match parse(first) {
+                    Some(first) => first..length,
+                    None => return ByteRanges::Ignored,
+                },
+                (first, last) => match (parse(first), parse(last)) {
+                    (Some(first), Some(last)) if first <= last => {
+                        first..length.min(last.saturating_add(1))
+                    }
+                    _ => return ByteRanges::Ignored,
+                },
+            };
+            if !range.is_empty() {
+                ranges.push(range);
+            }
+        }
+
+        if ranges.is_empty() {
+            ByteRanges::Unsatisfiable
+        } else {
+            ByteRanges::Satisfiable(ranges)
+        }
+    }
+}
+
+/// A middleware that answers `Range` requests for buffered and seekable response bodies,
+/// like the ones of [`StaticFiles`](crate::static_files::StaticFiles).
+///
+/// A single range is sent as a `206 Partial Content` with a `Content-Range`, several as a
+/// `multipart/byteranges` body, and a range outside of the body gets a
+/// `416 Range Not Satisfiable`. `If-Range` is honored, so a client resuming a download
+/// gets the whole body if it changed.
+///
+/// Add it after [`Compression`](super::compression::Compression), ranges refer to the
+/// uncompressed body.
+///
+/// ```no_run
+/// use http::middleware::range::RangeRequests;
+/// use http::server::Server;
+/// use http::static_files::static_files;
+///
+/// Server::new("0.0.0.0:4000", static_files("/", "public"))
+///     .wrap(RangeRequests)
+///     .listen()
+///     .unwrap();
+/// ```
+pub struct RangeRequests;
+
+impl RangeRequests {
+    /// Returns `true` if the `If-Range` value `if_range` still describes the response, in
+    /// which case the range can be sent.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-13.1.5>
+    fn if_range_matches(if_range: &str, headers: &Headers) -> bool {
+        match ETag::parse(if_range) {
+            Some(etag) => headers
+                .get("ETag")
+                .and_then(ETag::parse)
+                .is_some_and(|current| current.strong_eq(&etag)),
+            None => date::parse(if_range).is_some_and(|since| {
+                headers.get("Last-Modified").and_then(date::parse) == Some(since)
+            }),
+        }
+    }
+}
+
+impl Middleware for RangeRequests {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let mut response = next.run(request);
+        let Some(length) = response.body.content_length() else {
+            return response;
+        };
+        if response.status != Status::Ok
+            || matches!(response.body, Body::Stream(_) | Body::Sized { .. })
+        {
+            return response;
+        }
+        response.headers.insert("Accept-Ranges", "bytes");
+
+        // Only GET has ranges.
+        if request.method != Method::Get {
+            return response;
+        }
+        let Some(range) = request.header("Range") else {
+            return response;
+        };
+        if let Some(if_range) = request.header("If-Range") {
+            if !Self::if_range_matches(if_range, &response.headers) {
+                return response;
+            }
+        }
+
+        match ByteRanges::parse(range, length) {
+            ByteRanges::Ignored => response,
+            ByteRanges::Unsatisfiable => {
+                let mut response = Response::from_status(Status::RangeNotSatisfiable);
+                response
+                    .headers
+                    .insert("Content-Range", format!("bytes */{length}"));
+                response
+            }
+            ByteRanges::Satisfiable(ranges) => match partial(response, &ranges, length) {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("Error seeking a response body: {e:?}");
+                    Response::new(Status::InternalServerError, Headers::default(), "")
+                }
+            },
+        }
+    }
+}
+
+/// Turn a `200 OK` response into a `206 Partial Content` with the `ranges` of its body.
+fn partial(
+    mut response: Response,
+    ranges: &[Range<u64>],
+    length: u64,
+) -> io::Result<Response> {
+    let body = std::mem::take(&mut response.body);
+    let (mut reader, base): (Box<dyn ReadSeek>, u64) = match body {
+        Body::Bytes(bytes) => (Box::new(Cursor::new(bytes)), 0),
+        Body::Seekable { mut reader, .. } => {
+            let base = reader.stream_position()?;
+            (reader, base)
+        }
+        Body::Stream(_) | Body::Sized { .. } => unreachable!("streams can't seek"),
+    };
+    response.status = Status::PartialContent;
+
+    if let [range] = ranges {
+        reader.seek(SeekFrom::Start(base + range.start))?;
+        response.headers.insert(
+            "Content-Range",
+            format!("bytes {}-{}/{length}", range.start, range.end - 1),
+        );
+        response.body = Body::Seekable {
+            reader,
+            length: range.end - range.start,
+        };
+        return Ok(response);
+    }
+
+    // RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-14.6>
+    let boundary = boundary()?;
+    let content_type = response.headers.remove("Content-Type");
+    let mut segments = VecDeque::new();
+    for (i, range) in ranges.iter().enumerate() {
+        let mut head = String::new();
+        if i > 0 {
+            head.push_str("\r\n");
+        }
+        let _ = write!(head, "--{boundary}\r\n");
+        if let Some(content_type) = &content_type {
+            let _ = write!(head, "Content-Type: {content_type}\r\n");
+        }
+        let _ = write!(
+            head,
+            "Content-Range: bytes {}-{}/{length}\r\n\r\n",
+            range.start,
+            range.end - 1
+        );
+        segments.push_back(Segment::Text(Cursor::new(head.into_bytes())));
+        segments.push_back(Segment::Range(base + range.start..base + range.end));
+    }
+    segments.push_back(Segment::Text(Cursor::new(
+        format!("\r\n--{boundary}--\r\n").into_bytes(),
+    )));
+
+    response.headers.insert(
+        "Content-Type",
+        format!("multipart/byteranges; boundary={boundary}"),
+    );
+    response.body = Body::stream(MultipartRanges { reader, segments });
+    Ok(response)
+}
+
+fn boundary() -> io::Result<String> {
+    random::hex::<12>()
+}
+
+enum Segment {
+    Text(Cursor<Vec<u8>>),
+    /// Absolute positions in the reader.
+    Range(Range<u64>),
+}
+
+/// A `multipart/byteranges` body, reading each range from the original body as it's sent.
+struct MultipartRanges {
+    reader: Box<dyn ReadSeek>,
+    segments: VecDeque<Segment>,
+}
+
+impl Read for MultipartRanges {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(segment) = self.segments.front_mut() {
+            let read = match segment {
+                Segment::Text(text) => text.read(buf)?,
+                Segment::Range(range) if range.is_empty() => 0,
+                Segment::Range(range) => {
+                    let limit = usize::try_from(range.end - range.start)
+                        .unwrap_or(usize::MAX)
+                        .min(buf.len());
+                    self.reader.seek(SeekFrom::Start(range.start))?;
+                    let read = self.reader.read(&mut buf[..limit])?;
+                    if read == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Body ended before the range",
+                        ));
+                    }
+                    range.start += read as u64;
+                    read
+                }
+            };
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            self.segments.pop_front();
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::single_range_in_vec_init)]
+    use super::{ByteRanges, RangeRequests};
+    use crate::middleware::{Middleware, Next};
+    use crate::request::Request;
+    use crate::response::{Body, Headers, Response, Status};
+    use std::io::Cursor;
+
+    #[test]
+    fn it_parses_byte_ranges() {
+        let parse = |header| ByteRanges::parse(header, 10_000);
+        assert_eq!(ByteRanges::Satisfiable(vec![0..500]), parse("bytes=0-499"));
+        assert_eq!(
+            ByteRanges::Satisfiable(vec![500..1000, 9500..10_000, 9000..10_000]),
+            parse("bytes=500-999, -500,9000-")
+        );
+        assert_eq!(
+            ByteRanges::Satisfiable(vec![9000..10_000, 0..10_000]),
+            parse("Bytes=9000-20000,-20000")
+        );
+        assert_eq!(
+            ByteRanges::Satisfiable(vec![0..1]),
+            parse("bytes=10000-, 0-0")
+        );
+        assert_eq!(ByteRanges::Unsatisfiable, parse("bytes=10000-"));
+        assert_eq!(ByteRanges::Unsatisfiable, parse("bytes=-0"));
+
+        for ignored in [
+            "",
+            "bytes=",
+            "bytes=5",
+            "bytes=5-4",
+            "bytes=a-b",
+            "bytes=+1-2",
+            "items=0-1",
+            &format!("bytes={}", ["0-1"; 33].join(",")),
+        ] {
+            assert_eq!(ByteRanges::Ignored, parse(ignored), "{ignored}");
+        }
+    }
+
+    fn handler(_: &mut Request) -> Response {
+        let body = Cursor::new(b"xx0123456789".to_vec());
+        let mut body = Body::seekable(body).unwrap();
+        if let Body::Seekable { reader, length } = &mut body {
+            // The body starts at the reader's position.
+            reader.seek_relative(2).unwrap();
+            *length -= 2;
+        }
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/plain\r\nETag: \"v1\""),
+            body,
+        )
+    }
+
+    fn get(headers: &str) -> Response {
+        let raw = format!("GET / HTTP/1.1\r\nHost: localhost\r\n{headers}\r\n");
+        let mut request = Request::from(Cursor::new(raw)).unwrap();
+        RangeRequests.handle(&mut request, Next::new(&handler, &[]))
+    }
+
+    fn body(response: Response) -> String {
+        String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_sends_a_single_range() {
+        let response = get("Range: bytes=2-4\r\n");
+        assert_eq!(Status::PartialContent, response.status);
+        assert_eq!(Some("bytes 2-4/10"), response.headers.get("Content-Range"));
+        assert_eq!(Some(3), response.body.content_length());
+        assert_eq!("234", body(response));
+
+        let response = get("");
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(Some("bytes"), response.headers.get("Accept-Ranges"));
+    }
+
+    #[test]
+    fn it_sends_multiple_ranges() {
+        let response = get("Range: bytes=0-1,-2\r\n");
+        assert_eq!(Status::PartialContent, response.status);
+        let content_type = response.headers.get("Content-Type").unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        assert_eq!(
+            format!(
+                "--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+                 --{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n\
+                 --{boundary}--\r\n"
+            ),
+            body(response)
+        );
+    }
+
+    #[test]
+    fn it_rejects_unsatisfiable_ranges() {
+        let response = get("Range: bytes=10-\r\n");
+        assert_eq!(Status::RangeNotSatisfiable, response.status);
+        assert_eq!(Some("bytes */10"), response.headers.get("Content-Range"));
+    }
+
+    #[test]
+    fn it_sends_everything_if_the_body_changed() {
+        let response = get("Range: bytes=0-1\r\nIf-Range: \"v1\"\r\n");
+        assert_eq!(Status::PartialContent, response.status);
+
+        for if_range in ["\"v0\"", "W/\"v1\"", "Sun, 06 Nov 1994 08:49:37 GMT"] {
+            let response = get(&format!("Range: bytes=0-1\r\nIf-Range: {if_range}\r\n"));
+            assert_eq!(Status::Ok, response.status, "{if_range}");
+            assert_eq!("0123456789", body(response));
+        }
+    }
+}
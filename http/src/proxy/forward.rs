@@ -0,0 +1,390 @@
+use super::upstream;
+use crate::client::connection::{self, Config, Pool};
+use crate::request::uri::TargetForm;
+use crate::request::{Method, Request};
+use crate::response::{Headers, Response, Status};
+use crate::server::{Handler, Upgraded};
+use std::io;
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Act as an HTTP forward proxy, see [`ForwardProxy`].
+#[must_use]
+pub fn forward_proxy() -> ForwardProxy {
+    ForwardProxy::new()
+}
+
+type Allow = dyn Fn(&str, u16) -> bool + Send + Sync;
+
+/// A forward proxy: a handler for clients configured to send their requests through the
+/// server.
+///
+/// Requests for `http://` URLs (absolute-form targets, like
+/// `GET http://example.com/ HTTP/1.1`) are forwarded to their server over pooled
+/// connections, without hop-by-hop headers and with the `Host` of the URL.
+/// `CONNECT example.com:443` opens a TCP tunnel to the destination and shuttles bytes
+/// both ways until either side closes it, which is how clients reach `https://` URLs.
+///
+/// Every destination is checked with the [`allow`](ForwardProxy::allow) hook first and
+/// refused with `403 Forbidden` if it says no. So are destinations that resolve to
+/// loopback, private or other internal addresses, unless
+/// [`allow_internal`](ForwardProxy::allow_internal) says otherwise: the proxy would
+/// otherwise let anyone reach the server's own network. Requests for the server itself
+/// (origin-form targets) are answered with `400 Bad Request`. Unreachable destinations
+/// are answered with `502 Bad Gateway`, slow ones with `504 Gateway Timeout`.
+///
+/// ```no_run
+/// use http::proxy::forward_proxy;
+/// use http::server::Server;
+///
+/// let proxy = forward_proxy().allow(|host, port| host.ends_with(".test") && port != 25);
+/// Server::threaded("127.0.0.1:3128", proxy, 16).listen().unwrap();
+/// ```
+pub struct ForwardProxy {
+    allow: Arc<Allow>,
+    config: Config,
+    pool: Arc<Pool>,
+}
+
+impl Default for ForwardProxy {
+    fn default() -> Self {
+        ForwardProxy {
+            allow: Arc::new(|_, _| true),
+            config: Config {
+                public_only: true,
+                ..Config::default()
+            },
+            pool: Arc::default(),
+        }
+    }
+}
+
+impl ForwardProxy {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decide which destinations, by host and port, clients may reach. Every host is
+    /// allowed by default, as long as it resolves to a public address.
+    ///
+    /// The hook sees the host as the client sent it, before it's resolved: refuse
+    /// internal destinations with [`allow_internal`](ForwardProxy::allow_internal)
+    /// rather than by name, which `localhost`, `127.1` or any DNS name get around.
+    #[must_use]
+    pub fn allow<F>(mut self, allow: F) -> Self
+    where
+        F: Fn(&str, u16) -> bool + Send + Sync + 'static,
+    {
+        self.allow = Arc::new(allow);
+        self
+    }
+
+    /// Whether clients may reach loopback, private, link-local and other non-public
+    /// addresses. Checked against the addresses a destination resolves to, refused by
+    /// default.
+    #[must_use]
+    pub fn allow_internal(mut self, allowed: bool) -> Self {
+        self.config.public_only = !allowed;
+        self
+    }
+
+    /// How long to wait for a connection to a destination, 5 seconds by default.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// How long to wait for a destination to accept or send any data, 30 seconds by
+    /// default. Tunnels can stay idle for any time.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// How many idle connections to keep open per destination, 8 by default.
+    #[must_use]
+    pub fn max_idle(mut self, connections: usize) -> Self {
+        self.config.max_idle = connections;
+        self
+    }
+
+    /// Open a tunnel to the authority of a `CONNECT` request.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-9.3.6>
+    fn connect(&self, host: &str, port: u16) -> Response {
+        let addr = socket_addr(host, port);
+        match connection::connect(&addr, &self.config) {
+            Ok(destination) => {
+                Response::upgrade(Status::Ok, Headers::default(), move |client| {
+                    if let Err(e) = tunnel(client, &destination) {
+                        eprintln!("Error tunneling to {addr}: {e}");
+                    }
+                })
+            }
+            Err(e) => {
+                eprintln!("Error connecting to {addr}: {e}");
+                upstream::error_response(&e)
+            }
+        }
+    }
+
+    /// The request line and headers sent to the destination.
+    fn head(request: &Request, authority: &str, chunked: bool) -> Vec<u8> {
+        let mut headers = request
+            .headers
+            .as_ref()
+            .map(Headers::from_hash_map)
+            .unwrap_or_default();
+        upstream::strip_hop_by_hop(&mut headers);
+        if chunked {
+            headers.insert("Transfer-Encoding", "chunked");
+        }
+        // The target's authority wins over the `Host` header.
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-3.2.2>
+        headers.insert("Host", authority);
+
+        let target = match request.query() {
+            Some(query) => format!("{}?{query}", request.path()),
+            None => request.path().to_string(),
+        };
+        format!("{} {target} HTTP/1.1\r\n{headers}\r\n", request.method).into_bytes()
+    }
+}
+
+impl Handler for ForwardProxy {
+    fn handle(&self, request: &mut Request) -> Response {
+        let uri = request.uri().clone();
+        let default_port = match (uri.form(), uri.scheme()) {
+            (TargetForm::Authority, _) => 0,
+            (TargetForm::Absolute, Some("http")) => 80,
+            (TargetForm::Absolute, _) => {
+                return Response::plain(
+                    Status::NotImplemented,
+                    "Only http:// URLs can be forwarded",
+                )
+            }
+            (TargetForm::Origin | TargetForm::Asterisk, _) => {
+                return Response::plain(Status::BadRequest, "Expected a proxy request")
+            }
+        };
+        let Some((host, port)) = uri
+            .authority()
+            .and_then(|authority| host_port(authority, default_port))
+        else {
+            return Response::plain(Status::BadRequest, "Invalid destination");
+        };
+        if !(self.allow)(&host, port) {
+            return Response::plain(Status::Forbidden, "Destination not allowed");
+        }
+
+        if request.method == Method::Connect {
+            return self.connect(&host, port);
+        }
+        let authority = match port {
+            80 => host.clone(),
+            _ => socket_addr(&host, port),
+        };
+        let chunked = super::sends_chunked(request);
+        let head = Self::head(request, &authority, chunked);
+        super::relay(
+            &self.pool,
+            &self.config,
+            &socket_addr(&host, port),
+            &head,
+            request,
+            chunked,
+        )
+    }
+}
+
+/// Split an authority into its lower-cased host, without the brackets of an IPv6 address,
+/// and its port. A missing port is `default_port`, unless that's `0`.
+fn host_port(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    // Credentials have no business in a request target.
+    if authority.contains('@') {
+        return None;
+    }
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest.split_once(']')?;
+            let port = match rest {
+                "" => None,
+                _ => Some(rest.strip_prefix(':')?),
+            };
+            (host, port)
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok().filter(|port| *port != 0)?,
+        None if default_port != 0 => default_port,
+        None => return None,
+    };
+    (!host.is_empty() && !host.contains(['[', ']', '/']))
+        .then(|| (host.to_ascii_lowercase(), port))
+}
+
+/// The `host:port` to connect to, with IPv6 addresses in brackets.
+fn socket_addr(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+/// Copy bytes both ways until one side is done, then close both.
+fn tunnel(client: Upgraded, destination: &TcpStream) -> io::Result<()> {
+    destination.set_read_timeout(None)?;
+    destination.set_write_timeout(None)?;
+    let mut to_client = client.try_clone()?;
+    let mut from_client = client;
+    let mut to_destination = destination.try_clone()?;
+
+    let upload = thread::spawn(move || {
+        let _ = io::copy(&mut from_client, &mut to_destination);
+        let _ = to_destination.shutdown(Shutdown::Write);
+    });
+    let result = io::copy(&mut &*destination, &mut to_client);
+    // Also ends the upload, if the client is still sending.
+    let _ = to_client.shutdown(Shutdown::Both);
+    let _ = upload.join();
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{forward_proxy, host_port};
+    use crate::request::Request;
+    use crate::response::{Headers, Response, Status};
+    use crate::server::{Handler, Server};
+    use std::io::{BufRead, BufReader, Cursor, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    #[test]
+    fn it_splits_destinations() {
+        assert_eq!(Some(("a.test".into(), 80)), host_port("A.test", 80));
+        assert_eq!(Some(("a.test".into(), 8080)), host_port("a.test:8080", 80));
+        assert_eq!(Some(("::1".into(), 443)), host_port("[::1]:443", 0));
+        assert_eq!(None, host_port("a.test", 0));
+        assert_eq!(None, host_port("user@a.test:80", 0));
+        assert_eq!(None, host_port("a.test:0", 80));
+        assert_eq!(None, host_port("[::1]443", 80));
+    }
+
+    #[test]
+    fn it_forwards_absolute_form_requests() {
+        let upstream = Server::new("127.0.0.1:0", |request: &mut Request| {
+            let echo = format!(
+                "{} {:?} {:?}",
+                request.uri(),
+                request.header("Host"),
+                request.header("Proxy-Authorization")
+            );
+            Response::new(Status::Ok, Headers::default(), echo)
+        });
+        let addr = upstream.local_addr().unwrap();
+        thread::spawn(move || upstream.listen());
+
+        let allowed = addr.port();
+        let proxy = forward_proxy()
+            .allow(move |host, port| host == "127.0.0.1" && port == allowed)
+            .allow_internal(true);
+        let input = format!(
+            "GET http://{addr}/a?b HTTP/1.1\r\nHost: other\r\nProxy-Authorization: Basic eA==\r\n\r\n"
+        );
+        let response = proxy.handle(&mut Request::from(Cursor::new(input)).unwrap());
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(
+            format!("/a?b Some(\"{addr}\") None").into_bytes(),
+            response.body.into_bytes().unwrap()
+        );
+
+        for (input, status) in [
+            (
+                "GET http://127.0.0.1:1/ HTTP/1.1\r\nHost: a\r\n\r\n",
+                Status::Forbidden,
+            ),
+            (
+                "CONNECT 127.0.0.1:1 HTTP/1.1\r\nHost: a\r\n\r\n",
+                Status::Forbidden,
+            ),
+            ("GET / HTTP/1.1\r\nHost: a\r\n\r\n", Status::BadRequest),
+            (
+                "GET https://a/ HTTP/1.1\r\nHost: a\r\n\r\n",
+                Status::NotImplemented,
+            ),
+        ] {
+            let mut request = Request::from(Cursor::new(input)).unwrap();
+            assert_eq!(status, proxy.handle(&mut request).status, "{input}");
+        }
+    }
+
+    #[test]
+    fn it_refuses_internal_addresses_by_default() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let proxy = forward_proxy();
+        for target in [
+            format!("GET http://127.0.0.1:{port}/ HTTP/1.1"),
+            format!("GET http://localhost:{port}/ HTTP/1.1"),
+            format!("GET http://127.1:{port}/ HTTP/1.1"),
+            format!("GET http://[::ffff:127.0.0.1]:{port}/ HTTP/1.1"),
+            format!("CONNECT localhost:{port} HTTP/1.1"),
+        ] {
+            let input = format!("{target}\r\nHost: a\r\n\r\n");
+            let mut request = Request::from(Cursor::new(input)).unwrap();
+            assert_eq!(
+                Status::Forbidden,
+                proxy.handle(&mut request).status,
+                "{target}"
+            );
+        }
+    }
+
+    #[test]
+    fn it_tunnels_connect_requests() {
+        let echo = TcpListener::bind("127.0.0.1:0").unwrap();
+        let destination = echo.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = echo.accept().unwrap();
+            let mut bytes = Vec::new();
+            stream.read_to_end(&mut bytes).unwrap();
+            stream.write_all(&bytes).unwrap();
+        });
+        let server = Server::new("127.0.0.1:0", forward_proxy().allow_internal(true));
+        let proxy = server.local_addr().unwrap();
+        thread::spawn(move || server.listen());
+
+        let mut client = TcpStream::connect(proxy).unwrap();
+        // Bytes sent right after the request belong to the tunnel.
+        write!(
+            client,
+            "CONNECT {destination} HTTP/1.1\r\nHost: {destination}\r\n\r\nhello, "
+        )
+        .unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!("HTTP/1.1 200 OK\r\n", line);
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+
+        client.write_all(b"tunnel").unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut echoed = String::new();
+        reader.read_to_string(&mut echoed).unwrap();
+        assert_eq!("hello, tunnel", echoed);
+    }
+}
@@ -0,0 +1,344 @@
+//! Forwarding requests to other servers, as a reverse [`Proxy`] in front of a server or
+//! a [`ForwardProxy`] for clients.
+
+mod forward;
+mod upstream;
+
+pub use forward::{forward_proxy, ForwardProxy};
+
+use crate::client::connection::{Config, Outgoing, Pool};
+use crate::request::{Method, Request};
+use crate::response::{Headers, Response};
+use crate::server::Handler;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Forward requests to the HTTP/1.1 server at `upstream`, see [`Proxy`].
+#[must_use]
+pub fn proxy(upstream: &str) -> Proxy {
+    Proxy::new(upstream)
+}
+
+/// A reverse proxy: a handler that forwards requests to an upstream HTTP/1.1 server, e.g.
+/// `127.0.0.1:8080`, and relays its responses.
+///
+/// The method, target, headers and body of the request are forwarded as they arrive, and
+/// the response body is streamed back as the upstream server sends it. Hop-by-hop headers
+/// such as `Connection` are dropped both ways, and the client is described to the
+/// upstream server with `Forwarded`, `X-Forwarded-For`, `X-Forwarded-Host` and
+/// `X-Forwarded-Proto`. The `Host` header is forwarded unchanged.
+///
+/// Upstream connections are kept open and reused. A server that can't be reached, fails
+/// or sends an invalid response is answered with `502 Bad Gateway`, one that's too slow
+/// with `504 Gateway Timeout`.
+///
+/// ```no_run
+/// use http::proxy::proxy;
+/// use http::server::Server;
+///
+/// Server::threaded("0.0.0.0:4000", proxy("127.0.0.1:8080"), 8)
+///     .listen()
+///     .unwrap();
+/// ```
+pub struct Proxy {
+    upstream: String,
+    config: Config,
+    pool: Arc<Pool>,
+}
+
+impl Proxy {
+    #[must_use]
+    pub fn new(upstream: &str) -> Self {
+        Proxy {
+            upstream: upstream.to_string(),
+            config: Config::default(),
+            pool: Arc::default(),
+        }
+    }
+
+    /// How long to wait for a connection to the upstream server, 5 seconds by default.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// How long to wait for the upstream server to accept or send any data, 30 seconds by
+    /// default.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// How many idle upstream connections to keep open, 8 by default.
+    #[must_use]
+    pub fn max_idle(mut self, connections: usize) -> Self {
+        self.config.max_idle = connections;
+        self
+    }
+
+    /// The request line and headers sent upstream.
+    fn head(request: &Request, chunked: bool) -> Vec<u8> {
+        let mut headers = request
+            .headers
+            .as_ref()
+            .map(Headers::from_hash_map)
+            .unwrap_or_default();
+        upstream::strip_hop_by_hop(&mut headers);
+        if chunked {
+            headers.insert("Transfer-Encoding", "chunked");
+        }
+        add_forwarded(request, &mut headers);
+
+        let target = match request.query() {
+            Some(query) => format!("{}?{query}", request.path()),
+            None => request.path().to_string(),
+        };
+        format!("{} {target} HTTP/1.1\r\n{headers}\r\n", request.method).into_bytes()
+    }
+}
+
+/// Describe the client and the request it sent to the upstream server, adding to what
+/// proxies in front of this one said.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7239>
+fn add_forwarded(request: &Request, headers: &mut Headers) {
+    let proto = if request.is_secure() { "https" } else { "http" };
+    let ip = request.remote_addr().map(|addr| addr.ip());
+    let host = request.header("Host");
+
+    let mut forwarded = Vec::new();
+    if let Some(ip) = ip {
+        forwarded.push(match ip {
+            IpAddr::V4(ip) => format!("for={ip}"),
+            IpAddr::V6(ip) => format!("for=\"[{ip}]\""),
+        });
+    }
+    if let Some(host) = host {
+        forwarded.push(format!("host={}", quote(host)));
+    }
+    forwarded.push(format!("proto={proto}"));
+    append_to_list(headers, "Forwarded", &forwarded.join(";"));
+
+    if let Some(ip) = ip {
+        append_to_list(headers, "X-Forwarded-For", &ip.to_string());
+    }
+    if let Some(host) = host {
+        if !headers.contains("X-Forwarded-Host") {
+            headers.insert("X-Forwarded-Host", host);
+        }
+    }
+    if !headers.contains("X-Forwarded-Proto") {
+        headers.insert("X-Forwarded-Proto", proto);
+    }
+}
+
+fn append_to_list(headers: &mut Headers, name: &str, value: &str) {
+    let value = match headers.get(name) {
+        Some(list) => format!("{list}, {value}"),
+        None => value.to_string(),
+    };
+    headers.insert(name, value);
+}
+
+/// A `Forwarded` parameter value, quoted unless it's a token.
+fn quote(value: &str) -> String {
+    if crate::request::is_token(value) {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+impl Handler for Proxy {
+    fn handle(&self, request: &mut Request) -> Response {
+        let chunked = sends_chunked(request);
+        let head = Self::head(request, chunked);
+        relay(
+            &self.pool,
+            &self.config,
+            &self.upstream,
+            &head,
+            request,
+            chunked,
+        )
+    }
+}
+
+/// Whether the body of `request` is sent upstream with chunked transfer coding: it has
+/// one, without a `Content-Length`, e.g. it was sent chunked or over HTTP/2. The head and
+/// the body sent upstream must agree, or the upstream server reads a corrupted body or a
+/// smuggled request.
+fn sends_chunked(request: &Request) -> bool {
+    request.body.is_some() && request.header("Content-Length").is_none()
+}
+
+/// Send `head` and the body of `request` to `addr`, and relay the response. `chunked`
+/// must be what `head` says, see [`sends_chunked`].
+fn relay(
+    pool: &Arc<Pool>,
+    config: &Config,
+    addr: &str,
+    head: &[u8],
+    request: &mut Request,
+    chunked: bool,
+) -> Response {
+    let head_request = request.method == Method::Head;
+    let has_body = request.body.is_some();
+    let mut reader = request.body_reader();
+    let body = has_body.then_some(Outgoing {
+        reader: &mut reader,
+        chunked,
+    });
+
+    upstream::forward(pool, config, addr, head, body, head_request).unwrap_or_else(|e| {
+        eprintln!("Error forwarding to {addr}: {e}");
+        upstream::error_response(&e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::Proxy;
+    use crate::request::Request;
+    use crate::response::{Headers, Response, Status};
+    use crate::server::{Handler, Server};
+    use std::fmt::Write as _;
+    use std::io::{BufRead, BufReader, Cursor, Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::thread;
+    use std::time::Duration;
+
+    fn request(input: &str) -> Request<'_> {
+        let mut request = Request::from(Cursor::new(input)).unwrap();
+        request.remote_addr = Some(SocketAddr::from(([10, 0, 0, 1], 5000)));
+        request
+    }
+
+    #[test]
+    fn it_forwards_requests_to_another_server() {
+        let upstream = Server::new("127.0.0.1:0", |request: &mut Request| {
+            let mut echo = format!("{} {}\n", request.method, request.uri());
+            for name in [
+                "Host",
+                "X-Secret",
+                "Forwarded",
+                "X-Forwarded-For",
+                "X-Forwarded-Proto",
+            ] {
+                writeln!(echo, "{name}: {:?}", request.header(name)).unwrap();
+            }
+            let mut body = String::new();
+            request.body_reader().read_to_string(&mut body).unwrap();
+            echo.push_str(&body);
+            Response::new(Status::Created, Headers::new("Keep-Alive: timeout=5"), echo)
+        });
+        let addr = upstream.local_addr().unwrap();
+        thread::spawn(move || upstream.listen());
+
+        let proxy = Proxy::new(&addr.to_string());
+        let mut request = request(
+            "POST /a?b=c HTTP/1.1\r\nHost: example.com\r\nConnection: X-Secret\r\nX-Secret: 1\r\nX-Forwarded-For: 1.2.3.4\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+        );
+        let response = proxy.handle(&mut request);
+        assert_eq!(Status::Created, response.status);
+        assert!(!response.headers.contains("Keep-Alive"));
+        assert_eq!(
+            "POST /a?b=c\n\
+             Host: Some(\"example.com\")\n\
+             X-Secret: None\n\
+             Forwarded: Some(\"for=10.0.0.1;host=example.com;proto=http\")\n\
+             X-Forwarded-For: Some(\"1.2.3.4, 10.0.0.1\")\n\
+             X-Forwarded-Proto: Some(\"http\")\n\
+             hello",
+            String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_frames_bodies_the_way_the_head_says() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Echoes the head and everything sent after it.
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(200)))
+                .unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut received = String::new();
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                received.push_str(&line);
+            }
+            let mut body = Vec::new();
+            let _ = reader.read_to_end(&mut body);
+            received.push_str(&String::from_utf8(body).unwrap());
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{received}",
+                received.len()
+            )
+            .unwrap();
+        });
+
+        let proxy = Proxy::new(&addr.to_string());
+        let mut request =
+            request("POST / HTTP/1.1\r\nHost: a\r\ncontent-length: 5\r\n\r\nhello");
+        let response = proxy.handle(&mut request);
+        let received = String::from_utf8(response.body.into_bytes().unwrap()).unwrap();
+        assert!(received.contains("content-length: 5\r\n"), "{received}");
+        assert!(!received.contains("Transfer-Encoding"), "{received}");
+        assert!(received.ends_with("\r\n\r\nhello"), "{received}");
+    }
+
+    #[test]
+    fn it_reuses_upstream_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Serves two requests on a single connection, then stops accepting.
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            for body in ["one", "two"] {
+                let mut line = String::new();
+                while line != "\r\n" {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+                write!(
+                    &stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n{body}"
+                )
+                .unwrap();
+            }
+            thread::sleep(Duration::from_secs(1));
+        });
+
+        let proxy = Proxy::new(&addr.to_string()).timeout(Duration::from_millis(500));
+        for expected in ["one", "two"] {
+            let response =
+                proxy.handle(&mut request("GET / HTTP/1.1\r\nHost: a\r\n\r\n"));
+            assert_eq!(Status::Ok, response.status);
+            assert_eq!(Some(3), response.body.content_length());
+            assert_eq!(expected.as_bytes(), response.body.into_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    fn it_answers_upstream_failures_with_502_and_504() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let proxy = Proxy::new(&addr.to_string()).timeout(Duration::from_millis(100));
+        let response = proxy.handle(&mut request("GET / HTTP/1.1\r\nHost: a\r\n\r\n"));
+        assert_eq!(Status::GatewayTimeout, response.status);
+
+        drop(listener);
+        let response = proxy.handle(&mut request("GET / HTTP/1.1\r\nHost: a\r\n\r\n"));
+        assert_eq!(Status::BadGateway, response.status);
+    }
+}
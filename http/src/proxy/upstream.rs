@@ -0,0 +1,91 @@
+use crate::client::connection::{self, Config, Outgoing, Pool};
+use crate::client::Error;
+use crate::response::{Body, Headers, Response, Status};
+use std::io;
+use std::sync::Arc;
+
+/// Headers that describe a single connection, never forwarded.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-7.6.1>
+const HOP_BY_HOP: [&str; 9] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Connection",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+/// Remove the hop-by-hop headers, including the ones listed in `Connection`.
+pub(crate) fn strip_hop_by_hop(headers: &mut Headers) {
+    let listed = headers
+        .get_all("Connection")
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    for name in HOP_BY_HOP
+        .iter()
+        .copied()
+        .chain(listed.iter().map(String::as_str))
+    {
+        headers.remove(name);
+    }
+}
+
+/// Send `head` and `body` upstream, and build the response relayed to the client.
+pub(crate) fn forward(
+    pool: &Arc<Pool>,
+    config: &Config,
+    addr: &str,
+    head: &[u8],
+    body: Option<Outgoing>,
+    head_request: bool,
+) -> Result<Response, Error> {
+    let mut response = connection::send(pool, config, addr, head, body, head_request)?;
+    if head_request && response.may_have_body() {
+        // The length of the body a `GET` would have gotten.
+        let length = response
+            .headers
+            .get("Content-Length")
+            .and_then(|length| length.trim().parse().ok());
+        if let Some(length) = length {
+            response.body = Body::sized(io::empty(), length);
+        }
+    }
+    strip_hop_by_hop(&mut response.headers);
+    Ok(response)
+}
+
+/// The response to a request that couldn't be forwarded: `504 Gateway Timeout` if the
+/// upstream server was too slow, `403 Forbidden` if its address isn't allowed, `502 Bad
+/// Gateway` otherwise.
+pub(crate) fn error_response(error: &Error) -> Response {
+    let status = match error {
+        Error::Timeout => Status::GatewayTimeout,
+        Error::AddressNotAllowed => Status::Forbidden,
+        _ => Status::BadGateway,
+    };
+    Response::plain(
+        status,
+        format!("Error reaching the upstream server: {error}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::strip_hop_by_hop;
+    use crate::response::Headers;
+
+    #[test]
+    fn it_strips_hop_by_hop_headers() {
+        let mut headers = Headers::new(
+            "Connection: close, X-Secret\r\nX-Secret: 1\r\nKeep-Alive: 5\r\nTransfer-Encoding: chunked\r\nX-Kept: 1",
+        );
+        strip_hop_by_hop(&mut headers);
+        assert_eq!(Headers::new("X-Kept: 1"), headers);
+    }
+}
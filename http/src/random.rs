@@ -0,0 +1,14 @@
+use std::fmt::Write;
+use std::io;
+
+/// `N` random bytes from the operating system, hex encoded.
+pub(crate) fn hex<const N: usize>() -> io::Result<String> {
+    let mut bytes = [0; N];
+    getrandom::fill(&mut bytes)?;
+    Ok(bytes
+        .iter()
+        .fold(String::with_capacity(N * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        }))
+}
@@ -1,4 +1,13 @@
-use std::io::BufRead;
+use crate::response::{Response, Status};
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+/// The largest chunk [`Body`] and [`ChunkedDecoder`](super::chunked::ChunkedDecoder)
+/// read at once, so large bodies are not buffered whole.
+pub(crate) const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The error a [`BodyDecoder`] yields for content past its [limit](BodyDecoder::limit).
+pub const TOO_LARGE: &str = "Body too large";
 
 #[allow(dead_code)]
 #[derive(Debug, PartialEq)]
@@ -18,13 +27,131 @@ pub trait BodyDecoder: Iterator<Item = Result<Chunk, &'static str>> {
         }
         res
     }
+
+    /// Yield [`TOO_LARGE`] instead of content past `bytes`, before reading it. Decoders
+    /// that can't tell the size ahead ignore it.
+    fn limit(&mut self, _bytes: u64) {}
+}
+
+/// Adapts a [`BodyDecoder`] to [`Read`], for consuming a body as a stream of bytes.
+#[expect(clippy::module_name_repetitions)]
+pub struct BodyReader<'r> {
+    decoder: Option<&'r mut dyn BodyDecoder>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl<'r> BodyReader<'r> {
+    /// A reader over `decoder`, or an empty reader for requests without a body.
+    #[must_use]
+    pub fn new(decoder: Option<&'r mut dyn BodyDecoder>) -> Self {
+        BodyReader {
+            decoder,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for BodyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            let Some(decoder) = self.decoder.as_mut() else {
+                return Ok(0);
+            };
+            match decoder.next() {
+                None => {
+                    self.decoder = None;
+                    return Ok(0);
+                }
+                Some(Err(e)) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, e))
+                }
+                Some(Ok(chunk)) => {
+                    self.chunk = chunk.buf;
+                    self.position = 0;
+                }
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Why a request body couldn't be extracted, see [`Request::form`](super::Request::form),
+/// [`Request::json_value`](super::Request::json_value) and
+/// [`Request::multipart`](super::Request::multipart).
+#[derive(Debug, PartialEq)]
+#[expect(clippy::module_name_repetitions)]
+pub enum BodyError {
+    /// The `Content-Type` is not the one the handler asked for.
+    UnsupportedMediaType,
+    /// The body couldn't be read or doesn't match its `Content-Type`.
+    Malformed(String),
+    /// The body exceeds a configured limit.
+    TooLarge,
+}
+
+impl BodyError {
+    /// The status a handler should respond with.
+    #[must_use]
+    pub fn status(&self) -> Status {
+        match self {
+            BodyError::UnsupportedMediaType => Status::UnsupportedMediaType,
+            BodyError::Malformed(_) => Status::BadRequest,
+            BodyError::TooLarge => Status::PayloadTooLarge,
+        }
+    }
+}
+
+impl fmt::Display for BodyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyError::UnsupportedMediaType => write!(f, "Unsupported Content-Type"),
+            BodyError::Malformed(reason) => write!(f, "Malformed body: {reason}"),
+            BodyError::TooLarge => write!(f, "Body too large"),
+        }
+    }
+}
+
+impl std::error::Error for BodyError {}
+
+/// Streaming readers report a `BodyError` through [`io::Error`], this gets it back.
+impl From<io::Error> for BodyError {
+    fn from(error: io::Error) -> Self {
+        if error
+            .get_ref()
+            .is_some_and(<dyn std::error::Error + Send + Sync>::is::<BodyError>)
+        {
+            #[expect(clippy::unwrap_used)]
+            return *error.into_inner().unwrap().downcast::<BodyError>().unwrap();
+        }
+        BodyError::Malformed(error.to_string())
+    }
+}
+
+impl From<BodyError> for io::Error {
+    fn from(error: BodyError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl From<BodyError> for Response {
+    fn from(error: BodyError) -> Self {
+        Response::plain(error.status(), error.to_string())
+    }
 }
 
 /// A type used for requests with a known body size, explicitly indicated by the Content-Length
 /// header.
 pub struct Body<B: BufRead> {
     buf: B,
+    /// Bytes left to read.
     length: usize,
+    limit: u64,
     done: bool,
 }
 
@@ -33,13 +160,14 @@ impl<B: BufRead> Body<B> {
         Body {
             buf,
             length,
+            limit: u64::MAX,
             done: false,
         }
     }
 }
 
-/// An iterator that is supposed to be used once, as the body is a single chunk with a known
-/// size.
+/// An iterator over the body, in chunks of at most 64 KiB. An empty body is a single
+/// empty chunk.
 impl<B: BufRead> Iterator for Body<B> {
     type Item = Result<Chunk, &'static str>;
 
@@ -47,20 +175,29 @@ impl<B: BufRead> Iterator for Body<B> {
         if self.done {
             return None;
         }
+        if self.length as u64 > self.limit {
+            self.done = true;
+            return Some(Err(TOO_LARGE));
+        }
 
         let mut chunk = Chunk {
-            buf: vec![0; self.length],
+            buf: vec![0; self.length.min(MAX_CHUNK_SIZE)],
             extension: String::new(),
         };
 
         if self.buf.read_exact(&mut chunk.buf).is_err() {
             self.done = true;
             return Some(Err("Expected a body"));
-        };
+        }
 
-        self.done = true;
+        self.length -= chunk.buf.len();
+        self.done = self.length == 0;
         Some(Ok(chunk))
     }
 }
 
-impl<B: BufRead> BodyDecoder for Body<B> {}
+impl<B: BufRead> BodyDecoder for Body<B> {
+    fn limit(&mut self, bytes: u64) {
+        self.limit = bytes;
+    }
+}
@@ -1,14 +1,20 @@
 #![allow(clippy::all)]
 
-use super::body::{BodyDecoder, Chunk};
+use super::body::{BodyDecoder, Chunk, MAX_CHUNK_SIZE, TOO_LARGE};
 use std::io::BufRead;
 
 /// A Chunked Transfer Decoder
 /// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-7.1>
+///
+/// Chunks larger than 64 KiB are yielded in pieces, the first one with the extension.
 #[expect(clippy::module_name_repetitions)]
 pub struct ChunkedDecoder<A: BufRead> {
     buf: A,
     stopped: bool,
+    /// Bytes left in the current chunk.
+    remaining: u64,
+    /// Bytes the chunks still to come may add up to, see [`BodyDecoder::limit`].
+    budget: u64,
 }
 
 #[allow(dead_code)]
@@ -17,8 +23,15 @@ impl<A: BufRead> ChunkedDecoder<A> {
         ChunkedDecoder {
             buf,
             stopped: false,
+            remaining: 0,
+            budget: u64::MAX,
         }
     }
+
+    /// Returns the reader, positioned after the chunks read so far.
+    pub fn into_inner(self) -> A {
+        self.buf
+    }
 }
 
 impl<A: BufRead> Iterator for ChunkedDecoder<A> {
@@ -30,12 +43,47 @@ impl<A: BufRead> Iterator for ChunkedDecoder<A> {
             return None;
         }
 
+        let extension = if self.remaining == 0 {
+            match self.read_size()? {
+                Ok(extension) => extension,
+                Err(e) => return Some(Err(e)),
+            }
+        } else {
+            String::new()
+        };
+
+        let piece = usize::try_from(self.remaining)
+            .map_or(MAX_CHUNK_SIZE, |remaining| remaining.min(MAX_CHUNK_SIZE));
+        let mut chunk = vec![0; piece];
+
+        if let Err(_) = self.buf.read_exact(&mut chunk) {
+            self.stopped = true;
+            return Some(Err("Expected a chunk"));
+        }
+        self.remaining -= chunk.len() as u64;
+
+        // Read CR LF
+        if self.remaining == 0 {
+            let mut skip = [0; 2];
+            self.buf.read_exact(&mut skip).ok()?;
+        }
+
+        Some(Ok(Chunk {
+            buf: chunk,
+            extension,
+        }))
+    }
+}
+
+impl<A: BufRead> ChunkedDecoder<A> {
+    /// Read the size line of the next chunk, returning its extension.
+    fn read_size(&mut self) -> Option<Result<String, &'static str>> {
         let mut line = String::new();
 
         if let Err(_) = self.buf.read_line(&mut line) {
             self.stopped = true;
             return Some(Err("Expected chunk size"));
-        };
+        }
 
         let line = line.trim();
 
@@ -53,38 +101,35 @@ impl<A: BufRead> Iterator for ChunkedDecoder<A> {
             Some((length, extension)) => (length.trim(), extension.trim()),
         };
 
-        #[allow(clippy::cast_possible_truncation)]
-        let chunk_size = if let Ok(size) = u64::from_str_radix(length, 16) {
-            size as usize
-        } else {
+        let Ok(chunk_size) = u64::from_str_radix(length, 16) else {
             self.stopped = true;
             return Some(Err("Invalid chunk size"));
         };
 
-        // If the chunk size is zero, mark the iterator as `stopped` but still return an empty chunk.
-        // The last chunk signals the end of the stream, but may include an extension.
-        if chunk_size == 0 {
+        // Fail before reading a chunk that doesn't fit, however large it claims to be.
+        if chunk_size > self.budget {
             self.stopped = true;
+            return Some(Err(TOO_LARGE));
         }
+        self.budget -= chunk_size;
+        self.remaining = chunk_size;
 
-        let mut chunk = vec![0; chunk_size];
-
-        if let Err(_) = self.buf.read_exact(&mut chunk) {
+        // If the chunk size is zero, mark the iterator as `stopped` but still return an
+        // empty chunk.
+        // The last chunk signals the end of the stream, but may include an extension.
+        if chunk_size == 0 {
             self.stopped = true;
-            return Some(Err("Expected a chunk"));
-        };
+        }
 
-        // Read CR LF
-        let mut skip = [0; 2];
-        self.buf.read_exact(&mut skip).ok()?;
+        Some(Ok(extension.to_string()))
+    }
+}
 
-        Some(Ok(Chunk {
-            buf: chunk,
-            extension: extension.to_string(),
-        }))
+impl<A: BufRead> BodyDecoder for ChunkedDecoder<A> {
+    fn limit(&mut self, bytes: u64) {
+        self.budget = bytes;
     }
 }
-impl<A: BufRead> BodyDecoder for ChunkedDecoder<A> {}
 
 #[cfg(test)]
 mod test {
@@ -189,4 +234,38 @@ This is exactly 18
         let chunks: Vec<Chunk> = decoder.map(|c| c.unwrap()).collect();
         assert_eq!(expected, chunks);
     }
+
+    #[test]
+    fn it_reads_large_chunks_in_pieces() {
+        let mut body = b"10001;ext\r\n".to_vec();
+        body.resize(body.len() + 0x10001, b'a');
+        body.extend(b"\r\n0\r\n\r\n");
+        let chunks: Vec<Chunk> = ChunkedDecoder::new(Cursor::new(body))
+            .map(|c| c.unwrap())
+            .collect();
+        assert_eq!(
+            vec![(0x10000, "ext"), (1, ""), (0, "")],
+            chunks
+                .iter()
+                .map(|c| (c.buf.len(), c.extension.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_fails_before_reading_past_its_limit() {
+        let body = "ffffffffffff\r\nabc";
+        let mut decoder = ChunkedDecoder::new(Cursor::new(body));
+        decoder.limit(1024);
+        assert_eq!(Some(Err(TOO_LARGE)), decoder.next());
+        assert_eq!(None, decoder.next());
+
+        let mut decoder = ChunkedDecoder::new(Cursor::new(body));
+        assert_eq!(Some(Err("Expected a chunk")), decoder.next());
+
+        let mut decoder = ChunkedDecoder::new(Cursor::new("3\r\nabc\r\n3\r\ndef\r\n"));
+        decoder.limit(5);
+        assert_eq!(b"abc".to_vec(), decoder.next().unwrap().unwrap().buf);
+        assert_eq!(Some(Err(TOO_LARGE)), decoder.next());
+    }
 }
@@ -0,0 +1,107 @@
+use super::uri::percent_decode;
+
+/// The fields of an `application/x-www-form-urlencoded` body.
+///
+/// A name can appear several times (e.g. a `<select multiple>`), so every value is kept,
+/// in the order it was sent.
+/// Spec: <https://url.spec.whatwg.org/#application/x-www-form-urlencoded>
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Form {
+    fields: Vec<(String, String)>,
+}
+
+impl Form {
+    /// # Errors
+    ///
+    /// Will error if the body is not valid UTF-8 after decoding, or has an invalid
+    /// percent-encoding.
+    pub fn parse(body: &[u8]) -> Result<Self, &'static str> {
+        let body = std::str::from_utf8(body).map_err(|_| "Form body is not UTF-8")?;
+        let fields = body
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                Ok((decode(name)?, decode(value)?))
+            })
+            .collect::<Result<_, &'static str>>()?;
+
+        Ok(Form { fields })
+    }
+
+    /// Returns the first value of the field `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns every value of the field `name`.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.fields
+            .iter()
+            .filter(move |(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|(k, _)| k == name)
+    }
+
+    /// Iterate over every `(name, value)` pair, in the order they were sent.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+fn decode(input: &str) -> Result<String, &'static str> {
+    String::from_utf8(percent_decode(&input.replace('+', " "))?)
+        .map_err(|_| "Form field is not UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::Form;
+
+    #[test]
+    fn it_parses_fields() {
+        let form =
+            Form::parse(b"name=Jane+Doe&email=jane%40example.com&empty=&flag").unwrap();
+
+        assert_eq!(4, form.len());
+        assert_eq!(Some("Jane Doe"), form.get("name"));
+        assert_eq!(Some("jane@example.com"), form.get("email"));
+        assert_eq!(Some(""), form.get("empty"));
+        assert_eq!(Some(""), form.get("flag"));
+        assert_eq!(None, form.get("missing"));
+    }
+
+    #[test]
+    fn it_keeps_repeated_fields() {
+        let form = Form::parse(b"tag=a&other=1&tag=b&&tag=c").unwrap();
+
+        assert_eq!(vec!["a", "b", "c"], form.get_all("tag").collect::<Vec<_>>());
+        assert_eq!(Some("a"), form.get("tag"));
+    }
+
+    #[test]
+    fn it_rejects_invalid_encodings() {
+        assert!(Form::parse(b"a=%zz").is_err());
+        assert!(Form::parse(b"a=%ff").is_err());
+        assert!(Form::parse(&[b'a', b'=', 0xff]).is_err());
+    }
+}
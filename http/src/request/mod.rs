@@ -1,16 +1,36 @@
 pub mod body;
 pub mod chunked;
+pub mod extensions;
+pub mod form;
+pub mod multipart;
+pub mod uri;
 
-use body::{Body, BodyDecoder};
+use crate::cookie::Cookies;
+use crate::json;
+use crate::response::{Headers, Response, Status};
+use crate::session::Session;
+use body::{Body, BodyDecoder, BodyError, BodyReader};
 use chunked::ChunkedDecoder;
+use extensions::Extensions;
+use form::Form;
+use multipart::Multipart;
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::Arc;
+use uri::{TargetForm, Uri};
+
+/// The largest body [`Request::form`], [`Request::json_value`] and `Request::json` read
+/// by default, see [`Request::set_max_body_size`].
+pub const DEFAULT_MAX_BODY_SIZE: u64 = 1024 * 1024;
 
 #[derive(Debug, PartialEq)]
 pub enum HttpVersion {
+    /// A simple request, `GET /path` without a version, answered with the body alone.
     V0_9,
     V1_0,
+    /// HTTP/1.1 and any later HTTP/1.x.
     V1_1,
     V2_0,
     V3_0,
@@ -35,19 +55,73 @@ impl std::fmt::Display for HttpVersion {
 impl FromStr for HttpVersion {
     type Err = &'static str;
 
+    /// Parse the version of an HTTP/1 request line, `HTTP/1.0` or `HTTP/1.1` and later
+    /// minor versions, which are treated as HTTP/1.1.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-2.5>
     fn from_str(input: &str) -> Result<HttpVersion, Self::Err> {
-        match input {
-            "HTTP/0.9" => Ok(HttpVersion::V0_9),
-            "HTTP/1.0" => Ok(HttpVersion::V1_0),
-            "HTTP/1.1" => Ok(HttpVersion::V1_1),
-            "HTTP/2.0" => Ok(HttpVersion::V2_0),
-            "HTTP/3.0" => Ok(HttpVersion::V3_0),
-            _ => Err("Unknown HTTP version"),
+        match input.as_bytes() {
+            b"HTTP/1.0" => Ok(HttpVersion::V1_0),
+            [b'H', b'T', b'T', b'P', b'/', b'1', b'.', minor]
+                if minor.is_ascii_digit() =>
+            {
+                Ok(HttpVersion::V1_1)
+            }
+            _ => Err("Unsupported HTTP version"),
+        }
+    }
+}
+
+/// Why a request couldn't be read.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RequestError {
+    /// The request is malformed.
+    BadRequest(String),
+    /// The request line has a version other than HTTP/1.x, e.g. `HTTP/2.0` in text.
+    VersionNotSupported,
+}
+
+impl RequestError {
+    #[must_use]
+    pub fn status(&self) -> Status {
+        match self {
+            RequestError::BadRequest(_) => Status::BadRequest,
+            RequestError::VersionNotSupported => Status::HttpVersionNotSupported,
+        }
+    }
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::BadRequest(reason) => write!(f, "{reason}"),
+            RequestError::VersionNotSupported => write!(f, "Only HTTP/1.x is supported"),
         }
     }
 }
 
-#[derive(PartialEq, Debug)]
+impl std::error::Error for RequestError {}
+
+impl From<String> for RequestError {
+    fn from(reason: String) -> Self {
+        RequestError::BadRequest(reason)
+    }
+}
+
+impl From<&str> for RequestError {
+    fn from(reason: &str) -> Self {
+        RequestError::BadRequest(reason.to_string())
+    }
+}
+
+impl From<RequestError> for Response {
+    fn from(error: RequestError) -> Self {
+        Response::plain(error.status(), error.to_string())
+    }
+}
+
+/// A request method.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-9>
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Method {
     Head,
     Get,
@@ -56,6 +130,10 @@ pub enum Method {
     Put,
     Patch,
     Delete,
+    Connect,
+    Trace,
+    /// Any other method, e.g. `PROPFIND`. Methods are case-sensitive.
+    Extension(String),
 }
 
 impl std::fmt::Display for Method {
@@ -71,6 +149,9 @@ impl std::fmt::Display for Method {
                 Method::Put => "PUT",
                 Method::Patch => "PATCH",
                 Method::Delete => "DELETE",
+                Method::Connect => "CONNECT",
+                Method::Trace => "TRACE",
+                Method::Extension(method) => method,
             }
         )
     }
@@ -88,39 +169,353 @@ impl FromStr for Method {
             "PUT" => Ok(Method::Put),
             "PATCH" => Ok(Method::Patch),
             "DELETE" => Ok(Method::Delete),
+            "CONNECT" => Ok(Method::Connect),
+            "TRACE" => Ok(Method::Trace),
+            _ if is_token(input) => Ok(Method::Extension(input.to_string())),
             _ => Err("Invalid HTTP Method"),
         }
     }
 }
 
+/// Returns `true` if `input` is a token, the syntax of methods and header names.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-5.6.2>
+pub(crate) fn is_token(input: &str) -> bool {
+    !input.is_empty() && input.bytes().all(is_tchar)
+}
+
+/// Returns `true` if `byte` can be part of a token.
+pub(crate) fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
 pub struct Request<'a> {
-    request_line: String,
     pub headers: Option<HashMap<String, String>>,
     pub body: Option<Box<dyn BodyDecoder + 'a>>,
-    path_slice: std::ops::Range<usize>,
-
-    #[allow(dead_code)]
-    pub query_string: &'a str,
+    uri: Uri,
     pub http_version: HttpVersion,
     pub method: Method,
+    /// Values attached by middlewares.
+    pub extensions: Extensions,
+    pub(crate) tls: Option<Arc<TlsInfo>>,
+    pub(crate) remote_addr: Option<SocketAddr>,
+    /// Bytes read past the head of a request without a body, see
+    /// [`Upgraded`](crate::server::Upgraded).
+    pub(crate) buffered: Vec<u8>,
+    /// Where interim responses go, for requests on an HTTP/1.1 connection.
+    pub(crate) interim: Option<Box<dyn Write + 'a>>,
+    max_body_size: u64,
+}
+
+/// The TLS connection a request was received on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// The name the client asked for with SNI.
+    pub server_name: Option<String>,
+    /// The protocol chosen with ALPN, e.g. `h2`.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// The DER certificate chain the client authenticated with, its own certificate
+    /// first.
+    pub client_certificates: Vec<Vec<u8>>,
 }
 
 impl<'a> Request<'a> {
+    /// A request that wasn't parsed from HTTP/1, e.g. one received on an HTTP/2 stream.
+    pub(crate) fn new(
+        method: Method,
+        uri: Uri,
+        http_version: HttpVersion,
+        headers: HashMap<String, String>,
+        body: Option<Box<dyn BodyDecoder + 'a>>,
+    ) -> Self {
+        Request {
+            headers: (!headers.is_empty()).then_some(headers),
+            body,
+            uri,
+            http_version,
+            method,
+            extensions: Extensions::default(),
+            tls: None,
+            remote_addr: None,
+            buffered: Vec::new(),
+            interim: None,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// The request target as sent by the client.
+    #[must_use]
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The path of the request target, still percent-encoded.
     #[must_use]
     pub fn path(&self) -> &str {
-        &self.request_line[self.path_slice.clone()]
+        self.uri.path()
     }
 
-    /// # Panics
+    /// The query string of the request target, without the `?`.
+    #[must_use]
+    pub fn query(&self) -> Option<&str> {
+        self.uri.query()
+    }
+
+    /// The absolute URI the request is for, built from the request target and the `Host`
+    /// header.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-3.3>
+    #[must_use]
+    pub fn effective_uri(&self) -> Uri {
+        let scheme = if self.is_secure() { "https" } else { "http" };
+        self.uri.effective(self.header("Host"), scheme)
+    }
+
+    /// The address of the client, or of the last proxy in front of the server.
+    #[must_use]
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Whether the request was received over TLS.
+    #[must_use]
+    pub fn is_secure(&self) -> bool {
+        self.tls.is_some()
+    }
+
+    /// The TLS connection the request was received on, if any.
+    #[must_use]
+    pub fn tls(&self) -> Option<&TlsInfo> {
+        self.tls.as_deref()
+    }
+
+    /// The DER certificate the client authenticated with, when the server asks for one.
+    #[must_use]
+    pub fn client_certificate(&self) -> Option<&[u8]> {
+        self.tls()?.client_certificates.first().map(Vec::as_slice)
+    }
+
+    /// Send an interim `1xx` response ahead of the final one, which follows on the same
+    /// connection. Returns `false` when the client can't be sent one, interim responses
+    /// are only sent to HTTP/1.1 clients.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-15.2>
     ///
-    /// Will panic if the buffer can't read lines until CRLF CRLF, will change in the future.
+    /// # Errors
     ///
+    /// Will return an error if `status` isn't informational, or is `101 Switching
+    /// Protocols` which is a final response here, see [`Response::upgrade`]. Or if
+    /// writing to the connection fails.
+    pub fn send_interim(
+        &mut self,
+        status: Status,
+        headers: &Headers,
+    ) -> io::Result<bool> {
+        if !status.is_informational() || status == Status::SwitchingProtocols {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't an interim response", status.code()),
+            ));
+        }
+        let Some(writer) = self.interim.as_mut() else {
+            return Ok(false);
+        };
+        Response::new(status, headers.clone(), "").write_bare_head_to(writer)?;
+        Ok(true)
+    }
+
+    /// Send `103 Early Hints`, e.g. with `Link: </style.css>; rel=preload` headers, so
+    /// the client can start fetching while the final response is computed.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc8297>
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to the connection fails.
+    pub fn early_hints(&mut self, headers: &Headers) -> io::Result<bool> {
+        self.send_interim(Status::EarlyHints, headers)
+    }
+
+    /// Returns the value of the header `name`, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .as_ref()?
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the comma-separated header `name` lists `token`, compared
+    /// case-insensitively.
+    pub(crate) fn header_lists(&self, name: &str, token: &str) -> bool {
+        self.header(name).is_some_and(|value| {
+            value
+                .split(',')
+                .any(|listed| listed.trim().eq_ignore_ascii_case(token))
+        })
+    }
+
+    /// The id of the last [server-sent event](crate::sse) a reconnecting client received.
+    #[must_use]
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.header("Last-Event-ID")
+    }
+
+    /// Parse the `Cookie` header.
+    #[must_use]
+    pub fn cookies(&self) -> Cookies {
+        self.header("Cookie")
+            .map(Cookies::parse)
+            .unwrap_or_default()
+    }
+
+    /// The client's session, when the server is wrapped with
+    /// [`Sessions`](crate::session::Sessions).
+    pub fn session(&mut self) -> Option<&mut Session> {
+        self.extensions.get_mut()
+    }
+
+    /// Limit the bodies [`Request::form`], [`Request::json_value`] and `Request::json`
+    /// read to `bytes`, [`DEFAULT_MAX_BODY_SIZE`] by default. See
+    /// [`Server::max_body_size`](crate::server::Server::max_body_size) to set it for
+    /// every request. Streamed bodies aren't limited.
+    pub fn set_max_body_size(&mut self, bytes: u64) {
+        self.max_body_size = bytes;
+    }
+
+    /// Parse an `application/x-www-form-urlencoded` body.
+    ///
+    /// A request without a body is an empty form.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`BodyError::UnsupportedMediaType`] if the `Content-Type` is not
+    /// `application/x-www-form-urlencoded`, with [`BodyError::TooLarge`] if the body is
+    /// larger than the [maximum](Request::set_max_body_size), and with
+    /// [`BodyError::Malformed`] if the body can't be read or decoded.
+    pub fn form(&mut self) -> Result<Form, BodyError> {
+        if !self.has_media_type(|t| t == "application/x-www-form-urlencoded") {
+            return Err(BodyError::UnsupportedMediaType);
+        }
+        Form::parse(&self.body_bytes()?).map_err(|e| BodyError::Malformed(e.to_string()))
+    }
+
+    /// Parse an `application/json` (or `application/*+json`) body.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`BodyError::UnsupportedMediaType`] if the `Content-Type` is not
+    /// JSON, with [`BodyError::TooLarge`] if the body is larger than the
+    /// [maximum](Request::set_max_body_size), and with [`BodyError::Malformed`] if the
+    /// body can't be read or is not valid JSON.
+    pub fn json_value(&mut self) -> Result<json::Value, BodyError> {
+        let body = self.json_body()?;
+        let body = std::str::from_utf8(&body)
+            .map_err(|_| BodyError::Malformed("JSON is not UTF-8".to_string()))?;
+        body.parse()
+            .map_err(|e: &str| BodyError::Malformed(e.to_string()))
+    }
+
+    /// Deserialize an `application/json` (or `application/*+json`) body into `T`.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`BodyError::UnsupportedMediaType`] if the `Content-Type` is not
+    /// JSON, with [`BodyError::TooLarge`] if the body is larger than the
+    /// [maximum](Request::set_max_body_size), and with [`BodyError::Malformed`] if the
+    /// body can't be read or deserialized.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::de::DeserializeOwned>(&mut self) -> Result<T, BodyError> {
+        serde_json::from_slice(&self.json_body()?)
+            .map_err(|e| BodyError::Malformed(e.to_string()))
+    }
+
+    /// Stream a `multipart/form-data` body, part by part.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`BodyError::UnsupportedMediaType`] if the `Content-Type` is not
+    /// `multipart/form-data`, and with [`BodyError::Malformed`] if it has no valid
+    /// boundary.
+    pub fn multipart(&mut self) -> Result<Multipart<BodyReader<'_>>, BodyError> {
+        if !self.has_media_type(|t| t == "multipart/form-data") {
+            return Err(BodyError::UnsupportedMediaType);
+        }
+        let boundary = self
+            .header("Content-Type")
+            .and_then(|content_type| {
+                content_type.split(';').skip(1).find_map(|parameter| {
+                    let (key, value) = parameter.split_once('=')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("boundary")
+                        .then(|| value.trim().trim_matches('"').to_string())
+                })
+            })
+            // RFC: <https://datatracker.ietf.org/doc/html/rfc2046#section-5.1.1>
+            .filter(|boundary| (1..=70).contains(&boundary.len()))
+            .ok_or_else(|| {
+                BodyError::Malformed("Invalid multipart boundary".to_string())
+            })?;
+
+        Ok(Multipart::new(self.body_reader(), &boundary))
+    }
+
+    /// Read the body as a stream of bytes, regardless of how it's framed.
+    pub fn body_reader(&mut self) -> BodyReader<'_> {
+        BodyReader::new(
+            self.body
+                .as_deref_mut()
+                .map(|body| body as &mut dyn BodyDecoder),
+        )
+    }
+
+    fn json_body(&mut self) -> Result<Vec<u8>, BodyError> {
+        if !self.has_media_type(|t| {
+            t == "application/json"
+                || t.strip_prefix("application/")
+                    .is_some_and(|t| t.ends_with("+json"))
+        }) {
+            return Err(BodyError::UnsupportedMediaType);
+        }
+        self.body_bytes()
+    }
+
+    /// Whether the media type of the `Content-Type` header (lower-cased, without
+    /// parameters) satisfies `predicate`.
+    fn has_media_type(&self, predicate: impl Fn(&str) -> bool) -> bool {
+        self.header("Content-Type").is_some_and(|content_type| {
+            let media_type = content_type.split(';').next().unwrap_or_default();
+            predicate(&media_type.trim().to_lowercase())
+        })
+    }
+
+    /// The whole body, failing rather than buffering more than `max_body_size` bytes.
+    fn body_bytes(&mut self) -> Result<Vec<u8>, BodyError> {
+        let mut bytes = Vec::new();
+        let Some(body) = self.body.as_mut() else {
+            return Ok(bytes);
+        };
+        body.limit(self.max_body_size);
+        for chunk in body {
+            let mut chunk = chunk.map_err(|e| match e {
+                body::TOO_LARGE => BodyError::TooLarge,
+                e => BodyError::Malformed(e.to_string()),
+            })?;
+            if (bytes.len() + chunk.buf.len()) as u64 > self.max_body_size {
+                return Err(BodyError::TooLarge);
+            }
+            bytes.append(&mut chunk.buf);
+        }
+        Ok(bytes)
+    }
+
     /// # Errors
     ///
+    /// Will error if the request line or a header is malformed.
+    /// Will error with [`RequestError::VersionNotSupported`] for versions other than
+    /// HTTP/1.x.
+    /// Will error if an HTTP/1.1 request has no `Host` header, or any request has
+    /// several.
     /// Will error if Content-Length is not a number.
     /// Will error if the chunk size is not a hex number.
-    pub fn from<R: Read + 'a>(stream: R) -> Result<Self, String> {
-        // TODO: Support url-encoding
+    pub fn from<R: Read + 'a>(stream: R) -> Result<Self, RequestError> {
         let mut buf = BufReader::new(stream);
         let mut lines = buf.by_ref().lines();
 
@@ -128,12 +523,32 @@ impl<'a> Request<'a> {
             .next()
             .ok_or("Expected request line")?
             .map_err(|_| "Couldn't get request line")?;
+        let (method, uri, version) = parse_request_line(&request_line)?;
+
+        // A simple request has no headers, the request line is all there is.
+        if version == HttpVersion::V0_9 {
+            return Ok(Request {
+                headers: None,
+                body: None,
+                uri,
+                http_version: version,
+                method,
+                extensions: Extensions::default(),
+                tls: None,
+                remote_addr: None,
+                buffered: buf.buffer().to_vec(),
+                interim: None,
+                max_body_size: DEFAULT_MAX_BODY_SIZE,
+            });
+        }
 
-        #[expect(clippy::unwrap_used)]
         let mut lines = lines
-            // TODO: don't use unwrap
-            .take_while(|line| !line.as_ref().unwrap().is_empty())
+            .take_while(|line| !matches!(line, Ok(line) if line.is_empty()))
             .peekable();
+        let mut hosts = 0;
+        // Framing headers are kept as received, repeats would collapse in the map.
+        let mut lengths = Vec::new();
+        let mut codings = Vec::new();
 
         // TODO: Parse headers only when asked to.
         // This will pose a challenge to internally used headers such as Content-Length,
@@ -144,8 +559,22 @@ impl<'a> Request<'a> {
             let mut map: HashMap<String, String> = HashMap::new();
             lines.try_for_each(|line| -> Result<(), String> {
                 let binding = line.map_err(|_| "Expected a header".to_string())?;
-                let mut pair = binding.split(':');
-                if let (Some(key), Some(value)) = (pair.next(), pair.next()) {
+                // Values may contain colons, e.g. dates and `Host: localhost:4000`.
+                if let Some((key, value)) =
+                    binding.split_once(':').filter(|(key, _)| is_token(key))
+                {
+                    if key.eq_ignore_ascii_case("Host") {
+                        hosts += 1;
+                    } else if key.eq_ignore_ascii_case("Content-Length") {
+                        lengths.extend(value.split(',').map(|v| v.trim().to_string()));
+                    } else if key.eq_ignore_ascii_case("Transfer-Encoding") {
+                        codings.extend(
+                            value
+                                .split(',')
+                                .map(|coding| coding.trim().to_lowercase())
+                                .filter(|coding| !coding.is_empty()),
+                        );
+                    }
                     // TODO: Store headers in lower-case.
                     // TODO: Store both `Referer` and `Referrer`
                     map.insert(key.to_string(), value.trim().to_string());
@@ -157,74 +586,115 @@ impl<'a> Request<'a> {
             Some(map)
         };
 
-        let body: Option<Box<dyn BodyDecoder>> = match headers {
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-3.2>
+        if hosts > 1 || (hosts == 0 && version == HttpVersion::V1_1) {
+            return Err("Missing or duplicate Host header".into());
+        }
+
+        // A body framed two ways could be read one way here and another by a server the
+        // request is relayed to.
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-6.3>
+        let chunked = !codings.is_empty();
+        if chunked && !lengths.is_empty() {
+            return Err("Both Transfer-Encoding and Content-Length".into());
+        }
+        if codings.last().is_some_and(|coding| coding != "chunked") {
+            return Err("The final transfer coding isn't chunked".into());
+        }
+        let mut lengths = lengths.iter();
+        let length = lengths.next();
+        if lengths.any(|other| Some(other) != length) {
+            return Err("Conflicting Content-Length headers".into());
+        }
+        let length = match length {
+            // TODO: Handle isize::MAX and a max body size.
+            Some(length) => Some(
+                length
+                    .parse::<usize>()
+                    .map_err(|_| "Content-Length is not a number")?,
+            ),
             None => None,
-            Some(ref headers) => {
-                if let Some(length) = headers.get("Content-Length") {
-                    // TODO: Handle isize::MAX and a max body size.
-                    let length: usize = length
-                        .parse()
-                        .map_err(|_| "Content-Length is not a number")?;
-                    Some(Box::new(Body::new(length, buf)))
-                } else if let Some(encoding) =
-                    headers.get("Transfer-Encoding").map(|h| h.to_lowercase())
-                {
-                    if encoding.as_str() == "chunked" {
-                        Some(Box::new(ChunkedDecoder::new(buf)))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            }
         };
 
-        let (method, uri, version) = parse_request_line(&request_line)?;
+        // Without a body, whatever the reader buffered past the head belongs to the
+        // connection, and is handed over with it on an upgrade.
+        let (body, buffered): (Option<Box<dyn BodyDecoder>>, _) = match length {
+            Some(length) => (Some(Box::new(Body::new(length, buf))), Vec::new()),
+            None if chunked => (Some(Box::new(ChunkedDecoder::new(buf))), Vec::new()),
+            None => (None, buf.buffer().to_vec()),
+        };
 
         Ok(Request {
-            request_line,
             headers,
             body,
-            path_slice: uri,
-            query_string: "",
+            uri,
             http_version: version,
             method,
+            extensions: Extensions::default(),
+            tls: None,
+            remote_addr: None,
+            buffered,
+            interim: None,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
         })
     }
 }
 
 fn parse_request_line(
     request_line: &str,
-) -> Result<(Method, std::ops::Range<usize>, HttpVersion), &'static str> {
-    let mut parts = request_line.splitn(3, ' ');
-
-    let method = parts
-        .next()
-        .and_then(|m| Method::from_str(m).ok())
-        .ok_or("Invalid request line")?;
-    let path = parts.next().ok_or("Invalid request line")?;
-    let version = parts
-        .next()
-        .and_then(|v| HttpVersion::from_str(v).ok())
-        .ok_or("Invalid request line")?;
-
-    #[expect(clippy::expect_used)]
-    Ok((
-        method,
-        request_line.substr_range(path).expect(
-            "Range should always be `Some` due to `path` being a slice of `request_line`",
-        ),
-        version,
-    ))
+) -> Result<(Method, Uri, HttpVersion), RequestError> {
+    let parts = request_line.split(' ').collect::<Vec<_>>();
+
+    let (method, target, version) = match parts[..] {
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc1945#section-4.1>
+        ["GET", target] => ("GET", target, HttpVersion::V0_9),
+        [method, target, version] => {
+            let version = HttpVersion::from_str(version).map_err(|_| {
+                if is_version(version) {
+                    RequestError::VersionNotSupported
+                } else {
+                    RequestError::from("Invalid request line")
+                }
+            })?;
+            (method, target, version)
+        }
+        _ => return Err("Invalid request line".into()),
+    };
+    let method = Method::from_str(method).map_err(|_| "Invalid request line")?;
+    let uri = Uri::parse(target).map_err(|_| "Invalid request target")?;
+
+    // The authority form is only for CONNECT, and the asterisk form only for OPTIONS.
+    let valid = match uri.form() {
+        TargetForm::Origin | TargetForm::Absolute => method != Method::Connect,
+        TargetForm::Authority => method == Method::Connect,
+        TargetForm::Asterisk => method == Method::Options,
+    };
+    if !valid {
+        return Err("Invalid request target".into());
+    }
+
+    Ok((method, uri, version))
+}
+
+/// Whether `version` looks like an HTTP version, `HTTP/2` or `HTTP/1.1`, supported or
+/// not.
+fn is_version(version: &str) -> bool {
+    version.strip_prefix("HTTP/").is_some_and(|number| {
+        let (major, minor) = number.split_once('.').unwrap_or((number, "0"));
+        [major, minor]
+            .iter()
+            .all(|part| part.len() == 1 && part.as_bytes()[0].is_ascii_digit())
+    })
 }
 
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
     extern crate test;
-    use super::{HttpVersion, Method, Request};
+    use super::{BodyError, HttpVersion, Method, Request, RequestError, TlsInfo};
+    use crate::response::{Headers, Status};
     use std::io::Cursor;
+    use std::sync::Arc;
     use test::{black_box, Bencher};
 
     #[test]
@@ -237,64 +707,169 @@ mod tests {
         );
         assert_eq!(
             HttpVersion::V1_1,
-            Request::from(Cursor::new("GET / HTTP/1.1\r\n\r\n"))
+            Request::from(Cursor::new("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .unwrap()
+                .http_version
+        );
+        assert_eq!(
+            HttpVersion::V1_1,
+            Request::from(Cursor::new("GET / HTTP/1.2\r\nHost: localhost\r\n\r\n"))
                 .unwrap()
                 .http_version
         );
         assert_eq!(
             HttpVersion::V0_9,
-            Request::from(Cursor::new("GET / HTTP/0.9\r\n\r\n"))
+            Request::from(Cursor::new("GET /index.html\r\n"))
                 .unwrap()
                 .http_version
         );
     }
 
+    #[test]
+    fn it_rejects_other_versions() {
+        for line in [
+            "GET / HTTP/2.0",
+            "GET / HTTP/2",
+            "GET / HTTP/3.0",
+            "GET / HTTP/0.9",
+        ] {
+            let request = Request::from(Cursor::new(format!("{line}\r\n\r\n")));
+            assert_eq!(Some(RequestError::VersionNotSupported), request.err());
+        }
+        for line in [
+            "GET / HTTP/1.10",
+            "GET / http/1.1",
+            "POST /",
+            "GET / HTTP/1.1 x",
+        ] {
+            let request = Request::from(Cursor::new(format!("{line}\r\n\r\n")));
+            assert!(matches!(request, Err(RequestError::BadRequest(_))));
+        }
+    }
+
     #[test]
     fn it_parses_the_correct_method() {
         assert_eq!(
             Method::Get,
-            Request::from(Cursor::new("GET / HTTP/1.1\r\n\r\n"))
+            Request::from(Cursor::new("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
                 .unwrap()
                 .method
         );
         assert_eq!(
             Method::Post,
-            Request::from(Cursor::new("POST / HTTP/1.1\r\n\r\n"))
+            Request::from(Cursor::new("POST / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
                 .unwrap()
                 .method
         );
         assert_eq!(
             Method::Head,
-            Request::from(Cursor::new("HEAD / HTTP/1.1\r\n\r\n"))
+            Request::from(Cursor::new("HEAD / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
                 .unwrap()
                 .method
         );
         assert_eq!(
             Method::Put,
-            Request::from(Cursor::new("PUT / HTTP/1.1\r\n\r\n"))
+            Request::from(Cursor::new("PUT / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
                 .unwrap()
                 .method
         );
         assert_eq!(
             Method::Patch,
-            Request::from(Cursor::new("PATCH / HTTP/1.1\r\n\r\n"))
+            Request::from(Cursor::new("PATCH / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
                 .unwrap()
                 .method
         );
+        assert_eq!(
+            Method::Trace,
+            Request::from(Cursor::new("TRACE / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .unwrap()
+                .method
+        );
+        assert_eq!(
+            Method::Extension("PROPFIND".to_string()),
+            Request::from(Cursor::new(
+                "PROPFIND / HTTP/1.1\r\nHost: localhost\r\n\r\n"
+            ))
+            .unwrap()
+            .method
+        );
+        assert_eq!(
+            Method::Extension("get".to_string()),
+            Request::from(Cursor::new("get / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .unwrap()
+                .method
+        );
+        assert!(
+            Request::from(Cursor::new("GE(T / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .is_err()
+        );
     }
 
     #[test]
     fn it_parses_a_get_request() {
-        let body = String::from("GET / HTTP/1.1\r\n\r\n");
+        let body = String::from("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
         let request = Request::from(Cursor::new(body));
         let request = request.unwrap();
 
         assert_eq!(Method::Get, request.method);
         assert_eq!(HttpVersion::V1_1, request.http_version);
-        assert_eq!(None, request.headers);
+        assert_eq!(Some("localhost"), request.header("Host"));
         assert_eq!("/".to_string(), request.path());
     }
 
+    #[test]
+    fn it_parses_request_targets() {
+        let mut request = Request::from(Cursor::new(
+            "GET /a?b=c HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        ))
+        .unwrap();
+        assert_eq!(("/a", Some("b=c")), (request.path(), request.query()));
+        assert_eq!(
+            "http://example.com/a?b=c",
+            request.effective_uri().to_string()
+        );
+        assert!(!request.is_secure());
+        request.tls = Some(Arc::new(TlsInfo::default()));
+        assert_eq!(
+            "https://example.com/a?b=c",
+            request.effective_uri().to_string()
+        );
+        assert_eq!(None, request.client_certificate());
+
+        let request = Request::from(Cursor::new(
+            "GET http://example.com/a HTTP/1.1\r\nHost: proxy\r\n\r\n",
+        ))
+        .unwrap();
+        assert_eq!("http://example.com/a", request.effective_uri().to_string());
+
+        for valid in [
+            "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n",
+            "OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        ] {
+            assert!(Request::from(Cursor::new(valid)).is_ok(), "{valid}");
+        }
+        for invalid in [
+            "GET example.com:443 HTTP/1.1\r\nHost: example.com\r\n\r\n",
+            "CONNECT / HTTP/1.1\r\nHost: example.com\r\n\r\n",
+            "GET * HTTP/1.1\r\nHost: example.com\r\n\r\n",
+        ] {
+            assert!(Request::from(Cursor::new(invalid)).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn it_requires_a_single_host() {
+        assert!(Request::from(Cursor::new("GET / HTTP/1.1\r\n\r\n")).is_err());
+        assert!(Request::from(Cursor::new(
+            "GET / HTTP/1.1\r\nHost: a\r\nhost: b\r\n\r\n"
+        ))
+        .is_err());
+        assert!(Request::from(Cursor::new("GET / HTTP/1.0\r\n\r\n")).is_ok());
+        assert!(
+            Request::from(Cursor::new("GET / HTTP/1.1\r\nHost : a\r\n\r\n")).is_err()
+        );
+    }
+
     #[test]
     fn it_parses_a_get_request_with_headers() {
         let body = String::from(
@@ -306,9 +881,25 @@ mod tests {
         assert_eq!(Method::Get, request.method);
         assert_eq!(HttpVersion::V1_1, request.http_version);
         assert!(request.headers.is_some());
+        assert_eq!(Some("localhost:80"), request.header("host"));
         assert_eq!("/".to_string(), request.path());
     }
 
+    #[test]
+    fn it_keeps_the_bytes_read_past_a_bodiless_request() {
+        let request = Request::from(Cursor::new(
+            "GET / HTTP/1.1\r\nHost: a\r\nUpgrade: x\r\n\r\n\x00\x01hello",
+        ))
+        .unwrap();
+        assert_eq!(b"\x00\x01hello".to_vec(), request.buffered);
+
+        let request = Request::from(Cursor::new(
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 2\r\n\r\nhi",
+        ))
+        .unwrap();
+        assert!(request.buffered.is_empty());
+    }
+
     #[test]
     fn it_parses_a_post_request_with_body() {
         let body = String::from("POST / HTTP/1.1\r\nHost: localhost:80\r\nContent-Length: 10\r\n\r\n0123456789");
@@ -325,6 +916,167 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_frames_bodies_whatever_the_case_of_the_header() {
+        let request = Request::from(Cursor::new(
+            "POST / HTTP/1.1\r\nHost: a\r\ncontent-length: 2\r\n\r\nhi",
+        ));
+        assert_eq!(b"hi".to_vec(), request.unwrap().body.unwrap().all_bytes());
+        let request = Request::from(Cursor::new(
+            "POST / HTTP/1.1\r\nHost: a\r\ntransfer-encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n",
+        ));
+        assert_eq!(b"hi".to_vec(), request.unwrap().body.unwrap().all_bytes());
+        assert!(Request::from(Cursor::new(
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 2\r\ncontent-length: 5\r\n\r\nhi",
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn it_rejects_ambiguous_framing() {
+        for (headers, reason) in [
+            (
+                "Content-Length: 5\r\nContent-Length: 2",
+                "Conflicting Content-Length headers",
+            ),
+            ("Content-Length: 2, 5", "Conflicting Content-Length headers"),
+            (
+                "Content-Length: 2\r\nTransfer-Encoding: chunked",
+                "Both Transfer-Encoding and Content-Length",
+            ),
+            (
+                "Transfer-Encoding: chunked, gzip",
+                "The final transfer coding isn't chunked",
+            ),
+            (
+                "Transfer-Encoding: chunked\r\nTransfer-Encoding: identity",
+                "The final transfer coding isn't chunked",
+            ),
+        ] {
+            let raw = format!("POST / HTTP/1.1\r\nHost: a\r\n{headers}\r\n\r\n2\r\nhi");
+            assert_eq!(
+                Some(RequestError::BadRequest(reason.to_string())),
+                Request::from(Cursor::new(raw)).err(),
+                "{headers}"
+            );
+        }
+
+        let request = Request::from(Cursor::new(
+            "POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 2\r\nContent-Length: 2\r\n\r\nhi",
+        ));
+        assert_eq!(b"hi".to_vec(), request.unwrap().body.unwrap().all_bytes());
+        let request = Request::from(Cursor::new(
+            "POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: gzip, chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n",
+        ));
+        assert_eq!(b"hi".to_vec(), request.unwrap().body.unwrap().all_bytes());
+    }
+
+    #[test]
+    fn it_parses_a_form_body() {
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 25\r\n\r\nname=Jane+Doe&tag=a&tag=b";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        let form = request.form().unwrap();
+
+        assert_eq!(Some("Jane Doe"), form.get("name"));
+        assert_eq!(vec!["a", "b"], form.get_all("tag").collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn it_parses_a_json_body() {
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\ncontent-type: application/vnd.api+json; charset=utf-8\r\nContent-Length: 13\r\n\r\n{\"id\": [1.5]}";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+
+        assert_eq!(
+            Some(1.5),
+            request
+                .json_value()
+                .unwrap()
+                .get("id")
+                .unwrap()
+                .as_array()
+                .unwrap()[0]
+                .as_f64()
+        );
+    }
+
+    #[test]
+    fn it_limits_the_size_of_bodies_read_whole() {
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/x-www-form-urlencoded\r\nTransfer-Encoding: chunked\r\n\r\n4\r\na=bc\r\n4\r\n&d=e\r\n0\r\n\r\n";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        request.set_max_body_size(7);
+        let error = request.form().unwrap_err();
+        assert_eq!(BodyError::TooLarge, error);
+        assert_eq!(413, error.status().code());
+
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        request.set_max_body_size(8);
+        assert_eq!(Some("e"), request.form().unwrap().get("d"));
+
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffff\r\n{}";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        assert_eq!(Err(BodyError::TooLarge), request.json_value());
+
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 99999999999\r\n\r\n{}";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        assert_eq!(Err(BodyError::TooLarge), request.json_value());
+    }
+
+    #[test]
+    fn it_rejects_bodies_of_the_wrong_type() {
+        let body =
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\nContent-Length: 3\r\n\r\na=b";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        let error = request.form().unwrap_err();
+        assert_eq!(BodyError::UnsupportedMediaType, error);
+        assert_eq!(415, error.status().code());
+        assert_eq!(
+            Err(BodyError::UnsupportedMediaType),
+            Request::from(Cursor::new("POST / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .unwrap()
+                .json_value()
+        );
+
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 5\r\n\r\n{\"a\":";
+        let error = Request::from(Cursor::new(body))
+            .unwrap()
+            .json_value()
+            .unwrap_err();
+        assert!(matches!(error, BodyError::Malformed(_)));
+        assert_eq!(400, error.status().code());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_deserializes_a_json_body() {
+        let body = "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 10\r\n\r\n{\"a\": \"b\"}";
+        let mut request = Request::from(Cursor::new(body)).unwrap();
+        let map: std::collections::HashMap<String, String> = request.json().unwrap();
+
+        assert_eq!(Some(&"b".to_string()), map.get("a"));
+    }
+
+    #[test]
+    fn it_sends_interim_responses_ahead_of_the_final_one() {
+        let hints = Headers::new("Link: </style.css>; rel=preload; as=style");
+        let mut output = Vec::new();
+        let mut request =
+            Request::from(Cursor::new("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .unwrap();
+        assert!(!request.early_hints(&hints).unwrap());
+
+        request.interim = Some(Box::new(&mut output));
+        assert!(request.early_hints(&hints).unwrap());
+        assert!(request.send_interim(Status::Ok, &hints).is_err());
+        assert!(request
+            .send_interim(Status::SwitchingProtocols, &hints)
+            .is_err());
+        drop(request);
+        assert_eq!(
+            "HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload; as=style\r\n\r\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
     // BENCHMARKS
     //
     #[bench]
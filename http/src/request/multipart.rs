@@ -0,0 +1,484 @@
+use super::body::BodyError;
+use super::uri::percent_decode;
+use std::io::{self, Read};
+
+/// How much is read from the body at once.
+const READ_SIZE: usize = 8 * 1024;
+
+/// A streaming `multipart/form-data` parser.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc7578>
+///
+/// Parts are yielded one at a time, and each part's content is read straight from the
+/// body, so an upload is never buffered whole. Reading the next part skips whatever is
+/// left of the current one.
+///
+/// ```no_run
+/// # use http::request::Request;
+/// # use http::request::body::BodyError;
+/// # fn handler(request: &mut Request) -> Result<(), BodyError> {
+/// let mut multipart = request.multipart()?.max_parts(10);
+/// let mut uploads = 0;
+/// while let Some(mut part) = multipart.next_part()? {
+///     if part.filename().is_some() {
+///         // The filename is whatever the client sent, e.g. `../../etc/passwd`, never
+///         // use it as a path: name the file on the server.
+///         uploads += 1;
+///         let mut file = std::fs::File::create(format!("/tmp/upload-{uploads}"))?;
+///         std::io::copy(&mut part, &mut file)?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Multipart<R: Read> {
+    reader: R,
+    /// `CRLF--boundary`, which precedes every part and the closing delimiter.
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    eof: bool,
+    state: State,
+    parts: usize,
+    max_parts: usize,
+    max_part_size: u64,
+    max_header_size: usize,
+}
+
+#[derive(PartialEq)]
+enum State {
+    /// Reading the content of a part (or the preamble), up to the next delimiter.
+    Content,
+    /// Right after a delimiter, which is either followed by a part's headers or `--`.
+    Delimiter,
+    Done,
+}
+
+impl<R: Read> Multipart<R> {
+    /// Parse the parts of `reader`, delimited by `boundary` (from the `Content-Type`).
+    pub fn new(reader: R, boundary: &str) -> Self {
+        Multipart {
+            reader,
+            delimiter: format!("\r\n--{boundary}").into_bytes(),
+            // The first delimiter isn't preceded by a CRLF when there is no preamble.
+            buf: b"\r\n".to_vec(),
+            eof: false,
+            state: State::Content,
+            parts: 0,
+            max_parts: 100,
+            max_part_size: 16 * 1024 * 1024,
+            max_header_size: 8 * 1024,
+        }
+    }
+
+    /// Reject bodies with more than `count` parts, the default is 100.
+    #[must_use]
+    pub fn max_parts(mut self, count: usize) -> Self {
+        self.max_parts = count;
+        self
+    }
+
+    /// Reject parts larger than `bytes`, the default is 16 MiB.
+    #[must_use]
+    pub fn max_part_size(mut self, bytes: u64) -> Self {
+        self.max_part_size = bytes;
+        self
+    }
+
+    /// Reject parts whose headers are larger than `bytes`, the default is 8 KiB.
+    #[must_use]
+    pub fn max_header_size(mut self, bytes: usize) -> Self {
+        self.max_header_size = bytes;
+        self
+    }
+
+    /// Returns the next part, or `None` after the closing delimiter.
+    ///
+    /// # Errors
+    ///
+    /// Will error with [`BodyError::TooLarge`] if a limit is exceeded, and with
+    /// [`BodyError::Malformed`] if the body is not valid multipart.
+    pub fn next_part(&mut self) -> Result<Option<Part<'_, R>>, BodyError> {
+        // Skip the rest of the current part.
+        let mut discard = [0; READ_SIZE];
+        while self.state == State::Content {
+            self.read_content(&mut discard)?;
+        }
+        if self.state == State::Done {
+            return Ok(None);
+        }
+
+        self.fill(2)?;
+        if self.buf.starts_with(b"--") {
+            self.state = State::Done;
+            return Ok(None);
+        }
+        // Linear whitespace may follow the boundary.
+        self.fill(self.max_header_size)?;
+        let padding = self
+            .buf
+            .iter()
+            .take_while(|b| **b == b' ' || **b == b'\t')
+            .count();
+        if !self.buf[padding..].starts_with(b"\r\n") {
+            return Err(malformed("Expected CRLF after the boundary"));
+        }
+        self.buf.drain(..padding + 2);
+
+        if self.parts == self.max_parts {
+            return Err(BodyError::TooLarge);
+        }
+        self.parts += 1;
+
+        let headers = self.read_headers()?;
+        self.state = State::Content;
+        Ok(Some(Part::new(self, headers)))
+    }
+
+    /// Read the headers of a part, up to and including the empty line.
+    fn read_headers(&mut self) -> Result<Vec<(String, String)>, BodyError> {
+        self.fill(self.max_header_size + 2)?;
+        let end = if self.buf.starts_with(b"\r\n") {
+            0
+        } else {
+            match find(&self.buf, b"\r\n\r\n") {
+                Some(end) if end <= self.max_header_size => end + 2,
+                None if self.eof && self.buf.len() <= self.max_header_size => {
+                    return Err(malformed("Unexpected end of part headers"));
+                }
+                _ => return Err(BodyError::TooLarge),
+            }
+        };
+
+        let headers = std::str::from_utf8(&self.buf[..end])
+            .map_err(|_| malformed("Part headers are not UTF-8"))?
+            .split_terminator("\r\n")
+            .map(|line| {
+                line.split_once(':')
+                    .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                    .ok_or_else(|| malformed("Malformed part header"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.buf.drain(..end + 2);
+        Ok(headers)
+    }
+
+    /// Read content up to the next delimiter, returns 0 once it's reached.
+    fn read_content(&mut self, out: &mut [u8]) -> Result<usize, BodyError> {
+        if self.state != State::Content {
+            return Ok(0);
+        }
+        self.fill(self.delimiter.len() + READ_SIZE)?;
+
+        let available = match find(&self.buf, &self.delimiter) {
+            Some(0) => {
+                self.buf.drain(..self.delimiter.len());
+                self.state = State::Delimiter;
+                return Ok(0);
+            }
+            Some(index) => index,
+            None if self.eof => {
+                return Err(malformed("Unexpected end of multipart body"))
+            }
+            // The end of the buffer might be the start of a delimiter.
+            None => self.buf.len() - (self.delimiter.len() - 1),
+        };
+
+        let n = available.min(out.len());
+        out[..n].copy_from_slice(&self.buf[..n]);
+        self.buf.drain(..n);
+        Ok(n)
+    }
+
+    /// Read from the body until at least `size` bytes are buffered, or the body ends.
+    fn fill(&mut self, size: usize) -> Result<(), BodyError> {
+        let mut chunk = [0; READ_SIZE];
+        while self.buf.len() < size && !self.eof {
+            match self.reader.read(&mut chunk)? {
+                0 => self.eof = true,
+                n => self.buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single part of a multipart body. Its content is read through [`Read`].
+pub struct Part<'m, R: Read> {
+    multipart: &'m mut Multipart<R>,
+    headers: Vec<(String, String)>,
+    name: Option<String>,
+    filename: Option<String>,
+    read: u64,
+}
+
+impl<'m, R: Read> Part<'m, R> {
+    fn new(multipart: &'m mut Multipart<R>, headers: Vec<(String, String)>) -> Self {
+        let disposition = headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("Content-Disposition"))
+            .map(|(_, v)| parse_parameters(v))
+            .unwrap_or_default();
+        let parameter = |name: &str| {
+            disposition
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+        };
+
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc5987#section-3.2>
+        let extended_filename = parameter("filename*").and_then(|value| {
+            let (charset, rest) = value.split_once('\'')?;
+            let (_language, encoded) = rest.split_once('\'')?;
+            if !charset.eq_ignore_ascii_case("UTF-8") {
+                return None;
+            }
+            String::from_utf8(percent_decode(encoded).ok()?).ok()
+        });
+
+        Part {
+            name: parameter("name"),
+            filename: extended_filename.or_else(|| parameter("filename")),
+            multipart,
+            headers,
+            read: 0,
+        }
+    }
+
+    /// Returns the value of the part header `name`, compared case-insensitively.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[must_use]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// The form field name, from `Content-Disposition`.
+    #[must_use]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The uploaded file's name, for file inputs.
+    ///
+    /// This is whatever the client sent, it must not be trusted as a path.
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// The part's `Content-Type`, which defaults to `text/plain`.
+    #[must_use]
+    pub fn content_type(&self) -> &str {
+        self.header("Content-Type").unwrap_or("text/plain")
+    }
+
+    /// Read the remaining content into a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the content can't be read or is not UTF-8.
+    pub fn text(&mut self) -> Result<String, BodyError> {
+        let mut text = String::new();
+        self.read_to_string(&mut text)?;
+        Ok(text)
+    }
+}
+
+impl<R: Read> Read for Part<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.multipart.read_content(buf)?;
+        self.read += n as u64;
+        if self.read > self.multipart.max_part_size {
+            return Err(BodyError::TooLarge.into());
+        }
+        Ok(n)
+    }
+}
+
+/// Parse the `; key=value` parameters of a header such as `Content-Disposition`, values
+/// may be quoted strings.
+fn parse_parameters(value: &str) -> Vec<(String, String)> {
+    let mut parameters = Vec::new();
+    let mut rest = value.split_once(';').map_or("", |(_, rest)| rest);
+
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim_start_matches([';', ' ', '\t']).trim().to_string();
+        let after = after.trim_start();
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            (value, &quoted[end..])
+        } else {
+            let end = after.find(';').unwrap_or(after.len());
+            (after[..end].trim().to_string(), &after[end..])
+        };
+        parameters.push((key, value));
+        rest = remaining;
+    }
+
+    parameters
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn malformed(reason: &str) -> BodyError {
+    BodyError::Malformed(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{parse_parameters, BodyError, Multipart};
+    use std::io::{Cursor, Read};
+
+    const BODY: &str = "preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"name\"\r\n\
+        \r\n\
+        Jane\r\n\
+        --XyZ  \r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".txt\"\r\n\
+        Content-Type: application/octet-stream\r\n\
+        \r\n\
+        line 1\r\n--Xy not a boundary\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"empty\"\r\n\
+        \r\n\
+        \r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    /// A reader that returns a single byte per read, to split delimiters across reads.
+    struct Trickle<R: Read>(R);
+
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    fn parts<R: Read>(
+        mut multipart: Multipart<R>,
+    ) -> Vec<(String, Option<String>, String)> {
+        let mut parts = Vec::new();
+        while let Some(mut part) = multipart.next_part().unwrap() {
+            let content = part.text().unwrap();
+            parts.push((
+                part.name().unwrap().to_string(),
+                part.filename().map(String::from),
+                content,
+            ));
+        }
+        parts
+    }
+
+    #[test]
+    fn it_parses_each_part() {
+        let expected = vec![
+            ("name".to_string(), None, "Jane".to_string()),
+            (
+                "file".to_string(),
+                Some("a \"b\".txt".to_string()),
+                "line 1\r\n--Xy not a boundary".to_string(),
+            ),
+            ("empty".to_string(), None, String::new()),
+        ];
+
+        assert_eq!(expected, parts(Multipart::new(Cursor::new(BODY), "XyZ")));
+        assert_eq!(
+            expected,
+            parts(Multipart::new(Trickle(Cursor::new(BODY)), "XyZ"))
+        );
+    }
+
+    #[test]
+    fn it_skips_unread_parts() {
+        let mut multipart = Multipart::new(Cursor::new(BODY), "XyZ");
+        let mut names = Vec::new();
+        while let Some(part) = multipart.next_part().unwrap() {
+            names.push(part.name().unwrap().to_string());
+        }
+        assert_eq!(vec!["name", "file", "empty"], names);
+    }
+
+    #[test]
+    fn it_exposes_part_headers() {
+        let mut multipart = Multipart::new(Cursor::new(BODY), "XyZ");
+        let part = multipart.next_part().unwrap().unwrap();
+        assert_eq!("text/plain", part.content_type());
+
+        let part = multipart.next_part().unwrap().unwrap();
+        assert_eq!("application/octet-stream", part.content_type());
+        assert_eq!(2, part.headers().len());
+    }
+
+    #[test]
+    fn it_enforces_limits() {
+        let mut multipart = Multipart::new(Cursor::new(BODY), "XyZ").max_parts(2);
+        multipart.next_part().unwrap();
+        multipart.next_part().unwrap();
+        assert_eq!(Some(BodyError::TooLarge), multipart.next_part().err());
+
+        let mut multipart = Multipart::new(Cursor::new(BODY), "XyZ").max_part_size(5);
+        assert_eq!(
+            "Jane",
+            multipart.next_part().unwrap().unwrap().text().unwrap()
+        );
+        assert_eq!(
+            Some(BodyError::TooLarge),
+            multipart.next_part().unwrap().unwrap().text().err()
+        );
+
+        let mut multipart = Multipart::new(Cursor::new(BODY), "XyZ").max_header_size(16);
+        assert_eq!(Some(BodyError::TooLarge), multipart.next_part().err());
+    }
+
+    #[test]
+    fn it_rejects_truncated_bodies() {
+        let body = "--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nno end";
+        let mut multipart = Multipart::new(Cursor::new(body), "XyZ");
+        let mut part = multipart.next_part().unwrap().unwrap();
+        assert!(matches!(part.text(), Err(BodyError::Malformed(_))));
+        assert!(Multipart::new(Cursor::new(""), "XyZ").next_part().is_err());
+    }
+
+    #[test]
+    fn it_parses_disposition_parameters() {
+        assert_eq!(
+            vec![
+                ("name".to_string(), "a;b".to_string()),
+                ("filename*".to_string(), "UTF-8''%C3%A9.txt".to_string()),
+            ],
+            parse_parameters("form-data; name=\"a;b\"; filename*=UTF-8''%C3%A9.txt")
+        );
+
+        let body = "--b\r\nContent-Disposition: form-data; name=f; filename=\"e.txt\"; filename*=UTF-8''%C3%A9.txt\r\n\r\n\r\n--b--";
+        let mut multipart = Multipart::new(Cursor::new(body), "b");
+        assert_eq!(
+            Some("é.txt"),
+            multipart.next_part().unwrap().unwrap().filename()
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::fmt::{self, Write};
+
+/// Which of the four forms of request target a [`Uri`] was sent in.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-3.2>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetForm {
+    /// `/where?q=now`, the usual form.
+    Origin,
+    /// `http://www.example.org/pub/WWW/`, sent to proxies.
+    Absolute,
+    /// `www.example.com:80`, only used by `CONNECT`.
+    Authority,
+    /// `*`, only used by `OPTIONS` for the whole server.
+    Asterisk,
+}
+
+/// A request target, split into its components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uri {
+    form: TargetForm,
+    scheme: Option<String>,
+    authority: Option<String>,
+    path: String,
+    query: Option<String>,
+}
+
+impl Uri {
+    /// Parse a request target in any of its four forms.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the target is empty, contains whitespace, control characters or a
+    /// fragment, or isn't in one of the four forms.
+    pub fn parse(target: &str) -> Result<Self, &'static str> {
+        if target.is_empty()
+            || target.bytes().any(|b| b <= b' ' || b == 0x7f || b == b'#')
+        {
+            return Err("Invalid request target");
+        }
+
+        if target == "*" {
+            return Ok(Uri {
+                form: TargetForm::Asterisk,
+                scheme: None,
+                authority: None,
+                path: "*".to_string(),
+                query: None,
+            });
+        }
+        if target.starts_with('/') {
+            let (path, query) = split_query(target);
+            return Ok(Uri {
+                form: TargetForm::Origin,
+                scheme: None,
+                authority: None,
+                path: path.to_string(),
+                query: query.map(String::from),
+            });
+        }
+
+        if let Some((scheme, rest)) = target.split_once("://") {
+            let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.bytes().all(|b| {
+                    b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.')
+                });
+            if !valid_scheme {
+                return Err("Invalid request target");
+            }
+            let end = rest.find(['/', '?']).unwrap_or(rest.len());
+            let (authority, rest) = rest.split_at(end);
+            if authority.is_empty() {
+                return Err("Invalid request target");
+            }
+            let (path, query) = split_query(rest);
+            return Ok(Uri {
+                form: TargetForm::Absolute,
+                scheme: Some(scheme.to_ascii_lowercase()),
+                authority: Some(authority.to_string()),
+                // An empty path means the root.
+                path: if path.is_empty() { "/" } else { path }.to_string(),
+                query: query.map(String::from),
+            });
+        }
+
+        // `host:port`, the port is required.
+        match target.rsplit_once(':') {
+            Some((host, port))
+                if !host.is_empty()
+                    && !port.is_empty()
+                    && port.bytes().all(|b| b.is_ascii_digit())
+                    && !target.contains(['/', '?', '@']) =>
+            {
+                Ok(Uri {
+                    form: TargetForm::Authority,
+                    scheme: None,
+                    authority: Some(target.to_string()),
+                    path: String::new(),
+                    query: None,
+                })
+            }
+            _ => Err("Invalid request target"),
+        }
+    }
+
+    #[must_use]
+    pub fn form(&self) -> TargetForm {
+        self.form
+    }
+
+    /// The scheme in lower-case, e.g. `http`.
+    #[must_use]
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// The `host[:port]` part.
+    #[must_use]
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// The path, still percent-encoded. Empty for the authority form and `*` for the
+    /// asterisk form.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The query string, without the `?`.
+    #[must_use]
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Build the effective request URI from the target, the `Host` header and the scheme
+    /// the request was received over.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-3.3>
+    #[must_use]
+    pub fn effective(&self, host: Option<&str>, scheme: &str) -> Uri {
+        if self.form == TargetForm::Absolute {
+            return self.clone();
+        }
+        let path = match self.form {
+            TargetForm::Origin => self.path.clone(),
+            // The server itself, or the tunnel's destination.
+            _ => String::new(),
+        };
+        Uri {
+            form: TargetForm::Absolute,
+            scheme: Some(scheme.to_string()),
+            authority: match self.form {
+                TargetForm::Authority => self.authority.clone(),
+                _ => host.map(String::from),
+            },
+            path,
+            query: self.query.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{scheme}://")?;
+        }
+        if let Some(authority) = &self.authority {
+            write!(f, "{authority}")?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "?{query}")?;
+        }
+        Ok(())
+    }
+}
+
+fn split_query(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (target, None),
+    }
+}
+
+/// Decode `%XX` escapes.
+///
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc3986#section-2.1>
+///
+/// # Errors
+///
+/// Will error if a `%` isn't followed by two hex digits.
+pub fn percent_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = input.bytes();
+    let mut decoded = Vec::with_capacity(input.len());
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next(), bytes.next()];
+            let [Some(high), Some(low)] = hex.map(|b| b.and_then(hex_value)) else {
+                return Err("Invalid percent-encoding");
+            };
+            decoded.push(high << 4 | low);
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Encode every byte except the unreserved characters as `%XX`.
+///
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc3986#section-2.3>
+#[must_use]
+pub fn percent_encode(input: &[u8]) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for &byte in input {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    #[expect(clippy::cast_possible_truncation)]
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{percent_decode, percent_encode, TargetForm, Uri};
+
+    #[test]
+    fn it_parses_the_four_target_forms() {
+        let uri = Uri::parse("/where?q=now").unwrap();
+        assert_eq!(TargetForm::Origin, uri.form());
+        assert_eq!(("/where", Some("q=now")), (uri.path(), uri.query()));
+
+        let uri = Uri::parse("HTTP://www.example.org:8080?x").unwrap();
+        assert_eq!(TargetForm::Absolute, uri.form());
+        assert_eq!(Some("http"), uri.scheme());
+        assert_eq!(Some("www.example.org:8080"), uri.authority());
+        assert_eq!(("/", Some("x")), (uri.path(), uri.query()));
+        assert_eq!("http://www.example.org:8080/?x", uri.to_string());
+
+        let uri = Uri::parse("www.example.com:443").unwrap();
+        assert_eq!(TargetForm::Authority, uri.form());
+        assert_eq!(Some("www.example.com:443"), uri.authority());
+        assert_eq!(
+            TargetForm::Authority,
+            Uri::parse("[::1]:443").unwrap().form()
+        );
+
+        assert_eq!(TargetForm::Asterisk, Uri::parse("*").unwrap().form());
+
+        for invalid in [
+            "",
+            "where",
+            "www.example.com",
+            "host:",
+            "1http://a/",
+            "http:///a",
+            "/a#b",
+            "/a\tb",
+        ] {
+            assert!(Uri::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn it_builds_the_effective_request_uri() {
+        let effective = |target, host| {
+            Uri::parse(target)
+                .unwrap()
+                .effective(host, "http")
+                .to_string()
+        };
+        assert_eq!(
+            "http://example.com:8080/a?b",
+            effective("/a?b", Some("example.com:8080"))
+        );
+        assert_eq!(
+            "http://proxy.test/a",
+            effective("http://proxy.test/a", Some("ignored"))
+        );
+        assert_eq!("http://example.com:443", effective("example.com:443", None));
+        assert_eq!("http://example.com", effective("*", Some("example.com")));
+    }
+
+    #[test]
+    fn it_decodes_percent_escapes() {
+        assert_eq!(Ok(b"a b/c".to_vec()), percent_decode("a%20b%2fc"));
+        assert_eq!(Ok("é".as_bytes().to_vec()), percent_decode("%C3%A9"));
+        assert_eq!(Ok(b"100%".to_vec()), percent_decode("100%25"));
+    }
+
+    #[test]
+    fn it_encodes_reserved_characters() {
+        assert_eq!("a%20b%2Fc-._~", percent_encode(b"a b/c-._~"));
+        assert_eq!("%C3%A9", percent_encode("é".as_bytes()));
+        let input = "key=value&100%\n";
+        assert_eq!(
+            Ok(input.as_bytes().to_vec()),
+            percent_decode(&percent_encode(input.as_bytes()))
+        );
+    }
+
+    #[test]
+    fn it_rejects_truncated_escapes() {
+        assert!(percent_decode("100%").is_err());
+        assert!(percent_decode("%2").is_err());
+        assert!(percent_decode("%zz").is_err());
+    }
+}
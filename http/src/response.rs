@@ -1,186 +0,0 @@
-use std::collections::HashMap;
-use std::fmt;
-
-pub struct Headers {
-    pub headers: String,
-}
-
-impl Headers {
-    #[allow(dead_code)]
-    #[must_use]
-    pub fn from_hash_map(map: &HashMap<String, String>) -> Self {
-        let headers = map
-            .iter()
-            .map(|(k, v)| format!("{k}: {v}"))
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        Self::new(&headers)
-    }
-
-    #[must_use]
-    pub fn new(headers: &str) -> Self {
-        Headers {
-            headers: headers.to_string(),
-        }
-    }
-}
-
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-pub enum Status {
-    // Informational responses (100–199)
-    Continue = 100,
-    SwitchingProtocols = 101,
-    Processing = 102,
-
-    // Successful responses (200–299)
-    Ok = 200,
-    Created = 201,
-    Accepted = 202,
-    NonAuthoritativeInformation = 203,
-    NoContent = 204,
-    ResetContent = 205,
-    PartialContent = 206,
-    #[allow(clippy::enum_variant_names)]
-    MultiStatus = 207,
-    AlreadyReported = 208,
-    ImUsed = 226,
-
-    // Redirection messages (300–399)
-    MultipleChoices = 300,
-    MovedPermanently = 301,
-    Found = 302,
-    SeeOther = 303,
-    NotModified = 304,
-    UseProxy = 305,
-    TemporaryRedirect = 307,
-    PermanentRedirect = 308,
-
-    // Client error responses (400–499)
-    BadRequest = 400,
-    Unauthorized = 401,
-    PaymentRequired = 402,
-    Forbidden = 403,
-    NotFound = 404,
-    MethodNotAllowed = 405,
-    NotAcceptable = 406,
-    ProxyAuthenticationRequired = 407,
-    RequestTimeout = 408,
-    Conflict = 409,
-    Gone = 410,
-    LengthRequired = 411,
-    PreconditionFailed = 412,
-    PayloadTooLarge = 413,
-    UriTooLong = 414,
-    UnsupportedMediaType = 415,
-    RangeNotSatisfiable = 416,
-    ExpectationFailed = 417,
-    Teapot = 418,
-    MisdirectedRequest = 421,
-    UnprocessableEntity = 422,
-    Locked = 423,
-    FailedDependency = 424,
-    UpgradeRequired = 426,
-    PreconditionRequired = 428,
-    TooManyRequests = 429,
-    RequestHeaderFieldsTooLarge = 431,
-    UnavailableForLegalReasons = 451,
-
-    // Server error responses (500–599)
-    InternalServerError = 500,
-    NotImplemented = 501,
-    BadGateway = 502,
-    ServiceUnavailable = 503,
-    GatewayTimeout = 504,
-    HttpVersionNotSupported = 505,
-    VariantAlsoNegotiates = 506,
-    InsufficientStorage = 507,
-    LoopDetected = 508,
-    NotExtended = 510,
-    NetworkAuthenticationRequired = 511,
-}
-
-impl fmt::Display for Status {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let description = match self {
-            Status::Continue => "Continue",
-            Status::SwitchingProtocols => "Switching Protocols",
-            Status::Processing => "Processing",
-            Status::Ok => "OK",
-            Status::Created => "Created",
-            Status::Accepted => "Accepted",
-            Status::NonAuthoritativeInformation => "Non-Authoritative Information",
-            Status::NoContent => "No Content",
-            Status::ResetContent => "Reset Content",
-            Status::PartialContent => "Partial Content",
-            Status::MultiStatus => "Multi-Status",
-            Status::AlreadyReported => "Already Reported",
-            Status::ImUsed => "IM Used",
-            Status::MultipleChoices => "Multiple Choices",
-            Status::MovedPermanently => "Moved Permanently",
-            Status::Found => "Found",
-            Status::SeeOther => "See Other",
-            Status::NotModified => "Not Modified",
-            Status::UseProxy => "Use Proxy",
-            Status::TemporaryRedirect => "Temporary Redirect",
-            Status::PermanentRedirect => "Permanent Redirect",
-            Status::BadRequest => "Bad Request",
-            Status::Unauthorized => "Unauthorized",
-            Status::PaymentRequired => "Payment Required",
-            Status::Forbidden => "Forbidden",
-            Status::NotFound => "Not Found",
-            Status::MethodNotAllowed => "Method Not Allowed",
-            Status::NotAcceptable => "Not Acceptable",
-            Status::ProxyAuthenticationRequired => "Proxy Authentication Required",
-            Status::RequestTimeout => "Request Timeout",
-            Status::Conflict => "Conflict",
-            Status::Gone => "Gone",
-            Status::LengthRequired => "Length Required",
-            Status::PreconditionFailed => "Precondition Failed",
-            Status::PayloadTooLarge => "Payload Too Large",
-            Status::UriTooLong => "URI Too Long",
-            Status::UnsupportedMediaType => "Unsupported Media Type",
-            Status::RangeNotSatisfiable => "Range Not Satisfiable",
-            Status::ExpectationFailed => "Expectation Failed",
-            Status::Teapot => "I'm a teapot",
-            Status::MisdirectedRequest => "Misdirected Request",
-            Status::UnprocessableEntity => "Unprocessable Entity",
-            Status::Locked => "Locked",
-            Status::FailedDependency => "Failed Dependency",
-            Status::UpgradeRequired => "Upgrade Required",
-            Status::PreconditionRequired => "Precondition Required",
-            Status::TooManyRequests => "Too Many Requests",
-            Status::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
-            Status::UnavailableForLegalReasons => "Unavailable For Legal Reasons",
-            Status::InternalServerError => "Internal Server Error",
-            Status::NotImplemented => "Not Implemented",
-            Status::BadGateway => "Bad Gateway",
-            Status::ServiceUnavailable => "Service Unavailable",
-            Status::GatewayTimeout => "Gateway Timeout",
-            Status::HttpVersionNotSupported => "HTTP Version Not Supported",
-            Status::VariantAlsoNegotiates => "Variant Also Negotiates",
-            Status::InsufficientStorage => "Insufficient Storage",
-            Status::LoopDetected => "Loop Detected",
-            Status::NotExtended => "Not Extended",
-            Status::NetworkAuthenticationRequired => "Network Authentication Required",
-        };
-        write!(f, "{description}")
-    }
-}
-pub struct Response {
-    pub headers: Headers,
-    pub body: Vec<u8>,
-    pub status: Status,
-}
-
-impl Response {
-    #[must_use]
-    pub fn new(status: Status, headers: Headers, body: Vec<u8>) -> Self {
-        Response {
-            headers,
-            body,
-            status,
-        }
-    }
-}
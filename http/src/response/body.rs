@@ -0,0 +1,220 @@
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// A reader that can also seek, e.g. a [`File`](std::fs::File).
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// A response body, either fully buffered or streamed from a reader.
+pub enum Body {
+    Bytes(Vec<u8>),
+    /// A body of unknown length, read until EOF while the response is being written.
+    Stream(Box<dyn Read + Send>),
+    /// A body of known length, read from the reader's current position.
+    Seekable {
+        reader: Box<dyn ReadSeek>,
+        length: u64,
+    },
+    /// A body of known length that can only be streamed, e.g. one relayed from another
+    /// server. It's sent with a `Content-Length`, but can't serve ranges.
+    Sized {
+        reader: Box<dyn Read + Send>,
+        length: u64,
+    },
+}
+
+impl Body {
+    #[must_use]
+    pub fn empty() -> Self {
+        Body::Bytes(Vec::new())
+    }
+
+    #[must_use]
+    pub fn stream<R: Read + Send + 'static>(reader: R) -> Self {
+        Body::Stream(Box::new(reader))
+    }
+
+    /// A body of `length` bytes streamed from `reader`.
+    #[must_use]
+    pub fn sized<R: Read + Send + 'static>(reader: R, length: u64) -> Self {
+        Body::Sized {
+            reader: Box::new(reader),
+            length,
+        }
+    }
+
+    /// A body read from `reader`, from its current position to the end. Unlike a stream,
+    /// its length is known so it's sent with a `Content-Length`.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if seeking fails.
+    pub fn seekable<R: ReadSeek + 'static>(mut reader: R) -> io::Result<Self> {
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        Ok(Body::Seekable {
+            reader: Box::new(reader),
+            length: end.saturating_sub(start),
+        })
+    }
+
+    /// The length of the body, `None` for streaming bodies.
+    #[must_use]
+    pub fn content_length(&self) -> Option<u64> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Stream(_) => None,
+            Body::Seekable { length, .. } | Body::Sized { length, .. } => Some(*length),
+        }
+    }
+
+    /// Returns the buffered bytes, or `None` for streaming bodies.
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes),
+            Body::Stream(_) | Body::Seekable { .. } | Body::Sized { .. } => None,
+        }
+    }
+
+    /// Read the whole body into memory.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if reading a streaming body fails.
+    pub fn into_bytes(self) -> io::Result<Vec<u8>> {
+        match self {
+            Body::Bytes(bytes) => Ok(bytes),
+            Body::Stream(mut reader) => {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            Body::Seekable { reader, length } => {
+                let mut bytes = Vec::new();
+                Read::take(reader, length).read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            Body::Sized { reader, length } => {
+                let mut bytes = Vec::new();
+                Read::take(reader, length).read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Body::empty()
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Bytes(bytes) => f.debug_tuple("Bytes").field(&bytes.len()).finish(),
+            Body::Stream(_) => f.debug_tuple("Stream").finish(),
+            Body::Seekable { length, .. } => {
+                f.debug_tuple("Seekable").field(length).finish()
+            }
+            Body::Sized { length, .. } => f.debug_tuple("Sized").field(length).finish(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Body::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        Body::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<String> for Body {
+    fn from(string: String) -> Self {
+        Body::Bytes(string.into_bytes())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(string: &str) -> Self {
+        Body::Bytes(string.as_bytes().to_vec())
+    }
+}
+
+/// A Chunked Transfer Encoder, the counterpart of
+/// [`ChunkedDecoder`](crate::request::chunked::ChunkedDecoder).
+///
+/// Every `write` is sent as a single chunk, `finish` writes the last chunk.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-7.1>
+pub struct ChunkedEncoder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> ChunkedEncoder<W> {
+    pub fn new(writer: W) -> Self {
+        ChunkedEncoder { writer }
+    }
+
+    /// Write the last (empty) chunk, ending the body.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to the underlying writer fails.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(b"0\r\n\r\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for ChunkedEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // An empty chunk would terminate the body.
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        write!(self.writer, "{:X}\r\n", buf.len())?;
+        self.writer.write_all(buf)?;
+        self.writer.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::ChunkedEncoder;
+    use crate::request::body::BodyDecoder;
+    use crate::request::chunked::ChunkedDecoder;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn it_round_trips_through_the_chunked_decoder() {
+        let mut encoder = ChunkedEncoder::new(Vec::new());
+        encoder.write_all(b"Hello").unwrap();
+        encoder.write_all(b"").unwrap();
+        encoder.write_all(b"This is exactly 18").unwrap();
+        let body = encoder.finish().unwrap();
+
+        assert_eq!(
+            "5\r\nHello\r\n12\r\nThis is exactly 18\r\n0\r\n\r\n",
+            String::from_utf8(body.clone()).unwrap()
+        );
+        assert_eq!(
+            b"HelloThis is exactly 18".to_vec(),
+            ChunkedDecoder::new(Cursor::new(body)).all_bytes()
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Body, Headers, Response, Status};
+use crate::request::is_token;
+use std::fmt;
+
+/// Why a response couldn't be built.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// A 1xx status is never a final response, see
+    /// [`Request::send_interim`](crate::request::Request::send_interim) and
+    /// [`Response::upgrade`].
+    Interim(Status),
+    /// The status can't have content, e.g. `204 No Content`, but the body isn't empty.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-6.4.1>
+    UnexpectedBody(Status),
+    /// The status of a redirect isn't 3xx, or is `304 Not Modified`.
+    NotRedirect(Status),
+    /// The header name isn't a token, or its value has a line break or a NUL.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-5.5>
+    InvalidHeader(String),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Interim(status) => {
+                write!(f, "{} isn't a final response", status.code())
+            }
+            BuildError::UnexpectedBody(status) => {
+                write!(f, "A {} response can't have a body", status.code())
+            }
+            BuildError::NotRedirect(status) => {
+                write!(f, "{} isn't a redirect", status.code())
+            }
+            BuildError::InvalidHeader(name) => write!(f, "Invalid header {name:?}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<BuildError> for Response {
+    /// A response that can't be built is a bug in the handler, whatever the reason.
+    fn from(_: BuildError) -> Self {
+        Response::from_status(Status::InternalServerError)
+    }
+}
+
+/// Builds a [`Response`], checking that its status, headers and body go together.
+///
+/// ```
+/// use http::response::{Response, Status};
+///
+/// let response = Response::builder()
+///     .status(Status::Created)
+///     .header("Location", "/posts/1")
+///     .header("Content-Type", "text/plain; charset=utf-8")
+///     .body("Created")
+///     .unwrap();
+/// assert_eq!(Some("/posts/1"), response.headers.get("Location"));
+/// ```
+#[must_use]
+#[derive(Debug)]
+pub struct Builder {
+    status: Status,
+    headers: Headers,
+    error: Option<BuildError>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder {
+            status: Status::Ok,
+            headers: Headers::default(),
+            error: None,
+        }
+    }
+}
+
+impl Builder {
+    /// `200 OK` by default.
+    pub fn status(mut self, status: Status) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Add a value for the header `name`, keeping any existing values.
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        let value = value.into();
+        if !is_token(name) || value.contains(['\r', '\n', '\0']) {
+            self.error
+                .get_or_insert(BuildError::InvalidHeader(name.to_string()));
+        } else {
+            self.headers.append(name, value);
+        }
+        self
+    }
+
+    /// Finish the response with `body`.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if a header is invalid, if the status is 1xx, or if the
+    /// status can't have content and `body` isn't empty.
+    pub fn body(self, body: impl Into<Body>) -> Result<Response, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let body = body.into();
+        let response = Response::new(self.status, self.headers, Body::empty());
+        if response.status.is_informational() {
+            return Err(BuildError::Interim(response.status));
+        }
+        if !response.may_have_body() && body.content_length() != Some(0) {
+            return Err(BuildError::UnexpectedBody(response.status));
+        }
+        Ok(Response { body, ..response })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::BuildError;
+    use crate::response::{Body, Response, Status};
+    use std::io;
+
+    #[test]
+    fn it_validates_the_status_headers_and_body() {
+        let response = Response::builder()
+            .status(Status::NoContent)
+            .header("X-Id", "1")
+            .body("")
+            .unwrap();
+        assert_eq!(Status::NoContent, response.status);
+        assert_eq!(Some("1"), response.headers.get("X-Id"));
+
+        assert_eq!(
+            Some(BuildError::UnexpectedBody(Status::NotModified)),
+            Response::builder()
+                .status(Status::NotModified)
+                .body("abc")
+                .err()
+        );
+        assert_eq!(
+            Some(BuildError::UnexpectedBody(Status::NoContent)),
+            Response::builder()
+                .status(Status::NoContent)
+                .body(Body::stream(io::empty()))
+                .err()
+        );
+        assert_eq!(
+            Some(BuildError::Interim(Status::EarlyHints)),
+            Response::builder()
+                .status(Status::EarlyHints)
+                .body("")
+                .err()
+        );
+        assert_eq!(
+            Some(BuildError::InvalidHeader("X-Id".to_string())),
+            Response::builder()
+                .header("X-Id", "1\r\nX-Injected: 1")
+                .header("Bad Name", "1")
+                .body("")
+                .err()
+        );
+    }
+}
@@ -0,0 +1,931 @@
+pub mod body;
+mod builder;
+mod parse;
+
+pub use body::{Body, ChunkedEncoder, ReadSeek};
+pub use builder::{BuildError, Builder};
+pub use parse::ResponseError;
+pub(crate) use parse::{framing, read_head, Framing};
+
+use crate::json;
+use crate::request::HttpVersion;
+use crate::server::{OnUpgrade, Upgraded};
+use crate::static_files;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Response headers, kept in insertion order.
+///
+/// Header names are compared case-insensitively, as required by
+/// <https://datatracker.ietf.org/doc/html/rfc9110#section-5.1>.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Headers {
+    headers: Vec<(String, String)>,
+}
+
+impl Headers {
+    #[must_use]
+    pub fn from_hash_map(map: &HashMap<String, String>) -> Self {
+        Headers {
+            headers: map.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        }
+    }
+
+    /// Build headers from CRLF (or LF) separated `Name: value` lines.
+    ///
+    /// Lines without a colon are ignored.
+    #[must_use]
+    pub fn new(headers: &str) -> Self {
+        Headers {
+            headers: headers
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .collect(),
+        }
+    }
+
+    /// Returns the first value of the header `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns every value of the header `name`, in insertion order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.headers
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Set the header `name`, replacing any existing values. Line breaks and NULs are
+    /// removed from the name and value, so they can't add headers.
+    pub fn insert(&mut self, name: &str, value: impl Into<String>) {
+        let value = sanitize(value.into());
+        if let Some(index) = self
+            .headers
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(name))
+        {
+            self.headers[index].1 = value;
+            let mut i = index + 1;
+            while i < self.headers.len() {
+                if self.headers[i].0.eq_ignore_ascii_case(name) {
+                    self.headers.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+        } else {
+            self.headers.push((sanitize(name.to_string()), value));
+        }
+    }
+
+    /// Add a value for `name`, keeping any existing values. Line breaks and NULs are
+    /// removed, like with [`insert`](Headers::insert).
+    pub fn append(&mut self, name: &str, value: impl Into<String>) {
+        self.headers
+            .push((sanitize(name.to_string()), sanitize(value.into())));
+    }
+
+    /// Remove every value of the header `name`, returning the first one.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let mut removed = None;
+        self.headers.retain(|(k, v)| {
+            if k.eq_ignore_ascii_case(name) {
+                removed.get_or_insert_with(|| v.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Add `value` to a comma-separated list header such as `Vary`, unless it's already
+    /// listed.
+    pub fn add_to_list(&mut self, name: &str, value: &str) {
+        let listed = self
+            .get_all(name)
+            .flat_map(|v| v.split(','))
+            .any(|v| v.trim().eq_ignore_ascii_case(value));
+        if !listed {
+            self.append(name, value);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+}
+
+/// Remove the characters that would end a header line.
+fn sanitize(mut text: String) -> String {
+    if text.contains(['\r', '\n', '\0']) {
+        text.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+    }
+    text
+}
+
+impl fmt::Display for Headers {
+    /// Formats every header as a `Name: value` line terminated by CRLF.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (k, v) in &self.headers {
+            write!(f, "{k}: {v}\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// A response status: a code from 100 to 999, and the reason phrase sent with it.
+///
+/// Registered codes have constants, e.g. [`Status::NotFound`], and any other code is
+/// built with [`Status::try_from`]. The reason phrase is the registered one, unless a
+/// custom one is set with [`Status::with_reason`]. Statuses compare by code alone.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-15>
+#[derive(Clone, Debug)]
+pub struct Status {
+    code: u16,
+    reason: Option<Cow<'static, str>>,
+}
+
+#[allow(non_upper_case_globals)]
+impl Status {
+    // Informational responses (100–199)
+    pub const Continue: Status = Status::known(100);
+    pub const SwitchingProtocols: Status = Status::known(101);
+    pub const Processing: Status = Status::known(102);
+    pub const EarlyHints: Status = Status::known(103);
+
+    // Successful responses (200–299)
+    pub const Ok: Status = Status::known(200);
+    pub const Created: Status = Status::known(201);
+    pub const Accepted: Status = Status::known(202);
+    pub const NonAuthoritativeInformation: Status = Status::known(203);
+    pub const NoContent: Status = Status::known(204);
+    pub const ResetContent: Status = Status::known(205);
+    pub const PartialContent: Status = Status::known(206);
+    pub const MultiStatus: Status = Status::known(207);
+    pub const AlreadyReported: Status = Status::known(208);
+    pub const ImUsed: Status = Status::known(226);
+
+    // Redirection messages (300–399)
+    pub const MultipleChoices: Status = Status::known(300);
+    pub const MovedPermanently: Status = Status::known(301);
+    pub const Found: Status = Status::known(302);
+    pub const SeeOther: Status = Status::known(303);
+    pub const NotModified: Status = Status::known(304);
+    pub const UseProxy: Status = Status::known(305);
+    pub const TemporaryRedirect: Status = Status::known(307);
+    pub const PermanentRedirect: Status = Status::known(308);
+
+    // Client error responses (400–499)
+    pub const BadRequest: Status = Status::known(400);
+    pub const Unauthorized: Status = Status::known(401);
+    pub const PaymentRequired: Status = Status::known(402);
+    pub const Forbidden: Status = Status::known(403);
+    pub const NotFound: Status = Status::known(404);
+    pub const MethodNotAllowed: Status = Status::known(405);
+    pub const NotAcceptable: Status = Status::known(406);
+    pub const ProxyAuthenticationRequired: Status = Status::known(407);
+    pub const RequestTimeout: Status = Status::known(408);
+    pub const Conflict: Status = Status::known(409);
+    pub const Gone: Status = Status::known(410);
+    pub const LengthRequired: Status = Status::known(411);
+    pub const PreconditionFailed: Status = Status::known(412);
+    pub const PayloadTooLarge: Status = Status::known(413);
+    pub const UriTooLong: Status = Status::known(414);
+    pub const UnsupportedMediaType: Status = Status::known(415);
+    pub const RangeNotSatisfiable: Status = Status::known(416);
+    pub const ExpectationFailed: Status = Status::known(417);
+    pub const Teapot: Status = Status::known(418);
+    pub const MisdirectedRequest: Status = Status::known(421);
+    pub const UnprocessableEntity: Status = Status::known(422);
+    pub const Locked: Status = Status::known(423);
+    pub const FailedDependency: Status = Status::known(424);
+    pub const TooEarly: Status = Status::known(425);
+    pub const UpgradeRequired: Status = Status::known(426);
+    pub const PreconditionRequired: Status = Status::known(428);
+    pub const TooManyRequests: Status = Status::known(429);
+    pub const RequestHeaderFieldsTooLarge: Status = Status::known(431);
+    pub const UnavailableForLegalReasons: Status = Status::known(451);
+
+    // Server error responses (500–599)
+    pub const InternalServerError: Status = Status::known(500);
+    pub const NotImplemented: Status = Status::known(501);
+    pub const BadGateway: Status = Status::known(502);
+    pub const ServiceUnavailable: Status = Status::known(503);
+    pub const GatewayTimeout: Status = Status::known(504);
+    pub const HttpVersionNotSupported: Status = Status::known(505);
+    pub const VariantAlsoNegotiates: Status = Status::known(506);
+    pub const InsufficientStorage: Status = Status::known(507);
+    pub const LoopDetected: Status = Status::known(508);
+    pub const NotExtended: Status = Status::known(510);
+    pub const NetworkAuthenticationRequired: Status = Status::known(511);
+}
+
+impl Status {
+    const fn known(code: u16) -> Self {
+        Status { code, reason: None }
+    }
+
+    #[must_use]
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// The custom reason phrase if one is set, the registered one otherwise, and an empty
+    /// one for unregistered codes.
+    #[must_use]
+    pub fn reason(&self) -> &str {
+        match &self.reason {
+            Some(reason) => reason,
+            None => registered_reason(self.code).unwrap_or_default(),
+        }
+    }
+
+    /// Send `reason` instead of the registered reason phrase. Control characters, which
+    /// can't be part of it, are removed.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-4>
+    #[must_use]
+    pub fn with_reason(mut self, reason: impl Into<Cow<'static, str>>) -> Self {
+        let mut reason = reason.into();
+        if reason.chars().any(|c| c.is_control() && c != '\t') {
+            reason.to_mut().retain(|c| !c.is_control() || c == '\t');
+        }
+        self.reason = Some(reason);
+        self
+    }
+
+    /// 1xx: the request was received, and the final response is yet to come.
+    #[must_use]
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.code)
+    }
+
+    /// 2xx: the request succeeded.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.code)
+    }
+
+    /// 3xx: the client has to take further action, usually following `Location`.
+    #[must_use]
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.code)
+    }
+
+    /// 4xx: the request was wrong.
+    #[must_use]
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.code)
+    }
+
+    /// 5xx: the server failed to handle a valid request.
+    #[must_use]
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.code)
+    }
+}
+
+/// The registered reason phrase of `code`.
+fn registered_reason(code: u16) -> Option<&'static str> {
+    Some(match code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        102 => "Processing",
+        103 => "Early Hints",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        203 => "Non-Authoritative Information",
+        204 => "No Content",
+        205 => "Reset Content",
+        206 => "Partial Content",
+        207 => "Multi-Status",
+        208 => "Already Reported",
+        226 => "IM Used",
+        300 => "Multiple Choices",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        305 => "Use Proxy",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        402 => "Payment Required",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        407 => "Proxy Authentication Required",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        417 => "Expectation Failed",
+        418 => "I'm a teapot",
+        421 => "Misdirected Request",
+        422 => "Unprocessable Entity",
+        423 => "Locked",
+        424 => "Failed Dependency",
+        425 => "Too Early",
+        426 => "Upgrade Required",
+        428 => "Precondition Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        451 => "Unavailable For Legal Reasons",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        506 => "Variant Also Negotiates",
+        507 => "Insufficient Storage",
+        508 => "Loop Detected",
+        510 => "Not Extended",
+        511 => "Network Authentication Required",
+        _ => return None,
+    })
+}
+
+impl TryFrom<u16> for Status {
+    type Error = u16;
+
+    /// The status with `code`, or the code back if it isn't between 100 and 999.
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        if (100..1000).contains(&code) {
+            Ok(Status::known(code))
+        } else {
+            Err(code)
+        }
+    }
+}
+
+impl From<Status> for u16 {
+    fn from(status: Status) -> Self {
+        status.code
+    }
+}
+
+impl PartialEq for Status {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl Eq for Status {}
+
+impl fmt::Display for Status {
+    /// Formats the reason phrase.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.reason())
+    }
+}
+
+pub struct Response {
+    pub headers: Headers,
+    pub body: Body,
+    pub status: Status,
+    /// Takes over the connection once the head is sent, see [`Response::upgrade`].
+    pub(crate) on_upgrade: Option<OnUpgrade>,
+}
+
+impl Response {
+    #[must_use]
+    pub fn new(status: Status, headers: Headers, body: impl Into<Body>) -> Self {
+        Response {
+            headers,
+            body: body.into(),
+            status,
+            on_upgrade: None,
+        }
+    }
+
+    /// A response that hands the connection over to `on_upgrade` once its head is sent,
+    /// with the bytes the client sent past its request. The server stops managing the
+    /// connection.
+    ///
+    /// Only a `101 Switching Protocols` response, or a 2xx response to a `CONNECT`
+    /// request, hands the connection over; the head is sent without `Content-Length`
+    /// or `Transfer-Encoding`, since what follows belongs to the new protocol or
+    /// tunnel. With any other status `on_upgrade` is dropped and the response is sent
+    /// as usual. HTTP/2 streams can't be taken over and are reset, asking the client
+    /// to retry over HTTP/1.1.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-7.8>
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-9.3.6>
+    #[must_use]
+    pub fn upgrade<F>(status: Status, headers: Headers, on_upgrade: F) -> Self
+    where
+        F: FnOnce(Upgraded) + Send + 'static,
+    {
+        let mut response = Response::new(status, headers, "");
+        response.on_upgrade = Some(Box::new(on_upgrade));
+        response
+    }
+
+    /// Build a response, see [`Builder`].
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// A `200 OK` HTML page.
+    #[must_use]
+    pub fn html(body: impl Into<Body>) -> Self {
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/html; charset=utf-8"),
+            body,
+        )
+    }
+
+    /// A `200 OK` plain text response.
+    #[must_use]
+    pub fn text(body: impl Into<Body>) -> Self {
+        Response::plain(Status::Ok, body)
+    }
+
+    /// A plain text response with `status`.
+    #[must_use]
+    pub fn plain(status: Status, body: impl Into<Body>) -> Self {
+        Response::new(
+            status,
+            Headers::new("Content-Type: text/plain; charset=utf-8"),
+            body,
+        )
+    }
+
+    /// A plain text response with `status` and its reason phrase as the body, e.g. for
+    /// an error.
+    #[must_use]
+    pub fn from_status(status: Status) -> Self {
+        let reason = status.to_string();
+        Response::plain(status, reason)
+    }
+
+    /// A `200 OK` JSON response. Like [`Request::json_value`] and `Request::json`, the
+    /// name `json` is left to the `serde` constructor, so that enabling the feature
+    /// doesn't change what an existing call means.
+    ///
+    /// [`Request::json_value`]: crate::request::Request::json_value
+    #[must_use]
+    pub fn json_value(value: &json::Value) -> Self {
+        Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: application/json"),
+            value.to_string(),
+        )
+    }
+
+    /// A `200 OK` JSON response, serializing `value`.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `value` can't be serialized, e.g. a map with non-string
+    /// keys.
+    #[cfg(feature = "serde")]
+    pub fn json<T: serde::Serialize + ?Sized>(
+        value: &T,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: application/json"),
+            serde_json::to_vec(value)?,
+        ))
+    }
+
+    /// A redirect to `location`, e.g. with `303 See Other` after a form is posted. The
+    /// `location` is sent as is, a relative one is resolved by the client against the URI
+    /// of the request.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-10.2.2>
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `status` isn't 3xx or is `304 Not Modified`, or if
+    /// `location` has a line break.
+    pub fn redirect(status: Status, location: &str) -> Result<Self, BuildError> {
+        if !status.is_redirect() || status == Status::NotModified {
+            return Err(BuildError::NotRedirect(status));
+        }
+        Response::builder()
+            .status(status)
+            .header("Location", location)
+            .body("")
+    }
+
+    /// A plain text `404 Not Found`.
+    #[must_use]
+    pub fn not_found() -> Self {
+        Response::from_status(Status::NotFound)
+    }
+
+    /// A `200 OK` response with the file at `path`, its `Content-Type` guessed from its
+    /// extension, see [`static_files::mime_type`]. It has an `ETag` and a `Last-Modified`
+    /// header, like the files served by [`StaticFiles`](static_files::StaticFiles).
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the file can't be opened.
+    pub fn file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        static_files::serve_file(path.as_ref())
+    }
+
+    /// Returns `false` for statuses that never have content: 1xx, 204 and 304.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-6.4.1>
+    #[must_use]
+    pub fn may_have_body(&self) -> bool {
+        !matches!(self.status.code(), 100..=199 | 204 | 304)
+    }
+
+    /// Remove the framing headers of a status that can't have content: 1xx and 204
+    /// responses can't have `Content-Length` or `Transfer-Encoding`. A 304 keeps the
+    /// `Content-Length` of the representation it stands in for.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-8.6>
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-6.1>
+    pub(crate) fn remove_framing(&mut self) {
+        if self.status != Status::NotModified {
+            self.headers.remove("Content-Length");
+        }
+        self.headers.remove("Transfer-Encoding");
+    }
+
+    /// Write the status line, headers and body to `writer`.
+    ///
+    /// Buffered and seekable bodies are sent with a `Content-Length`. Streaming bodies
+    /// are sent with chunked transfer coding to HTTP/1.1 clients, and delimited by
+    /// closing the connection otherwise, and flushed as they're read. The body of a
+    /// status that can't have one is dropped.
+    ///
+    /// The status line always reads `HTTP/1.1`, `version` is the version of the request
+    /// and only decides the framing. HTTP/0.9 requests get the bare body, without a
+    /// status line or headers.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-6.2>
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to `writer` or reading a streaming body fails.
+    pub fn write_to<W: Write>(self, writer: W, version: &HttpVersion) -> io::Result<()> {
+        self.write(writer, version, true)
+    }
+
+    /// Write the status line and headers to `writer`, as the response to a `HEAD`
+    /// request.
+    ///
+    /// The headers are the ones [`write_to`](Response::write_to) would send, including
+    /// the `Content-Length` of the body.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to `writer` fails.
+    pub fn write_head_to<W: Write>(
+        self,
+        writer: W,
+        version: &HttpVersion,
+    ) -> io::Result<()> {
+        self.write(writer, version, false)
+    }
+
+    /// Write the status line and headers of a response without a body: an interim
+    /// response, or one handing the connection over.
+    pub(crate) fn write_bare_head_to<W: Write>(
+        mut self,
+        mut writer: W,
+    ) -> io::Result<()> {
+        self.headers.remove("Content-Length");
+        self.headers.remove("Transfer-Encoding");
+        self.write_status_and_headers(&mut writer)?;
+        writer.flush()
+    }
+
+    fn write_status_and_headers<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(
+            writer,
+            "{http} {status_number} {status}\r\n{headers}\r\n",
+            http = HttpVersion::V1_1,
+            status_number = self.status.code(),
+            status = self.status,
+            headers = self.headers,
+        )
+    }
+
+    fn write<W: Write>(
+        mut self,
+        writer: W,
+        version: &HttpVersion,
+        body: bool,
+    ) -> io::Result<()> {
+        let chunked = match self.body {
+            _ if !self.may_have_body() => {
+                self.remove_framing();
+                false
+            }
+            Body::Bytes(ref bytes) => {
+                self.headers
+                    .insert("Content-Length", bytes.len().to_string());
+                false
+            }
+            Body::Seekable { length, .. } | Body::Sized { length, .. } => {
+                self.headers.insert("Content-Length", length.to_string());
+                false
+            }
+            Body::Stream(_) => {
+                self.headers.remove("Content-Length");
+                if *version == HttpVersion::V1_1 {
+                    self.headers.insert("Transfer-Encoding", "chunked");
+                    true
+                } else {
+                    self.headers.insert("Connection", "close");
+                    false
+                }
+            }
+        };
+
+        let mut writer = BufWriter::new(writer);
+        if *version != HttpVersion::V0_9 {
+            self.write_status_and_headers(&mut writer)?;
+        }
+        if !body || !self.may_have_body() {
+            return writer.flush();
+        }
+
+        match self.body {
+            Body::Bytes(bytes) => writer.write_all(&bytes)?,
+            Body::Stream(mut reader) if chunked => {
+                let mut encoder = ChunkedEncoder::new(&mut writer);
+                copy_flushing(&mut reader, &mut encoder)?;
+                encoder.finish()?;
+            }
+            Body::Stream(mut reader) => {
+                copy_flushing(&mut reader, &mut writer)?;
+            }
+            Body::Seekable { reader, length } => {
+                copy_length(reader, length, &mut writer)?;
+            }
+            Body::Sized { reader, length } => copy_length(reader, length, &mut writer)?,
+        }
+        writer.flush()
+    }
+}
+
+/// Copy the `length` bytes of a body, which fails if the reader ends early.
+fn copy_length<R: Read, W: Write>(
+    reader: R,
+    length: u64,
+    writer: &mut W,
+) -> io::Result<()> {
+    let copied = io::copy(&mut Read::take(reader, length), writer)?;
+    if copied < length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Body ended before its length",
+        ));
+    }
+    Ok(())
+}
+
+/// Copy a streaming body, flushing after every read so bodies produced over time, like
+/// [server-sent events](crate::sse), reach the client as they're produced.
+fn copy_flushing<R: Read + ?Sized, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut buf = [0; 8192];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..read])?;
+        writer.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{Body, BuildError, Headers, Response, Status};
+    use crate::request::HttpVersion;
+    use std::fs;
+    use std::io::Cursor;
+
+    fn written(response: Response, head: bool) -> String {
+        written_as(response, head, &HttpVersion::V1_1)
+    }
+
+    fn written_as(response: Response, head: bool, version: &HttpVersion) -> String {
+        let mut output = Vec::new();
+        if head {
+            response.write_head_to(&mut output, version).unwrap();
+        } else {
+            response.write_to(&mut output, version).unwrap();
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn it_writes_head_responses_without_a_body() {
+        let response = Response::new(Status::Ok, Headers::default(), "hello");
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n",
+            written(response, true)
+        );
+
+        let response = Response::new(
+            Status::Ok,
+            Headers::default(),
+            Body::stream(Cursor::new("hello")),
+        );
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n",
+            written(response, true)
+        );
+    }
+
+    #[test]
+    fn it_never_writes_a_body_for_bodiless_statuses() {
+        let response = Response::new(
+            Status::NoContent,
+            Headers::new("Content-Length: 7\r\nTransfer-Encoding: chunked"),
+            "ignored",
+        );
+        assert_eq!("HTTP/1.1 204 No Content\r\n\r\n", written(response, false));
+
+        let response = Response::new(
+            Status::NotModified,
+            Headers::new("ETag: \"a\"\r\nContent-Length: 7"),
+            Body::stream(Cursor::new("ignored")),
+        );
+        assert_eq!(
+            "HTTP/1.1 304 Not Modified\r\nETag: \"a\"\r\nContent-Length: 7\r\n\r\n",
+            written(response, false)
+        );
+    }
+
+    #[test]
+    fn it_writes_upgrade_heads_without_framing() {
+        let mut response =
+            Response::upgrade(Status::Ok, Headers::new("Content-Length: 3"), |_| {});
+        assert!(response.on_upgrade.take().is_some());
+        let mut output = Vec::new();
+        response.write_bare_head_to(&mut output).unwrap();
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\n\r\n",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_answers_every_version_as_http_1_1() {
+        let response = || {
+            Response::new(
+                Status::Ok,
+                Headers::default(),
+                Body::stream(Cursor::new("hello")),
+            )
+        };
+        assert_eq!(
+            "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello",
+            written_as(response(), false, &HttpVersion::V1_0)
+        );
+        assert_eq!("hello", written_as(response(), false, &HttpVersion::V0_9));
+    }
+
+    #[test]
+    fn it_writes_any_status_code_and_reason() {
+        let status = Status::try_from(299).unwrap();
+        assert!(status.is_success() && !status.is_redirect());
+        assert_eq!("", status.reason());
+        assert_eq!(Err(99), Status::try_from(99));
+        assert_eq!(Err(1000), Status::try_from(1000));
+
+        let status = Status::NotFound.with_reason("Nothing\r\nX-Injected: 1");
+        assert_eq!(Status::NotFound, status);
+        assert!(status.is_client_error());
+        let response = Response::new(status, Headers::default(), "");
+        assert_eq!(
+            "HTTP/1.1 404 NothingX-Injected: 1\r\nContent-Length: 0\r\n\r\n",
+            written(response, false)
+        );
+        assert_eq!(
+            "HTTP/1.1 425 Too Early\r\nContent-Length: 0\r\n\r\n",
+            written(
+                Response::new(Status::TooEarly, Headers::default(), ""),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn it_keeps_header_values_on_one_line() {
+        let mut headers = Headers::default();
+        headers.insert("X-A", "1\r\nX-Injected: 1");
+        headers.append("X-B\r\nX-Injected", "2\n");
+        assert_eq!(
+            vec![("X-A", "1X-Injected: 1"), ("X-BX-Injected", "2")],
+            headers.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_builds_common_responses() {
+        let response = Response::html("<h1>hi</h1>");
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(
+            Some("text/html; charset=utf-8"),
+            response.headers.get("Content-Type")
+        );
+        assert_eq!(
+            Some("text/plain; charset=utf-8"),
+            Response::text("hi").headers.get("Content-Type")
+        );
+        let response = Response::json_value(&"[1, true]".parse().unwrap());
+        assert_eq!(
+            Some("application/json"),
+            response.headers.get("Content-Type")
+        );
+        assert_eq!(Some(&b"[1,true]"[..]), response.body.as_bytes());
+
+        let response = Response::not_found();
+        assert_eq!(Status::NotFound, response.status);
+        assert_eq!(Some(&b"Not Found"[..]), response.body.as_bytes());
+        let response = Response::from_status(Status::Forbidden);
+        assert_eq!(Status::Forbidden, response.status);
+        assert_eq!(Some(&b"Forbidden"[..]), response.body.as_bytes());
+        let response = Response::plain(Status::BadGateway, "Upstream closed");
+        assert_eq!(
+            Some("text/plain; charset=utf-8"),
+            response.headers.get("Content-Type")
+        );
+        assert_eq!(Some(&b"Upstream closed"[..]), response.body.as_bytes());
+
+        let response = Response::redirect(Status::SeeOther, "/posts/1").unwrap();
+        assert_eq!(
+            "HTTP/1.1 303 See Other\r\nLocation: /posts/1\r\nContent-Length: 0\r\n\r\n",
+            written(response, false)
+        );
+        assert_eq!(
+            Some(BuildError::NotRedirect(Status::NotModified)),
+            Response::redirect(Status::NotModified, "/").err()
+        );
+        assert_eq!(
+            Some(BuildError::NotRedirect(Status::Ok)),
+            Response::redirect(Status::Ok, "/").err()
+        );
+        assert!(Response::redirect(Status::Found, "/\r\nX-Injected: 1").is_err());
+    }
+
+    #[test]
+    fn it_builds_file_responses() {
+        let path =
+            std::env::temp_dir().join(format!("response-{}.css", std::process::id()));
+        fs::write(&path, "body {}").unwrap();
+        let response = Response::file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            Some("text/css; charset=utf-8"),
+            response.headers.get("Content-Type")
+        );
+        assert!(response.headers.contains("ETag"));
+        assert_eq!(Some(7), response.body.content_length());
+        assert!(Response::file(&path).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_json_responses() {
+        let response =
+            Response::json(&std::collections::BTreeMap::from([("a", 1)])).unwrap();
+        assert_eq!(
+            Some("application/json"),
+            response.headers.get("Content-Type")
+        );
+        assert_eq!(Some(&br#"{"a":1}"#[..]), response.body.as_bytes());
+    }
+}
@@ -0,0 +1,396 @@
+use super::{Body, Headers, Response, Status};
+use crate::request::body::{self, BodyDecoder};
+use crate::request::chunked::ChunkedDecoder;
+use crate::request::{is_token, HttpVersion, Method};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::str::FromStr;
+
+/// The largest response head read.
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Why a response couldn't be read.
+#[derive(Debug)]
+pub enum ResponseError {
+    /// Reading failed, or the input ended before the head did.
+    Io(io::Error),
+    /// The response is malformed.
+    Malformed(&'static str),
+    /// The status line has a version other than HTTP/1.x, e.g. `HTTP/2`.
+    VersionNotSupported,
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseError::Io(e) => write!(f, "Couldn't read the response: {e}"),
+            ResponseError::Malformed(reason) => write!(f, "{reason}"),
+            ResponseError::VersionNotSupported => write!(f, "Only HTTP/1.x is supported"),
+        }
+    }
+}
+
+impl std::error::Error for ResponseError {}
+
+impl From<io::Error> for ResponseError {
+    fn from(error: io::Error) -> Self {
+        ResponseError::Io(error)
+    }
+}
+
+/// The status line and headers of a response.
+pub(crate) struct Head {
+    pub(crate) version: HttpVersion,
+    pub(crate) status: Status,
+    pub(crate) headers: Headers,
+}
+
+/// Read a status line and headers. A reason phrase other than the registered one is kept.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-4>
+pub(crate) fn read_head<R: BufRead>(reader: &mut R) -> Result<Head, ResponseError> {
+    let mut limited = reader.take(MAX_HEAD_SIZE as u64);
+    let mut line = String::new();
+    if limited.read_line(&mut line)? == 0 {
+        return Err(ResponseError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+    let status_line = line.trim_end_matches(['\r', '\n']);
+    let (version, rest) = status_line
+        .split_once(' ')
+        .ok_or(ResponseError::Malformed("Invalid status line"))?;
+    let version = match HttpVersion::from_str(version) {
+        Ok(version) => version,
+        Err(_) if version.starts_with("HTTP/") => {
+            return Err(ResponseError::VersionNotSupported)
+        }
+        Err(_) => return Err(ResponseError::Malformed("Invalid status line")),
+    };
+    let mut status = rest
+        .get(..3)
+        .filter(|code| code.bytes().all(|b| b.is_ascii_digit()))
+        .filter(|_| rest.len() == 3 || rest.as_bytes()[3] == b' ')
+        .and_then(|code| code.parse::<u16>().ok())
+        .and_then(|code| Status::try_from(code).ok())
+        .ok_or(ResponseError::Malformed("Invalid status code"))?;
+    let reason = rest.get(4..).unwrap_or_default().trim();
+    if !reason.is_empty() && reason != status.reason() {
+        status = status.with_reason(reason.to_string());
+    }
+
+    let mut headers = Headers::default();
+    loop {
+        line.clear();
+        if limited.read_line(&mut line)? == 0 {
+            return Err(ResponseError::Malformed("Incomplete or oversized head"));
+        }
+        let field = line.trim_end_matches(['\r', '\n']);
+        if field.is_empty() {
+            return Ok(Head {
+                version,
+                status,
+                headers,
+            });
+        }
+        let (name, value) = field
+            .split_once(':')
+            .filter(|(name, _)| is_token(name))
+            .ok_or(ResponseError::Malformed("Invalid header"))?;
+        headers.append(name, value.trim());
+    }
+}
+
+/// How the body of a response is delimited.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Empty,
+    Length(u64),
+    Chunked,
+    /// Read until the server closes the connection.
+    Close,
+}
+
+/// Find out how the body of a response with status `code` is delimited.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-6.3>
+pub(crate) fn framing(
+    code: u16,
+    headers: &Headers,
+    head_request: bool,
+) -> Result<Framing, ResponseError> {
+    if head_request || matches!(code, 100..=199 | 204 | 304) {
+        return Ok(Framing::Empty);
+    }
+    // `Transfer-Encoding` overrides `Content-Length`. Only a final chunked coding
+    // delimits the body, any other lasts until the connection is closed.
+    if let Some(codings) = headers.get("Transfer-Encoding") {
+        let chunked = codings
+            .rsplit(',')
+            .next()
+            .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"));
+        return Ok(if chunked {
+            Framing::Chunked
+        } else {
+            Framing::Close
+        });
+    }
+    let Some(lengths) = headers.get("Content-Length") else {
+        return Ok(Framing::Close);
+    };
+    // A list of identical lengths, e.g. from merged headers, is a single length.
+    let mut lengths = lengths
+        .split(',')
+        .map(|length| length.trim().parse::<u64>());
+    let length = lengths
+        .next()
+        .and_then(Result::ok)
+        .ok_or(ResponseError::Malformed("Invalid Content-Length"))?;
+    if !lengths.all(|other| other == Ok(length)) {
+        return Err(ResponseError::Malformed("Invalid Content-Length"));
+    }
+    Ok(match length {
+        0 => Framing::Empty,
+        length => Framing::Length(length),
+    })
+}
+
+impl Response {
+    /// Parse a response from `reader`, the counterpart of
+    /// [`Request::from`](crate::request::Request::from).
+    ///
+    /// The body is streamed from `reader` as it's read, decoded from whichever framing
+    /// the response has: a `Content-Length`, chunked transfer coding, or the end of
+    /// the input. Headers are kept as they were sent, so writing the response back
+    /// with [`write_to`](Response::write_to) frames the body the same way. Interim
+    /// 1xx responses are skipped. Unregistered status codes and custom reason phrases
+    /// are kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if reading the head fails or the head is malformed. Errors in
+    /// the body, e.g. the input ending early, are returned when the body is read.
+    pub fn from_reader<R: Read + Send + 'static>(
+        reader: R,
+    ) -> Result<Self, ResponseError> {
+        Self::from_reader_to(reader, &Method::Get)
+    }
+
+    /// Like [`from_reader`](Response::from_reader), for the response to a `method`
+    /// request: responses to `HEAD` have no body, whatever their headers say.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if reading the head fails or the head is malformed.
+    pub fn from_reader_to<R: Read + Send + 'static>(
+        reader: R,
+        method: &Method,
+    ) -> Result<Self, ResponseError> {
+        let mut reader = BufReader::new(reader);
+        let head = loop {
+            let head = read_head(&mut reader)?;
+            if !head.status.is_informational() {
+                break head;
+            }
+        };
+
+        let head_request = *method == Method::Head;
+        let body = match framing(head.status.code(), &head.headers, head_request)? {
+            Framing::Empty => Body::empty(),
+            Framing::Length(length) => {
+                let decoder = body::Body::new(
+                    usize::try_from(length).map_err(|_| {
+                        ResponseError::Malformed("Invalid Content-Length")
+                    })?,
+                    reader,
+                );
+                Body::sized(Decoded::new(decoder, false), length)
+            }
+            Framing::Chunked => {
+                Body::stream(Decoded::new(ChunkedDecoder::new(reader), true))
+            }
+            Framing::Close => Body::stream(reader),
+        };
+        Ok(Response::new(head.status, head.headers, body))
+    }
+}
+
+/// A body decoded from its framing, read as bytes.
+struct Decoded<D> {
+    decoder: D,
+    /// Chunked bodies end with an empty chunk, anything else is truncated.
+    chunked: bool,
+    done: bool,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl<D: BodyDecoder> Decoded<D> {
+    fn new(decoder: D, chunked: bool) -> Self {
+        Decoded {
+            decoder,
+            chunked,
+            done: false,
+            chunk: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<D: BodyDecoder> Read for Decoded<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.decoder.next() {
+                None if self.chunked => return Err(io::ErrorKind::UnexpectedEof.into()),
+                None => self.done = true,
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+                Some(Ok(chunk)) => {
+                    self.done = self.chunked && chunk.buf.is_empty();
+                    self.chunk = chunk.buf;
+                    self.position = 0;
+                }
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{framing, read_head, Framing, ResponseError};
+    use crate::request::{HttpVersion, Method};
+    use crate::response::{Body, Headers, Response, Status};
+    use std::io::Cursor;
+
+    fn parse(input: &str) -> Result<Response, ResponseError> {
+        Response::from_reader(Cursor::new(input.to_string()))
+    }
+
+    fn written(response: Response, version: &HttpVersion) -> Vec<u8> {
+        let mut output = Vec::new();
+        response.write_to(&mut output, version).unwrap();
+        output
+    }
+
+    #[test]
+    fn it_reads_response_heads() {
+        let mut reader = Cursor::new(
+            "HTTP/1.1 404 Not Found\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\n\r\nbody",
+        );
+        let head = read_head(&mut reader).unwrap();
+        assert_eq!(HttpVersion::V1_1, head.version);
+        assert_eq!(Status::NotFound, head.status);
+        assert_eq!(2, head.headers.get_all("set-cookie").count());
+
+        assert!(read_head(&mut Cursor::new("HTTP/1.0 200\r\n\r\n")).is_ok());
+        assert!(matches!(
+            read_head(&mut Cursor::new("HTTP/2 200\r\n\r\n")),
+            Err(ResponseError::VersionNotSupported)
+        ));
+        for head in [
+            "HTTP/1.1 20 OK\r\n\r\n",
+            "HTTP/1.1 099 Nope\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n",
+            "HTTP/1.1 200 OK\r\nNo colon\r\n\r\n",
+        ] {
+            assert!(read_head(&mut Cursor::new(head)).is_err(), "{head}");
+        }
+    }
+
+    #[test]
+    fn it_round_trips_with_the_serializer() {
+        for (body, version) in [
+            (Body::from("hello"), HttpVersion::V1_1),
+            (Body::stream(Cursor::new("hello")), HttpVersion::V1_1),
+            // Delimited by the end of the connection.
+            (Body::stream(Cursor::new("hello")), HttpVersion::V1_0),
+            (Body::empty(), HttpVersion::V1_1),
+        ] {
+            let headers = Headers::new("Content-Type: text/plain\r\nSet-Cookie: a=1");
+            let sent = written(Response::new(Status::Created, headers, body), &version);
+
+            let parsed = Response::from_reader(Cursor::new(sent.clone())).unwrap();
+            assert_eq!(Status::Created, parsed.status);
+            assert_eq!(Some("text/plain"), parsed.headers.get("Content-Type"));
+            assert_eq!(sent, written(parsed, &version));
+        }
+    }
+
+    #[test]
+    fn it_reads_every_body_framing() {
+        let response = parse(
+            "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 3, 3\r\n\r\nabcdef",
+        )
+        .unwrap();
+        assert_eq!(Some(3), response.body.content_length());
+        assert_eq!(b"abc".to_vec(), response.body.into_bytes().unwrap());
+
+        let response = parse(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 1\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(b"abc".to_vec(), response.body.into_bytes().unwrap());
+
+        let response = parse("HTTP/1.1 204 No Content\r\n\r\nabc").unwrap();
+        assert_eq!(Some(0), response.body.content_length());
+
+        let response = Response::from_reader_to(
+            Cursor::new("HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n"),
+            &Method::Head,
+        )
+        .unwrap();
+        assert_eq!(Some(0), response.body.content_length());
+
+        // Truncated bodies fail once they're read.
+        for input in [
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nabc",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n",
+        ] {
+            assert!(parse(input).unwrap().body.into_bytes().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn it_keeps_unknown_status_codes_and_custom_reasons() {
+        let status = parse("HTTP/1.1 499 Client Closed Request\r\n\r\n")
+            .unwrap()
+            .status;
+        assert_eq!(499, status.code());
+        assert_eq!("Client Closed Request", status.reason());
+        assert!(status.is_client_error());
+
+        let status = parse("HTTP/1.1 200 Fine\r\n\r\n").unwrap().status;
+        assert_eq!(Status::Ok, status);
+        assert_eq!("Fine", status.reason());
+
+        let response = parse("HTTP/1.1 799\r\nContent-Length: 0\r\n\r\n").unwrap();
+        assert_eq!("", response.status.reason());
+        assert_eq!(
+            b"HTTP/1.1 799 \r\nContent-Length: 0\r\n\r\n".to_vec(),
+            written(response, &HttpVersion::V1_1)
+        );
+    }
+
+    #[test]
+    fn it_finds_the_framing() {
+        let headers = |headers| Headers::new(headers);
+        assert_eq!(
+            Framing::Close,
+            framing(200, &headers("Transfer-Encoding: chunked, gzip"), false).unwrap()
+        );
+        assert_eq!(Framing::Close, framing(200, &headers(""), false).unwrap());
+        assert_eq!(
+            Framing::Empty,
+            framing(304, &headers("Content-Length: 5"), false).unwrap()
+        );
+        assert!(framing(200, &headers("Content-Length: 3, 4"), false).is_err());
+        assert!(framing(200, &headers("Content-Length: -1"), false).is_err());
+    }
+}
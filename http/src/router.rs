@@ -0,0 +1,266 @@
+use crate::request::{Method, Request};
+use crate::response::{Headers, Response, Status};
+use crate::server::Handler;
+
+/// A handler that dispatches requests to other handlers by method and path.
+///
+/// Besides the registered routes, it answers:
+/// - `HEAD` with the `GET` handler of the path, the server drops the body.
+/// - `OPTIONS` with the methods of the path in an `Allow` header, and `OPTIONS *` with
+///   the methods of every route.
+/// - `405 Method Not Allowed` for a registered path with another method, or
+///   `501 Not Implemented` if no route handles that method at all. The server leaves
+///   that to the handler, see [`Handler`].
+///
+/// Requests to unregistered paths go to the fallback handler, `404 Not Found` by default.
+///
+/// ```no_run
+/// use http::request::Request;
+/// use http::response::{Headers, Response, Status};
+/// use http::router::Router;
+/// use http::server::Server;
+/// use http::static_files::static_files;
+///
+/// fn hello(_: &mut Request) -> Response {
+///     Response::new(Status::Ok, Headers::new("Content-Type: text/plain"), "hello")
+/// }
+///
+/// let router = Router::new()
+///     .get("/hello", hello)
+///     .fallback(static_files("/", "public"));
+/// Server::new("0.0.0.0:4000", router).listen().unwrap();
+/// ```
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<Box<dyn Handler>>,
+}
+
+struct Route {
+    method: Method,
+    path: String,
+    handler: Box<dyn Handler>,
+}
+
+impl Router {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle `method` requests to `path` with `handler`. Paths are matched exactly,
+    /// without the query string.
+    #[must_use]
+    pub fn route<H: Handler + 'static>(
+        mut self,
+        method: Method,
+        path: &str,
+        handler: H,
+    ) -> Self {
+        self.routes.push(Route {
+            method,
+            path: path.to_string(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    #[must_use]
+    pub fn get<H: Handler + 'static>(self, path: &str, handler: H) -> Self {
+        self.route(Method::Get, path, handler)
+    }
+
+    #[must_use]
+    pub fn post<H: Handler + 'static>(self, path: &str, handler: H) -> Self {
+        self.route(Method::Post, path, handler)
+    }
+
+    #[must_use]
+    pub fn put<H: Handler + 'static>(self, path: &str, handler: H) -> Self {
+        self.route(Method::Put, path, handler)
+    }
+
+    #[must_use]
+    pub fn patch<H: Handler + 'static>(self, path: &str, handler: H) -> Self {
+        self.route(Method::Patch, path, handler)
+    }
+
+    #[must_use]
+    pub fn delete<H: Handler + 'static>(self, path: &str, handler: H) -> Self {
+        self.route(Method::Delete, path, handler)
+    }
+
+    /// Handle requests to paths without a route, e.g. with
+    /// [`StaticFiles`](crate::static_files::StaticFiles).
+    #[must_use]
+    pub fn fallback<H: Handler + 'static>(mut self, handler: H) -> Self {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Returns `false` for extension methods no route handles, they're answered with
+    /// `501 Not Implemented` rather than `405 Method Not Allowed`.
+    fn implements(&self, method: &Method) -> bool {
+        !matches!(method, Method::Extension(_))
+            || self.routes.iter().any(|route| route.method == *method)
+    }
+
+    /// The value of the `Allow` header for `routes`.
+    fn allow<'a>(routes: impl Iterator<Item = &'a Route>) -> String {
+        let mut methods: Vec<&Method> = Vec::new();
+        for route in routes {
+            if !methods.contains(&&route.method) {
+                methods.push(&route.method);
+            }
+        }
+        let head = methods.contains(&&Method::Get) && !methods.contains(&&Method::Head);
+        let options = !methods.contains(&&Method::Options);
+
+        let mut allow = methods.iter().map(ToString::to_string).collect::<Vec<_>>();
+        if head {
+            allow.push(Method::Head.to_string());
+        }
+        if options {
+            allow.push(Method::Options.to_string());
+        }
+        allow.join(", ")
+    }
+}
+
+impl Handler for Router {
+    fn handle(&self, request: &mut Request) -> Response {
+        let path = request.path();
+
+        if path == "*" && request.method == Method::Options {
+            let mut response = Response::new(Status::NoContent, Headers::default(), "");
+            response
+                .headers
+                .insert("Allow", Self::allow(self.routes.iter()));
+            return response;
+        }
+
+        let routes = self
+            .routes
+            .iter()
+            .filter(|route| route.path == path)
+            .collect::<Vec<_>>();
+        if routes.is_empty() {
+            return match &self.fallback {
+                Some(fallback) => fallback.handle(request),
+                None if !self.implements(&request.method) => {
+                    Response::from_status(Status::NotImplemented)
+                }
+                None => Response::from_status(Status::NotFound),
+            };
+        }
+
+        let find = |method: &Method| routes.iter().find(|route| route.method == *method);
+        let route = find(&request.method).or_else(|| {
+            // RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-9.3.2>
+            (request.method == Method::Head)
+                .then(|| find(&Method::Get))
+                .flatten()
+        });
+        if let Some(route) = route {
+            return route.handler.handle(request);
+        }
+
+        let allow = Self::allow(routes.into_iter());
+        let mut response = if request.method == Method::Options {
+            Response::new(Status::NoContent, Headers::default(), "")
+        } else if !self.implements(&request.method) {
+            Response::from_status(Status::NotImplemented)
+        } else {
+            Response::from_status(Status::MethodNotAllowed)
+        };
+        response.headers.insert("Allow", allow);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::Router;
+    use crate::request::{Method, Request};
+    use crate::response::{Headers, Response, Status};
+    use crate::server::Handler;
+    use std::io::Cursor;
+
+    fn router() -> Router {
+        Router::new()
+            .get("/users", |request: &mut Request| {
+                let method = request.method.to_string();
+                Response::new(Status::Ok, Headers::default(), method)
+            })
+            .post("/users", |_: &mut Request| {
+                Response::new(Status::Created, Headers::default(), "")
+            })
+            .delete("/users/1", |_: &mut Request| {
+                Response::new(Status::NoContent, Headers::default(), "")
+            })
+    }
+
+    fn send(router: &Router, method: &str, target: &str) -> Response {
+        let raw = format!("{method} {target} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        router.handle(&mut Request::from(Cursor::new(raw)).unwrap())
+    }
+
+    #[test]
+    fn it_dispatches_by_method_and_path() {
+        let router = router();
+        let response = send(&router, "GET", "/users?page=2");
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(Some(b"GET".as_slice()), response.body.as_bytes());
+        assert_eq!(Status::Created, send(&router, "POST", "/users").status);
+        assert_eq!(Status::NotFound, send(&router, "GET", "/posts").status);
+    }
+
+    #[test]
+    fn it_runs_get_handlers_for_head() {
+        let response = send(&router(), "HEAD", "/users");
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(Some(b"HEAD".as_slice()), response.body.as_bytes());
+    }
+
+    #[test]
+    fn it_lists_allowed_methods() {
+        let router = router();
+        let response = send(&router, "PUT", "/users");
+        assert_eq!(Status::MethodNotAllowed, response.status);
+        assert_eq!(
+            Some("GET, POST, HEAD, OPTIONS"),
+            response.headers.get("Allow")
+        );
+
+        let response = send(&router, "OPTIONS", "/users/1");
+        assert_eq!(Status::NoContent, response.status);
+        assert_eq!(Some("DELETE, OPTIONS"), response.headers.get("Allow"));
+
+        let response = send(&router, "PROPFIND", "/users");
+        assert_eq!(Status::NotImplemented, response.status);
+        assert_eq!(
+            Status::NotImplemented,
+            send(&router, "BREW", "/coffee").status
+        );
+
+        let response = send(&router, "OPTIONS", "*");
+        assert_eq!(Status::NoContent, response.status);
+        assert_eq!(
+            Some("GET, POST, DELETE, HEAD, OPTIONS"),
+            response.headers.get("Allow")
+        );
+    }
+
+    #[test]
+    fn it_falls_back_for_unknown_paths() {
+        let router = router().route(Method::Options, "/users", |_: &mut Request| {
+            Response::new(Status::Ok, Headers::new("Allow: GET"), "")
+        });
+        let router = router.fallback(|_: &mut Request| {
+            Response::new(Status::Gone, Headers::default(), "")
+        });
+        assert_eq!(Status::Gone, send(&router, "GET", "/posts").status);
+        assert_eq!(Status::Ok, send(&router, "OPTIONS", "/users").status);
+    }
+}
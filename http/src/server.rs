@@ -1,15 +1,86 @@
-use super::request::Request;
-use super::response::Response;
+use super::request::{HttpVersion, Method, Request, TlsInfo};
+use super::response::{Headers, Response, Status};
+use crate::date;
+use crate::http2::{self, frame::PREFACE};
+use crate::middleware::{Middleware, Next};
 use crate::threadpool::ThreadPool;
-use std::io::Write;
-use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+use std::io::{Cursor, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
 
-pub type Handler = fn(&mut Request) -> Response;
+/// Turns a request into a response.
+///
+/// Implemented for every function or closure taking a `&mut Request` and returning a
+/// `Response`, implement it directly for handlers that carry configuration, like
+/// [`StaticFiles`](crate::static_files::StaticFiles).
+///
+/// The server hands every request to the handler whatever its method, extension methods
+/// like `PROPFIND` included, since only the handler knows which ones it implements. It's
+/// up to the handler to answer the others with `501 Not Implemented`, as
+/// [`Router`](crate::router::Router) does.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-9.1>
+pub trait Handler: Send + Sync {
+    fn handle(&self, request: &mut Request) -> Response;
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&mut Request) -> Response + Send + Sync,
+{
+    fn handle(&self, request: &mut Request) -> Response {
+        self(request)
+    }
+}
 
 pub struct Server {
     listener: TcpListener,
-    handler: Handler,
+    handler: Arc<dyn Handler>,
     threadpool: Option<ThreadPool>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    config: Config,
+    defaults: Defaults,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+/// Headers added to responses that don't have them, see [`Server::server_header`] and
+/// [`Server::default_content_type`].
+#[derive(Default)]
+struct Defaults {
+    server: Option<String>,
+    content_type: Option<String>,
+}
+
+/// Connection-level settings shared by every connection of a [`Server`].
+#[derive(Clone, Copy)]
+struct Config {
+    http09: bool,
+    http2: bool,
+    max_body_size: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            http09: true,
+            http2: true,
+            max_body_size: None,
+        }
+    }
+}
+
+/// What every connection of a listening [`Server`] needs.
+struct Context {
+    handler: Arc<dyn Handler>,
+    /// The middlewares of the server, between [`Stamp`] and [`DefaultContentType`].
+    middlewares: Vec<Arc<dyn Middleware>>,
+    stamp: Arc<Stamp>,
+    config: Config,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
 }
 
 impl Server {
@@ -18,12 +89,17 @@ impl Server {
     /// # Panics
     ///
     /// Will panic if the socket can't bind to the address
-    pub fn new<A: ToSocketAddrs>(addr: A, handler: Handler) -> Self {
+    pub fn new<A: ToSocketAddrs, H: Handler + 'static>(addr: A, handler: H) -> Self {
         Server {
             #[expect(clippy::unwrap_used)]
             listener: TcpListener::bind(addr).unwrap(),
-            handler,
+            handler: Arc::new(handler),
             threadpool: None,
+            middlewares: Vec::new(),
+            config: Config::default(),
+            defaults: Defaults::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 
@@ -32,42 +108,153 @@ impl Server {
     /// # Panics
     ///
     /// Will panic if the socket can't bind to the address
-    pub fn threaded<A: ToSocketAddrs>(
+    pub fn threaded<A: ToSocketAddrs, H: Handler + 'static>(
         addr: A,
-        handler: Handler,
+        handler: H,
         pool_count: usize,
     ) -> Self {
         Server {
             #[expect(clippy::unwrap_used)]
             listener: TcpListener::bind(addr).unwrap(),
-            handler,
+            handler: Arc::new(handler),
             threadpool: Some(ThreadPool::new(pool_count)),
+            middlewares: Vec::new(),
+            config: Config::default(),
+            defaults: Defaults::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 
-    /// Start listening for incoming connections.
+    /// Add a middleware around the handler.
     ///
-    /// # Panics
+    /// Middlewares run in the order they're added: the first one sees the request first
+    /// and the response last.
+    #[must_use]
+    pub fn wrap<M: Middleware + 'static>(mut self, middleware: M) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Whether to answer HTTP/0.9 simple requests (`GET /path` without a version) with
+    /// the bare body. When disabled, their connections are closed without a response.
+    /// Enabled by default.
+    #[must_use]
+    pub fn http09(mut self, enabled: bool) -> Self {
+        self.config.http09 = enabled;
+        self
+    }
+
+    /// Whether to speak [HTTP/2](crate::http2) to clients that start with the HTTP/2
+    /// preface or ask for `Upgrade: h2c`, or pick `h2` with ALPN over TLS. Enabled by
+    /// default.
+    ///
+    /// An HTTP/2 connection keeps its thread until the client closes it, and runs every
+    /// request in a thread of its own, outside the pool of [`Server::threaded`]. A client
+    /// may have at most 16 requests running at once per connection, further ones are
+    /// refused until one completes, so a pool of `n` threads can run up to `16 * n` more
+    /// for HTTP/2 clients. Disable HTTP/2 where that's more than the server can afford.
+    #[must_use]
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.config.http2 = enabled;
+        self
+    }
+
+    /// The largest body [`Request::form`], [`Request::json_value`] and `Request::json`
+    /// read, [`DEFAULT_MAX_BODY_SIZE`](crate::request::DEFAULT_MAX_BODY_SIZE) by default.
+    /// Larger ones fail with [`BodyError::TooLarge`](crate::request::body::BodyError),
+    /// answered with `413 Payload Too Large`. Middlewares and handlers can change it for
+    /// a request with [`Request::set_max_body_size`].
+    #[must_use]
+    pub fn max_body_size(mut self, bytes: u64) -> Self {
+        self.config.max_body_size = Some(bytes);
+        self
+    }
+
+    /// Send a `Server` header with every response, e.g. `my-app/1.0`, unless the handler
+    /// set one. None is sent by default, it tells attackers what to look for.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-10.2.4>
+    #[must_use]
+    pub fn server_header(mut self, product: impl Into<String>) -> Self {
+        self.defaults.server = Some(product.into());
+        self
+    }
+
+    /// The `Content-Type` of responses with content whose handler set none, e.g.
+    /// `text/html; charset=utf-8`. Without one they're sent as they are, and clients
+    /// guess.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-8.3>
+    #[must_use]
+    pub fn default_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.defaults.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Serve HTTPS instead of HTTP, see [`tls`](crate::tls).
+    #[cfg(feature = "tls")]
+    #[must_use]
+    pub fn tls(mut self, config: TlsConfig) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// The address the server is bound to, e.g. to find the port picked for port `0`.
+    ///
+    /// # Errors
     ///
-    /// Will panic if a request can't be parsed, this will change once 400 handlers are
-    /// introduced.
+    /// Will return an error if the socket's address can't be read.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Start listening for incoming connections.
+    ///
+    /// Requests that can't be parsed are answered with `400 Bad Request`, and requests
+    /// for versions other than HTTP/1.x with `505 HTTP Version Not Supported`. Any
+    /// method goes to the handler, see [`Handler`].
+    /// Responses are always sent as HTTP/1.1, with a `Date` header.
     ///
     /// # Errors
     ///
-    /// Will return an error if a `TCPStream` can't be opened.
+    /// Will return an error if a `TCPStream` can't be opened, or the TLS certificates
+    /// can't be loaded.
     pub fn listen(&self) -> std::io::Result<()> {
+        let stamp = Arc::new(Stamp {
+            server: self.defaults.server.clone(),
+        });
+        let mut middlewares: Vec<Arc<dyn Middleware>> = vec![stamp.clone()];
+        if let Some(bytes) = self.config.max_body_size {
+            middlewares.push(Arc::new(MaxBodySize(bytes)));
+        }
+        middlewares.extend(self.middlewares.iter().cloned());
+        if let Some(content_type) = &self.defaults.content_type {
+            middlewares.push(Arc::new(DefaultContentType(content_type.clone())));
+        }
+        let context = Arc::new(Context {
+            handler: self.handler.clone(),
+            middlewares,
+            stamp,
+            config: self.config,
+            #[cfg(feature = "tls")]
+            tls: self
+                .tls
+                .as_ref()
+                .map(|tls| tls.server_config(self.config.http2))
+                .transpose()?,
+        });
+
         if let Some(pool) = &self.threadpool {
             for stream in self.listener.incoming() {
-                let handler = self.handler;
+                let context = context.clone();
                 let _ = pool.execute(move || {
-                    if let Err(e) = handle_connection(handler, stream) {
+                    if let Err(e) = context.handle_connection(stream) {
                         eprintln!("Error handling connection: {e:?}");
                     }
                 });
             }
         } else {
             for stream in self.listener.incoming() {
-                if let Err(e) = handle_connection(self.handler, stream) {
+                if let Err(e) = context.handle_connection(stream) {
                     eprintln!("Error handling connection: {e:?}");
                 }
             }
@@ -77,39 +264,405 @@ impl Server {
     }
 }
 
-/// Handles an incoming connection by parsing the HTTP request from the provided
-/// `TcpStream`, invoking the `handler` to generate a response, and writing
-/// the formatted HTTP response back to the stream.
+impl Context {
+    /// Handles an incoming connection by parsing the HTTP request from the provided
+    /// `TcpStream` (or handing it over to HTTP/2), invoking the handler (through the
+    /// middlewares) to generate a response, and writing the formatted HTTP response
+    /// back to the stream.
+    ///
+    /// # TODOs
+    /// Allow connections to be re-used.
+    #[inline]
+    fn handle_connection(
+        &self,
+        stream: std::io::Result<TcpStream>,
+    ) -> std::io::Result<()> {
+        let stream = stream?;
+        let remote_addr = stream.peer_addr().ok();
+
+        #[cfg(feature = "tls")]
+        if let Some(config) = &self.tls {
+            let stream = crate::tls::accept(config.clone(), stream)?;
+            let info = stream.info();
+            if info.alpn_protocol.as_deref() == Some(b"h2") {
+                return http2::serve(
+                    &*self.handler,
+                    &self.middlewares,
+                    &stream,
+                    &stream,
+                    None,
+                    Some(Arc::new(info)),
+                    remote_addr,
+                );
+            }
+            if let Some((on_upgrade, buffered)) =
+                self.serve(&stream, Vec::new(), Some(Arc::new(info)), remote_addr)?
+            {
+                upgrade(on_upgrade, Io::Tls(Arc::new(stream)), buffered);
+            }
+            return Ok(());
+        }
+
+        let head = if self.config.http2 {
+            sniff_preface(&stream)?
+        } else {
+            Vec::new()
+        };
+        if head == PREFACE {
+            return http2::serve(
+                &*self.handler,
+                &self.middlewares,
+                Cursor::new(head).chain(&stream),
+                &stream,
+                None,
+                None,
+                remote_addr,
+            );
+        }
+        if let Some((on_upgrade, buffered)) =
+            self.serve(&stream, head, None, remote_addr)?
+        {
+            upgrade(on_upgrade, Io::Tcp(stream), buffered);
+        }
+        Ok(())
+    }
+
+    /// Serve an HTTP/1 request, whose first bytes were already read into `head`. Returns
+    /// what takes over the connection, and the bytes read past the request, if the
+    /// response hands it over.
+    fn serve<S: Sync>(
+        &self,
+        stream: &S,
+        head: Vec<u8>,
+        tls: Option<Arc<TlsInfo>>,
+        remote_addr: Option<SocketAddr>,
+    ) -> std::io::Result<Option<(OnUpgrade, Vec<u8>)>>
+    where
+        for<'s> &'s S: Read + Write,
+    {
+        let mut request = match Request::from(Cursor::new(head).chain(stream)) {
+            Ok(request) => request,
+            Err(e) => {
+                let mut response = Response::from(e);
+                self.stamp.apply(&mut response);
+                response.headers.insert("Connection", "close");
+                return response.write_to(stream, &HttpVersion::V1_1).map(|()| None);
+            }
+        };
+        if request.http_version == HttpVersion::V0_9 && !self.config.http09 {
+            return Ok(None);
+        }
+        // h2c is for cleartext only, TLS clients pick h2 with ALPN.
+        if let Some(upgrade) = (self.config.http2 && tls.is_none())
+            .then(|| http2::upgrade(&mut request))
+            .flatten()
+        {
+            Response::new(
+                Status::SwitchingProtocols,
+                Headers::new("Connection: Upgrade\r\nUpgrade: h2c"),
+                "",
+            )
+            .write_to(stream, &HttpVersion::V1_1)?;
+            return http2::serve(
+                &*self.handler,
+                &self.middlewares,
+                stream,
+                stream,
+                Some(upgrade),
+                None,
+                remote_addr,
+            )
+            .map(|()| None);
+        }
+        request.tls = tls;
+        request.remote_addr = remote_addr;
+        if request.http_version == HttpVersion::V1_1 {
+            request.interim = Some(Box::new(stream));
+        }
+
+        let mut response = Next::new(&*self.handler, &self.middlewares).run(&mut request);
+        let hands_over = response.status == Status::SwitchingProtocols
+            || (request.method == Method::Connect && response.status.is_success());
+        if let Some(on_upgrade) = response.on_upgrade.take().filter(|_| hands_over) {
+            response.write_bare_head_to(stream)?;
+            return Ok(Some((on_upgrade, std::mem::take(&mut request.buffered))));
+        }
+
+        // Connections aren't reused, say so instead of leaving clients to find out.
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc9112#section-9.6>
+        response.headers.insert("Connection", "close");
+        if request.method == Method::Head {
+            response.write_head_to(stream, &request.http_version)?;
+        } else {
+            response.write_to(stream, &request.http_version)?;
+        }
+        Ok(None)
+    }
+}
+
+/// Adds the headers every response gets: `Date`, and `Server` if one is configured. It
+/// runs before the middlewares of the server, so it sees their responses too.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-6.6.1>
+struct Stamp {
+    server: Option<String>,
+}
+
+impl Stamp {
+    fn apply(&self, response: &mut Response) {
+        if !response.headers.contains("Date") {
+            response.headers.insert("Date", date::now());
+        }
+        if let Some(server) = self.server.as_deref() {
+            if !response.headers.contains("Server") {
+                response.headers.insert("Server", server);
+            }
+        }
+    }
+}
+
+impl Middleware for Stamp {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let mut response = next.run(request);
+        self.apply(&mut response);
+        response
+    }
+}
+
+/// Sets the [`Server::max_body_size`] of every request, ahead of the user's middlewares.
+struct MaxBodySize(u64);
+
+impl Middleware for MaxBodySize {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        request.set_max_body_size(self.0);
+        next.run(request)
+    }
+}
+
+/// Sets the `Content-Type` of responses with content that have none. It runs after the
+/// middlewares of the server, which see the responses as if the handler had set it.
+struct DefaultContentType(String);
+
+impl Middleware for DefaultContentType {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let mut response = next.run(request);
+        if !response.headers.contains("Content-Type")
+            && response.may_have_body()
+            && response.body.content_length() != Some(0)
+        {
+            response.headers.insert("Content-Type", self.0.as_str());
+        }
+        response
+    }
+}
+
+/// A connection handed over by the server after a response made with
+/// [`Response::upgrade`], e.g. to a [`WebSocket`](crate::websocket::WebSocket).
 ///
-/// # TODOs
-/// Allow connections to be re-used.
-#[inline]
-fn handle_connection(
-    handler: Handler,
-    stream: std::io::Result<TcpStream>,
-) -> std::io::Result<()> {
-    let stream = stream?;
-
-    #[expect(clippy::unwrap_used)]
-    let mut request = Request::from(&stream).unwrap();
-
-    let response = handler(&mut request);
-
-    let mut headers = response.headers.headers;
-    if !headers.is_empty() {
-        headers.insert_str(0, "\r\n");
-    }
-
-    let resp = format!(
-        "{http_version} {status_number} {status_description}\r\nContent-Length: {len}{headers}\r\n\r\n",
-        http_version = request.http_version,
-        status_number = response.status as u16,
-        status_description = response.status,
-        headers = headers,
-        len = response.body.len(),
-    );
-
-    let _ = (&stream).write_all(resp.as_bytes()); // status line + headers
-    let _ = (&stream).write_all(&response.body); // body
-    Ok(())
+/// Reading yields the bytes the client sent past its request first, then reads from the
+/// connection.
+pub struct Upgraded {
+    io: Io,
+    buffered: Cursor<Vec<u8>>,
+}
+
+enum Io {
+    Tcp(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Arc<crate::tls::TlsStream>),
+    #[cfg(test)]
+    Memory(Box<dyn Stream>),
+}
+
+#[cfg(test)]
+trait Stream: Read + Write + Send {}
+
+#[cfg(test)]
+impl<S: Read + Write + Send> Stream for S {}
+
+impl Upgraded {
+    #[cfg(test)]
+    pub(crate) fn new<S: Read + Write + Send + 'static>(stream: S) -> Self {
+        Upgraded {
+            io: Io::Memory(Box::new(stream)),
+            buffered: Cursor::default(),
+        }
+    }
+
+    /// Another handle to the same connection, e.g. to read and write from different
+    /// threads. Bytes buffered in this handle aren't shared: read them from it.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the socket can't be cloned.
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        let io = match &self.io {
+            Io::Tcp(stream) => Io::Tcp(stream.try_clone()?),
+            #[cfg(feature = "tls")]
+            Io::Tls(stream) => Io::Tls(stream.clone()),
+            #[cfg(test)]
+            Io::Memory(_) => return Err(std::io::ErrorKind::Unsupported.into()),
+        };
+        Ok(Upgraded {
+            io,
+            buffered: Cursor::default(),
+        })
+    }
+
+    /// Shut down reads, writes or both, like [`TcpStream::shutdown`]. Other handles to
+    /// the connection are affected too, e.g. a blocked read returns.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the socket can't be shut down.
+    pub fn shutdown(&self, how: Shutdown) -> std::io::Result<()> {
+        match &self.io {
+            Io::Tcp(stream) => stream.shutdown(how),
+            #[cfg(feature = "tls")]
+            Io::Tls(stream) => stream.shutdown(how),
+            #[cfg(test)]
+            Io::Memory(_) => Ok(()),
+        }
+    }
+
+    /// The TCP connection, and the bytes of it that were buffered but not read yet.
+    ///
+    /// # Errors
+    ///
+    /// Will return `self` back for connections that aren't plain TCP, e.g. over TLS.
+    pub fn into_tcp_stream(self) -> Result<(TcpStream, Vec<u8>), Self> {
+        let Upgraded { io, buffered } = self;
+        // Connections are always TCP without the `tls` feature.
+        #[allow(irrefutable_let_patterns)]
+        if let Io::Tcp(stream) = io {
+            let position = usize::try_from(buffered.position()).unwrap_or(usize::MAX);
+            let mut buffered = buffered.into_inner();
+            buffered.drain(..position.min(buffered.len()));
+            Ok((stream, buffered))
+        } else {
+            Err(Upgraded { io, buffered })
+        }
+    }
+}
+
+impl Read for Upgraded {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.buffered.position() < self.buffered.get_ref().len() as u64 {
+            return self.buffered.read(buf);
+        }
+        match &mut self.io {
+            Io::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Io::Tls(stream) => (&**stream).read(buf),
+            #[cfg(test)]
+            Io::Memory(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Upgraded {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.io {
+            Io::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Io::Tls(stream) => (&**stream).write(buf),
+            #[cfg(test)]
+            Io::Memory(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.io {
+            Io::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Io::Tls(stream) => (&**stream).flush(),
+            #[cfg(test)]
+            Io::Memory(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Takes over a connection after its response, see [`Upgraded`].
+pub(crate) type OnUpgrade = Box<dyn FnOnce(Upgraded) + Send>;
+
+/// Hand the connection over, in a thread of its own so it doesn't hold up the server.
+fn upgrade(on_upgrade: OnUpgrade, io: Io, buffered: Vec<u8>) {
+    let upgraded = Upgraded {
+        io,
+        buffered: Cursor::new(buffered),
+    };
+    thread::spawn(move || on_upgrade(upgraded));
+}
+
+/// Read the start of the connection for as long as it could be the HTTP/2 client preface.
+/// The bytes read are returned, to be parsed as HTTP/1 if they're not the preface.
+fn sniff_preface(mut stream: &TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(PREFACE.len());
+    let mut buf = [0; PREFACE.len()];
+    while head.len() < PREFACE.len() && PREFACE.starts_with(&head) {
+        let read = stream.read(&mut buf[..PREFACE.len() - head.len()])?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{DefaultContentType, Stamp};
+    use crate::date;
+    use crate::middleware::{Middleware, Next};
+    use crate::request::Request;
+    use crate::response::{Headers, Response, Status};
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    fn send(handler: fn(&mut Request) -> Response) -> Response {
+        let middlewares: [Arc<dyn Middleware>; 2] = [
+            Arc::new(Stamp {
+                server: Some("test/1.0".to_string()),
+            }),
+            Arc::new(DefaultContentType("text/html".to_string())),
+        ];
+        let mut request =
+            Request::from(Cursor::new("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"))
+                .unwrap();
+        Next::new(&handler, &middlewares).run(&mut request)
+    }
+
+    #[test]
+    fn it_adds_default_headers() {
+        let response = send(|_| Response::new(Status::Ok, Headers::default(), "<p>"));
+        assert!(response.headers.get("Date").and_then(date::parse).is_some());
+        assert_eq!(Some("test/1.0"), response.headers.get("Server"));
+        assert_eq!(Some("text/html"), response.headers.get("Content-Type"));
+
+        let response = send(|_| {
+            Response::new(
+                Status::Ok,
+                Headers::new("Server: app\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT"),
+                Response::text("hi").body,
+            )
+        });
+        assert_eq!(Some("app"), response.headers.get("Server"));
+        assert_eq!(
+            Some("Sun, 06 Nov 1994 08:49:37 GMT"),
+            response.headers.get("Date")
+        );
+        assert_eq!(Some("text/html"), response.headers.get("Content-Type"));
+
+        assert_eq!(
+            Some("text/plain; charset=utf-8"),
+            send(|_| Response::text("hi")).headers.get("Content-Type")
+        );
+        for response in [
+            send(|_| Response::new(Status::NoContent, Headers::default(), "")),
+            send(|_| Response::new(Status::Ok, Headers::default(), "")),
+        ] {
+            assert!(!response.headers.contains("Content-Type"));
+            assert!(response.headers.contains("Date"));
+        }
+    }
 }
@@ -0,0 +1,351 @@
+pub mod store;
+
+pub use store::{FileStore, MemoryStore, SessionData, SessionStore};
+
+use crate::cookie::{is_attribute_value, SameSite, SetCookie};
+use crate::middleware::{Middleware, Next};
+use crate::random;
+use crate::request::Request;
+use crate::response::Response;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The state of a client across requests, see [`Request::session`].
+///
+/// Changes are saved by the [`Sessions`] middleware once the handler returns. A session
+/// is only stored, and its cookie sent, once something is inserted into it.
+#[derive(Debug, Default)]
+pub struct Session {
+    id: Option<String>,
+    data: SessionData,
+    changed: bool,
+    rotate: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    /// The session ID, `None` for a session that hasn't been stored yet.
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the value of `key`, parsed as a `T`.
+    #[must_use]
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.data.get(key)?.parse().ok()
+    }
+
+    pub fn insert<T: Display>(&mut self, key: &str, value: T) {
+        self.data.insert(key.to_string(), value.to_string());
+        self.changed = true;
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let value = self.data.remove(key);
+        self.changed |= value.is_some();
+        value
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Move the data to a new session ID, invalidating the current one.
+    ///
+    /// Call this when the user logs in, so an ID planted before login (session fixation)
+    /// is useless afterwards.
+    pub fn rotate(&mut self) {
+        self.rotate = true;
+    }
+
+    /// Delete the session and its cookie, e.g. when the user logs out.
+    pub fn destroy(&mut self) {
+        self.data.clear();
+        self.destroyed = true;
+    }
+}
+
+/// A middleware that gives every request a [`Session`], identified by a random ID stored
+/// in a cookie.
+///
+/// ```no_run
+/// use http::request::Request;
+/// use http::response::{Headers, Response, Status};
+/// use http::server::Server;
+/// use http::session::Sessions;
+///
+/// fn visits(request: &mut Request) -> Response {
+///     let session = request.session().expect("Sessions is installed");
+///     let visits = session.get::<u32>("visits").unwrap_or_default() + 1;
+///     session.insert("visits", visits);
+///     Response::new(Status::Ok, Headers::default(), visits.to_string())
+/// }
+///
+/// Server::new("0.0.0.0:4000", visits)
+///     .wrap(Sessions::default())
+///     .listen()
+///     .unwrap();
+/// ```
+pub struct Sessions {
+    store: Box<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    path: String,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl Default for Sessions {
+    /// Sessions kept in memory for a day.
+    fn default() -> Self {
+        Sessions::new(MemoryStore::new())
+    }
+}
+
+impl Sessions {
+    #[must_use]
+    pub fn new<S: SessionStore + 'static>(store: S) -> Self {
+        Sessions {
+            store: Box::new(store),
+            cookie_name: "session_id".to_string(),
+            ttl: Duration::from_hours(24),
+            path: "/".to_string(),
+            secure: false,
+            same_site: SameSite::Lax,
+        }
+    }
+
+    #[must_use]
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    /// How long a session lives after it was last changed, the default is a day.
+    #[must_use]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The `Path` of the cookie, `/` by default.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `path` has a `;` or a control character, when the middleware is
+    /// built rather than on every request.
+    #[must_use]
+    pub fn path(mut self, path: &str) -> Self {
+        assert!(is_attribute_value(path), "Invalid cookie path {path:?}");
+        self.path = path.to_string();
+        self
+    }
+
+    /// Only send the cookie over HTTPS.
+    #[must_use]
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    #[must_use]
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    fn cookie(&self, value: &str) -> SetCookie {
+        SetCookie::new(&self.cookie_name, value)
+            .path(&self.path)
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site)
+    }
+
+    /// Load the session named by the request's cookie. Unknown IDs are not reused, so a
+    /// client can't pick its own.
+    fn load(&self, request: &Request) -> Session {
+        let id = request
+            .cookies()
+            .get(&self.cookie_name)
+            .filter(|id| is_valid_id(id))
+            .map(String::from);
+
+        let Some(id) = id else {
+            return Session::default();
+        };
+        match self.store.load(&id) {
+            Ok(Some(data)) => Session {
+                id: Some(id),
+                data,
+                ..Session::default()
+            },
+            Ok(None) => Session::default(),
+            Err(e) => {
+                eprintln!("Error loading session: {e:?}");
+                Session::default()
+            }
+        }
+    }
+
+    /// Persist the session after the handler ran, and set or clear its cookie.
+    fn save(&self, session: Session, response: &mut Response) -> std::io::Result<()> {
+        if session.destroyed {
+            if let Some(id) = session.id {
+                self.store.remove(&id)?;
+                let removal = SetCookie::removal(&self.cookie_name).path(&self.path);
+                response.headers.append("Set-Cookie", removal.to_string());
+            }
+            return Ok(());
+        }
+        if !(session.changed || session.rotate)
+            || (session.id.is_none() && session.is_empty())
+        {
+            return Ok(());
+        }
+
+        let id = match session.id {
+            Some(id) if !session.rotate => id,
+            previous => {
+                if let Some(previous) = previous {
+                    self.store.remove(&previous)?;
+                }
+                generate_id()?
+            }
+        };
+        self.store.save(&id, &session.data, self.ttl)?;
+        let cookie = self.cookie(&id).max_age(self.ttl);
+        response.headers.append("Set-Cookie", cookie.to_string());
+        Ok(())
+    }
+}
+
+impl Middleware for Sessions {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let session = self.load(request);
+        request.extensions.insert(session);
+
+        let mut response = next.run(request);
+
+        if let Some(session) = request.extensions.remove::<Session>() {
+            if let Err(e) = self.save(session, &mut response) {
+                eprintln!("Error saving session: {e:?}");
+            }
+        }
+        response
+    }
+}
+
+/// A new session ID: 256 random bits, hex encoded.
+fn generate_id() -> std::io::Result<String> {
+    random::hex::<32>()
+}
+
+fn is_valid_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::Sessions;
+    use crate::middleware::{Middleware, Next};
+    use crate::request::Request;
+    use crate::response::{Headers, Response, Status};
+    use std::io::Cursor;
+
+    fn handler(request: &mut Request) -> Response {
+        let path = request.path().to_string();
+        let session = request.session().unwrap();
+        match path.as_str() {
+            "/login" => {
+                session.insert("user", 42);
+                session.rotate();
+            }
+            "/logout" => session.destroy(),
+            _ => {}
+        }
+        let user = session.get::<u32>("user").map(|u| u.to_string());
+        Response::new(Status::Ok, Headers::default(), user.unwrap_or_default())
+    }
+
+    /// Send a request, returns the body and the session ID set by the response.
+    fn send(
+        sessions: &Sessions,
+        path: &str,
+        id: Option<&str>,
+    ) -> (String, Option<String>) {
+        let cookie =
+            id.map_or(String::new(), |id| format!("Cookie: session_id={id}\r\n"));
+        let raw = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n{cookie}\r\n");
+        let mut request = Request::from(Cursor::new(raw)).unwrap();
+        let response = sessions.handle(&mut request, Next::new(&handler, &[]));
+
+        let set_cookie = response.headers.get("Set-Cookie").map(|cookie| {
+            let (pair, _) = cookie.split_once(';').unwrap();
+            pair.strip_prefix("session_id=").unwrap().to_string()
+        });
+        let body = String::from_utf8(response.body.into_bytes().unwrap()).unwrap();
+        (body, set_cookie)
+    }
+
+    #[test]
+    fn it_creates_sessions_lazily() {
+        let sessions = Sessions::default();
+        assert_eq!((String::new(), None), send(&sessions, "/", None));
+    }
+
+    #[test]
+    fn it_keeps_state_across_requests() {
+        let sessions = Sessions::default();
+        let (body, id) = send(&sessions, "/login", None);
+        assert_eq!("42", body);
+        let id = id.unwrap();
+        assert_eq!(64, id.len());
+
+        assert_eq!(("42".to_string(), None), send(&sessions, "/", Some(&id)));
+    }
+
+    #[test]
+    fn it_rotates_the_id_on_login() {
+        let sessions = Sessions::default();
+        let (_, id) = send(&sessions, "/login", None);
+        let id = id.unwrap();
+
+        let (_, rotated) = send(&sessions, "/login", Some(&id));
+        let rotated = rotated.unwrap();
+        assert_ne!(id, rotated);
+        assert_eq!(String::new(), send(&sessions, "/", Some(&id)).0);
+        assert_eq!("42", send(&sessions, "/", Some(&rotated)).0);
+    }
+
+    #[test]
+    fn it_invalidates_the_session_on_logout() {
+        let sessions = Sessions::default();
+        let (_, id) = send(&sessions, "/login", None);
+        let id = id.unwrap();
+
+        assert_eq!(
+            (String::new(), Some(String::new())),
+            send(&sessions, "/logout", Some(&id))
+        );
+        assert_eq!(String::new(), send(&sessions, "/", Some(&id)).0);
+    }
+
+    #[test]
+    fn it_ignores_unknown_ids() {
+        let sessions = Sessions::default();
+        let planted = "a".repeat(64);
+        let (_, id) = send(&sessions, "/login", Some(&planted));
+        assert_ne!(planted, id.unwrap());
+    }
+
+    #[test]
+    #[should_panic = "Invalid cookie path"]
+    fn it_rejects_invalid_paths_when_built() {
+        let _ = Sessions::default().path("/; Domain=evil.example");
+    }
+}
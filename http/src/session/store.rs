@@ -0,0 +1,245 @@
+use crate::request::uri::{percent_decode, percent_encode};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The values stored in a session.
+pub type SessionData = HashMap<String, String>;
+
+/// Where [`Sessions`](super::Sessions) keeps session data between requests.
+///
+/// IDs are generated by the middleware and are always 64 hex characters.
+#[expect(clippy::module_name_repetitions)]
+pub trait SessionStore: Send + Sync {
+    /// Returns the data of the session `id`, or `None` if it doesn't exist or expired.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the store can't be read.
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>>;
+
+    /// Store the data of the session `id`, expiring after `ttl`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the store can't be written.
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()>;
+
+    /// Delete the session `id`, if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the store can't be written.
+    fn remove(&self, id: &str) -> io::Result<()>;
+}
+
+/// Keeps sessions in memory, they are lost when the server stops.
+///
+/// Expired sessions are dropped when they're loaded, and swept at most once a minute when
+/// a session is saved.
+#[derive(Default)]
+#[expect(clippy::module_name_repetitions)]
+pub struct MemoryStore {
+    inner: Mutex<MemoryStoreInner>,
+}
+
+#[derive(Default)]
+struct MemoryStoreInner {
+    sessions: HashMap<String, (SessionData, Instant)>,
+    last_sweep: Option<Instant>,
+}
+
+impl MemoryStore {
+    const SWEEP_INTERVAL: Duration = Duration::from_mins(1);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryStoreInner> {
+        // The map is never left half-updated, so a panic elsewhere doesn't invalidate it.
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let mut inner = self.lock();
+        match inner.sessions.get(id) {
+            Some((_, expires)) if *expires <= Instant::now() => {
+                inner.sessions.remove(id);
+                Ok(None)
+            }
+            Some((data, _)) => Ok(Some(data.clone())),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        let now = Instant::now();
+        let mut inner = self.lock();
+        if inner
+            .last_sweep
+            .is_none_or(|last| now.duration_since(last) >= Self::SWEEP_INTERVAL)
+        {
+            inner.sessions.retain(|_, (_, expires)| *expires > now);
+            inner.last_sweep = Some(now);
+        }
+        inner
+            .sessions
+            .insert(id.to_string(), (data.clone(), now + ttl));
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> io::Result<()> {
+        self.lock().sessions.remove(id);
+        Ok(())
+    }
+}
+
+/// Keeps each session in a file named after its ID, so sessions survive restarts.
+///
+/// The first line of a file is the expiry time in seconds since the epoch, followed by a
+/// percent-encoded `key=value` line per entry. Expired files are deleted when loaded.
+#[expect(clippy::module_name_repetitions)]
+pub struct FileStore {
+    directory: PathBuf,
+}
+
+impl FileStore {
+    /// Store sessions in `directory`, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the directory can't be created.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(FileStore { directory })
+    }
+
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        // Never let an ID escape the directory.
+        if id.is_empty() || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Invalid session ID",
+            ));
+        }
+        Ok(self.directory.join(id))
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let path = self.path(id)?;
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let invalid = || io::Error::new(ErrorKind::InvalidData, "Corrupted session file");
+        let mut lines = contents.lines();
+        let expires: u64 = lines
+            .next()
+            .and_then(|line| line.parse().ok())
+            .ok_or_else(invalid)?;
+        if UNIX_EPOCH + Duration::from_secs(expires) <= SystemTime::now() {
+            fs::remove_file(path)?;
+            return Ok(None);
+        }
+
+        let decode = |s: &str| {
+            percent_decode(s)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(invalid)
+        };
+        lines
+            .map(|line| {
+                let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+                Ok((decode(key)?, decode(value)?))
+            })
+            .collect::<io::Result<_>>()
+            .map(Some)
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        let path = self.path(id)?;
+        let expires = (SystemTime::now() + ttl)
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        let mut contents = format!("{expires}\n");
+        for (key, value) in data {
+            contents.push_str(&percent_encode(key.as_bytes()));
+            contents.push('=');
+            contents.push_str(&percent_encode(value.as_bytes()));
+            contents.push('\n');
+        }
+
+        // Write then rename, so a concurrent load never sees a partial file.
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(temporary, path)
+    }
+
+    fn remove(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{FileStore, MemoryStore, SessionData, SessionStore};
+    use std::time::Duration;
+
+    fn data() -> SessionData {
+        SessionData::from([
+            ("user".to_string(), "42".to_string()),
+            ("note".to_string(), "a=b&c\nd%".to_string()),
+        ])
+    }
+
+    fn assert_stores_sessions(store: &impl SessionStore) {
+        assert_eq!(None, store.load("aa").unwrap());
+
+        store.save("aa", &data(), Duration::from_mins(1)).unwrap();
+        assert_eq!(Some(data()), store.load("aa").unwrap());
+
+        store.remove("aa").unwrap();
+        assert_eq!(None, store.load("aa").unwrap());
+        store.remove("aa").unwrap();
+
+        store.save("bb", &data(), Duration::ZERO).unwrap();
+        assert_eq!(None, store.load("bb").unwrap());
+    }
+
+    #[test]
+    fn it_stores_sessions_in_memory() {
+        assert_stores_sessions(&MemoryStore::new());
+    }
+
+    #[test]
+    fn it_stores_sessions_in_files() {
+        let directory =
+            std::env::temp_dir().join(format!("http-sessions-{}", std::process::id()));
+        let store = FileStore::new(&directory).unwrap();
+        assert_stores_sessions(&store);
+
+        assert!(store.load("../etc/passwd").is_err());
+        assert!(store.save("", &data(), Duration::ZERO).is_err());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
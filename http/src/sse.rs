@@ -0,0 +1,349 @@
+//! Server-sent events: responses that stream `text/event-stream` events to the client as
+//! they're sent.
+//!
+//! The handler returns the response of a [`channel`] and keeps its [`Sender`], usually by
+//! moving it to another thread. The stream ends when every sender is dropped, and senders
+//! find out the client is gone once the server fails to write to it. Comments are sent
+//! while no events are, so a gone client is noticed even on a quiet stream.
+//!
+//! ```no_run
+//! use http::request::Request;
+//! use http::response::Response;
+//! use http::server::Server;
+//! use http::sse::{self, Event};
+//! use std::time::Duration;
+//!
+//! fn ticks(request: &mut Request) -> Response {
+//!     let mut tick: u64 = request
+//!         .last_event_id()
+//!         .and_then(|id| id.parse().ok())
+//!         .unwrap_or_default();
+//!     let (sender, response) = sse::channel();
+//!     std::thread::spawn(move || loop {
+//!         tick += 1;
+//!         let event = Event::data(format!("tick {tick}")).id(tick.to_string());
+//!         if sender.send(event).is_err() {
+//!             break;
+//!         }
+//!         std::thread::sleep(Duration::from_secs(1));
+//!     });
+//!     response
+//! }
+//!
+//! Server::threaded("0.0.0.0:4000", ticks, 4).listen().unwrap();
+//! ```
+//!
+//! RFC: <https://html.spec.whatwg.org/multipage/server-sent-events.html>
+
+use crate::response::{Body, Headers, Response, Status};
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An event, written as `field: value` lines followed by a blank line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    kind: Option<String>,
+    id: Option<String>,
+    data: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// An event without data, only useful to set its `id` or `retry`: clients don't
+    /// dispatch events without data.
+    #[must_use]
+    pub fn new() -> Self {
+        Event::default()
+    }
+
+    /// A `message` event carrying `data`, which is sent as one `data` field per line.
+    #[must_use]
+    pub fn data(data: impl Into<String>) -> Self {
+        Event {
+            data: Some(data.into()),
+            ..Event::default()
+        }
+    }
+
+    /// The type of the event, `message` when not set. Line breaks are removed.
+    #[must_use]
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.kind = Some(single_line(event.into()));
+        self
+    }
+
+    /// The id the client sends back as `Last-Event-ID` when it reconnects. Line breaks
+    /// and NUL characters, which would make clients ignore it, are removed.
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        let mut id = single_line(id.into());
+        id.retain(|c| c != '\0');
+        self.id = Some(id);
+        self
+    }
+
+    /// How long the client waits before reconnecting once the connection is lost.
+    #[must_use]
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(kind) = &self.kind {
+            writeln!(f, "event: {kind}")?;
+        }
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {id}")?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        if let Some(data) = &self.data {
+            for line in data.replace("\r\n", "\n").split(['\r', '\n']) {
+                writeln!(f, "data: {line}")?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+impl From<String> for Event {
+    fn from(data: String) -> Self {
+        Event::data(data)
+    }
+}
+
+impl From<&str> for Event {
+    fn from(data: &str) -> Self {
+        Event::data(data)
+    }
+}
+
+fn single_line(mut value: String) -> String {
+    value.retain(|c| c != '\r' && c != '\n');
+    value
+}
+
+/// The client is gone, or the response was never sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Disconnected;
+
+impl fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The event stream is disconnected")
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+/// Sends events to the response of a [`channel`]. Clones send to the same response.
+#[derive(Clone)]
+pub struct Sender {
+    sender: mpsc::Sender<Vec<u8>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl Sender {
+    /// Queue `event` to be written to the client.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Disconnected`] once the client is gone.
+    pub fn send(&self, event: impl Into<Event>) -> Result<(), Disconnected> {
+        self.send_bytes(event.into().to_bytes())
+    }
+
+    /// Queue a comment, which clients ignore. Line breaks are removed.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Disconnected`] once the client is gone.
+    pub fn comment(&self, comment: &str) -> Result<(), Disconnected> {
+        self.send_bytes(
+            format!(": {}\n\n", single_line(comment.to_string())).into_bytes(),
+        )
+    }
+
+    /// Whether the client is still there, as far as the server knows: it's only noticed
+    /// gone when writing to it fails.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    fn send_bytes(&self, bytes: Vec<u8>) -> Result<(), Disconnected> {
+        if !self.is_connected() {
+            return Err(Disconnected);
+        }
+        self.sender.send(bytes).map_err(|_| Disconnected)
+    }
+}
+
+/// Settings of event stream responses, see [`channel`] for the defaults.
+#[derive(Clone, Debug)]
+pub struct EventStream {
+    keep_alive: Option<Duration>,
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        EventStream {
+            keep_alive: Some(Duration::from_secs(15)),
+        }
+    }
+}
+
+impl EventStream {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a comment after `interval` without events, keeping proxies from closing an
+    /// idle connection and finding out when the client is gone. `None` disables them.
+    #[must_use]
+    pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+
+    /// A `200 OK` event stream response, and the sender of its events.
+    #[must_use]
+    pub fn channel(&self) -> (Sender, Response) {
+        let (sender, receiver) = mpsc::channel();
+        let connected = Arc::new(AtomicBool::new(true));
+        let events = Events {
+            receiver,
+            keep_alive: self.keep_alive,
+            pending: Cursor::default(),
+            connected: connected.clone(),
+        };
+        let response = Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/event-stream\r\nCache-Control: no-cache"),
+            Body::stream(events),
+        );
+        (Sender { sender, connected }, response)
+    }
+}
+
+/// A `200 OK` event stream response, and the sender of its events, with a keep-alive
+/// comment every 15 seconds without events.
+#[must_use]
+pub fn channel() -> (Sender, Response) {
+    EventStream::default().channel()
+}
+
+/// The body of an event stream, read by the server as events are sent.
+struct Events {
+    receiver: Receiver<Vec<u8>>,
+    keep_alive: Option<Duration>,
+    pending: Cursor<Vec<u8>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl Read for Events {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.position() == self.pending.get_ref().len() as u64 {
+            let bytes = match self.keep_alive {
+                Some(interval) => match self.receiver.recv_timeout(interval) {
+                    Ok(bytes) => bytes,
+                    Err(RecvTimeoutError::Timeout) => b":\n\n".to_vec(),
+                    Err(RecvTimeoutError::Disconnected) => return Ok(0),
+                },
+                None => match self.receiver.recv() {
+                    Ok(bytes) => bytes,
+                    Err(_) => return Ok(0),
+                },
+            };
+            self.pending = Cursor::new(bytes);
+        }
+        self.pending.read(buf)
+    }
+}
+
+impl Drop for Events {
+    /// The server drops the body once it's done writing it, whether the stream ended or
+    /// the client is gone.
+    fn drop(&mut self) {
+        self.connected.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{channel, Disconnected, Event, EventStream};
+    use crate::request::HttpVersion;
+    use crate::response::Body;
+    use std::io::Read;
+    use std::time::Duration;
+
+    #[test]
+    fn it_frames_events() {
+        let event = Event::data("one\ntwo\r\nthree\rfour")
+            .event("update")
+            .id("4\n2")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            "event: update\nid: 42\nretry: 3000\ndata: one\ndata: two\ndata: three\ndata: four\n\n",
+            String::from_utf8(event.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            "data: \n\n",
+            String::from_utf8(Event::data("").to_bytes()).unwrap()
+        );
+        assert_eq!(
+            "retry: 10\n\n",
+            String::from_utf8(Event::new().retry(Duration::from_millis(10)).to_bytes())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn it_streams_events_until_the_senders_are_dropped() {
+        let (sender, response) = channel();
+        assert_eq!(
+            Some("text/event-stream"),
+            response.headers.get("Content-Type")
+        );
+        sender.send("hello").unwrap();
+        sender.clone().comment("note").unwrap();
+        drop(sender);
+
+        let mut output = Vec::new();
+        response.write_to(&mut output, &HttpVersion::V1_1).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output
+            .ends_with("\r\n\r\nD\r\ndata: hello\n\n\r\n8\r\n: note\n\n\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn it_keeps_the_stream_alive_and_notices_disconnects() {
+        let (sender, response) = EventStream::new()
+            .keep_alive(Some(Duration::from_millis(10)))
+            .channel();
+        let Body::Stream(mut events) = response.body else {
+            panic!("expected a streaming body");
+        };
+        let mut buf = [0; 16];
+        let read = events.read(&mut buf).unwrap();
+        assert_eq!(b":\n\n", &buf[..read]);
+        assert!(sender.is_connected());
+
+        drop(events);
+        assert!(!sender.is_connected());
+        assert_eq!(Err(Disconnected), sender.send("gone"));
+    }
+}
@@ -0,0 +1,376 @@
+use crate::date;
+use crate::middleware::conditional::ETag;
+use crate::request::uri::{percent_decode, percent_encode};
+use crate::request::{Method, Request};
+use crate::response::{Body, Headers, Response, Status};
+use crate::server::Handler;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Serve the files in `root` under the URL `prefix`, see [`StaticFiles`].
+#[must_use]
+pub fn static_files<P: Into<PathBuf>>(prefix: &str, root: P) -> StaticFiles {
+    StaticFiles::new(prefix, root)
+}
+
+/// A handler that maps a URL prefix to a directory.
+///
+/// `GET /static/css/site.css` with the prefix `/static` and the root `public` serves
+/// `public/css/site.css`. Directories are served by their `index.html`, or by a listing
+/// of their entries if [`listings`](StaticFiles::listings) is enabled.
+///
+/// Paths are percent-decoded before they're resolved, and a path that resolves outside of
+/// the root, through `..` or a symbolic link, is answered with a `404`.
+///
+/// Files are sent with an `ETag` derived from their size and modification time and a
+/// `Last-Modified` header, wrap the server with
+/// [`ConditionalRequests`](crate::middleware::conditional::ConditionalRequests) to answer
+/// conditional requests.
+///
+/// ```no_run
+/// use http::server::Server;
+/// use http::static_files::static_files;
+///
+/// Server::new("0.0.0.0:4000", static_files("/", "public").listings(true))
+///     .listen()
+///     .unwrap();
+/// ```
+pub struct StaticFiles {
+    prefix: String,
+    root: PathBuf,
+    listings: bool,
+}
+
+impl StaticFiles {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(prefix: &str, root: P) -> Self {
+        StaticFiles {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            root: root.into(),
+            listings: false,
+        }
+    }
+
+    /// List the entries of directories that have no `index.html`, disabled by default.
+    #[must_use]
+    pub fn listings(mut self, listings: bool) -> Self {
+        self.listings = listings;
+        self
+    }
+
+    /// Returns `true` if `path` is under the prefix, i.e. if this handler should serve
+    /// it.
+    #[must_use]
+    pub fn matches(&self, path: &str) -> bool {
+        path.strip_prefix(&self.prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// Resolve a request path to a file system path inside the root.
+    ///
+    /// Returns `Ok(None)` if the path isn't under the prefix or escapes the root.
+    fn resolve(&self, path: &str) -> Result<Option<PathBuf>, Status> {
+        if !self.matches(path) {
+            return Ok(None);
+        }
+        let decoded = percent_decode(&path[self.prefix.len()..])
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(Status::BadRequest)?;
+
+        let mut resolved = self.root.clone();
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return Ok(None),
+                // Separators and NUL would let a segment mean more than one path
+                // component.
+                _ if segment.contains(['\\', '\0']) => return Ok(None),
+                _ => resolved.push(segment),
+            }
+        }
+
+        // Symbolic links may point anywhere, compare the real paths.
+        let (Ok(root), Ok(resolved)) =
+            (fs::canonicalize(&self.root), fs::canonicalize(resolved))
+        else {
+            return Ok(None);
+        };
+        Ok(resolved.starts_with(root).then_some(resolved))
+    }
+
+    fn listing(&self, directory: &Path, path: &str) -> io::Result<Response> {
+        let mut entries = fs::read_dir(directory)?
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let is_dir = fs::metadata(entry.path()).is_ok_and(|m| m.is_dir());
+                (entry.file_name(), is_dir)
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+
+        let title = escape_html(&String::from_utf8_lossy(
+            &percent_decode(path).unwrap_or_default(),
+        ));
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n<body>\n<h1>Index of {title}</h1>\n<ul>\n"
+        );
+        if path != format!("{}/", self.prefix) {
+            html.push_str("<li><a href=\"../\">../</a></li>\n");
+        }
+        for (name, is_dir) in entries {
+            let slash = if is_dir { "/" } else { "" };
+            let _ = writeln!(
+                html,
+                "<li><a href=\"{href}{slash}\">{name}{slash}</a></li>",
+                href = percent_encode(name.as_encoded_bytes()),
+                name = escape_html(&name.to_string_lossy()),
+            );
+        }
+        html.push_str("</ul>\n</body>\n</html>\n");
+
+        Ok(Response::new(
+            Status::Ok,
+            Headers::new("Content-Type: text/html; charset=utf-8"),
+            html,
+        ))
+    }
+}
+
+impl Handler for StaticFiles {
+    fn handle(&self, request: &mut Request) -> Response {
+        if !matches!(request.method, Method::Get | Method::Head) {
+            let mut response = Response::from_status(Status::MethodNotAllowed);
+            response.headers.insert("Allow", "GET, HEAD");
+            return response;
+        }
+
+        let path = request.path();
+        let resolved = match self.resolve(path) {
+            Ok(Some(resolved)) => resolved,
+            Ok(None) => return Response::from_status(Status::NotFound),
+            Err(status) => return Response::from_status(status),
+        };
+
+        let result = if resolved.is_dir() {
+            // Relative links in the index resolve against the directory only with a
+            // trailing slash. Extra leading slashes, and backslashes browsers read as
+            // slashes, are dropped: `//host/` would be a redirect to another host.
+            if !path.ends_with('/') {
+                let path = path.trim_start_matches(['/', '\\']);
+                let location = match request.query() {
+                    Some(query) => format!("/{path}/?{query}"),
+                    None => format!("/{path}/"),
+                };
+                let mut response =
+                    Response::new(Status::MovedPermanently, Headers::default(), "");
+                response.headers.insert("Location", location);
+                return response;
+            }
+
+            match self.resolve(&format!("{path}index.html")) {
+                Ok(Some(index)) if index.is_file() => serve_file(&index),
+                _ if self.listings => self.listing(&resolved, path),
+                _ => return Response::from_status(Status::NotFound),
+            }
+        } else {
+            serve_file(&resolved)
+        };
+
+        result.unwrap_or_else(|e| match e.kind() {
+            ErrorKind::NotFound => Response::from_status(Status::NotFound),
+            ErrorKind::PermissionDenied => Response::from_status(Status::Forbidden),
+            _ => {
+                eprintln!("Error serving {}: {e:?}", resolved.display());
+                Response::from_status(Status::InternalServerError)
+            }
+        })
+    }
+}
+
+pub(crate) fn serve_file(path: &Path) -> io::Result<Response> {
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let mut headers = Headers::default();
+    headers.insert("Content-Type", mime_type(path));
+    if let Ok(modified) = metadata.modified() {
+        headers.insert(
+            "ETag",
+            ETag::from_metadata(metadata.len(), modified).to_string(),
+        );
+        headers.insert("Last-Modified", date::format(modified));
+    }
+    Ok(Response::new(Status::Ok, headers, Body::seekable(file)?))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Guess the media type of a file from its extension, `application/octet-stream` if it's
+/// unknown.
+#[must_use]
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "xml" => "application/xml",
+        "json" | "map" => "application/json",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{static_files, StaticFiles};
+    use crate::request::Request;
+    use crate::response::{Response, Status};
+    use crate::server::Handler;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    /// A directory tree to serve, removed when dropped.
+    struct Tree(PathBuf);
+
+    impl Tree {
+        fn new(name: &str) -> Self {
+            let base = std::env::temp_dir()
+                .join(format!("http-static-{name}-{}", std::process::id()));
+            let root = base.join("root");
+            fs::create_dir_all(root.join("docs")).unwrap();
+            fs::write(root.join("index.html"), "<h1>home</h1>").unwrap();
+            fs::write(root.join("a b.css"), "body {}").unwrap();
+            fs::write(root.join("docs/guide.txt"), "guide").unwrap();
+            fs::write(base.join("secret.txt"), "secret").unwrap();
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(base.join("secret.txt"), root.join("link.txt"))
+                .unwrap();
+            Tree(base)
+        }
+
+        fn root(&self) -> PathBuf {
+            self.0.join("root")
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn get(handler: &StaticFiles, target: &str) -> Response {
+        let raw = format!("GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        handler.handle(&mut Request::from(Cursor::new(raw)).unwrap())
+    }
+
+    fn body(response: Response) -> String {
+        String::from_utf8(response.body.into_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_serves_files_under_the_prefix() {
+        let tree = Tree::new("serve");
+        let handler = static_files("/static/", tree.root());
+
+        let response = get(&handler, "/static/a%20b.css?v=1");
+        assert_eq!(Status::Ok, response.status);
+        assert_eq!(
+            Some("text/css; charset=utf-8"),
+            response.headers.get("Content-Type")
+        );
+        assert_eq!(Some(7), response.body.content_length());
+        assert!(response.headers.get("ETag").unwrap().starts_with("\"7-"));
+        assert!(response.headers.contains("Last-Modified"));
+        assert_eq!("body {}", body(response));
+
+        assert_eq!("guide", body(get(&handler, "/static/docs/./guide.txt")));
+        assert_eq!(Status::NotFound, get(&handler, "/staticx/a%20b.css").status);
+        assert_eq!(Status::NotFound, get(&handler, "/static/missing").status);
+        assert_eq!(Status::BadRequest, get(&handler, "/static/%zz").status);
+    }
+
+    #[test]
+    fn it_serves_directory_indexes() {
+        let tree = Tree::new("index");
+        let handler = static_files("/", tree.root());
+
+        assert_eq!("<h1>home</h1>", body(get(&handler, "/")));
+
+        let response = get(&handler, "/docs?a=1");
+        assert_eq!(Status::MovedPermanently, response.status);
+        assert_eq!(Some("/docs/?a=1"), response.headers.get("Location"));
+        for target in ["//docs", "///docs"] {
+            let response = get(&handler, target);
+            assert_eq!(Some("/docs/"), response.headers.get("Location"), "{target}");
+        }
+
+        assert_eq!(Status::NotFound, get(&handler, "/docs/").status);
+
+        let listing = body(get(&handler.listings(true), "/docs/"));
+        assert!(listing.contains("<a href=\"../\">../</a>"));
+        assert!(listing.contains("<a href=\"guide.txt\">guide.txt</a>"));
+    }
+
+    #[test]
+    fn it_blocks_escapes_from_the_root() {
+        let tree = Tree::new("escape");
+        let handler = static_files("/", tree.root());
+
+        for target in [
+            "/../secret.txt",
+            "/%2e%2e/secret.txt",
+            "/docs/%2E%2E/%2E%2E/secret.txt",
+            "/..%2fsecret.txt",
+            "/..%5csecret.txt",
+            "/link.txt",
+        ] {
+            assert_eq!(Status::NotFound, get(&handler, target).status, "{target}");
+        }
+    }
+}
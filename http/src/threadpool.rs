@@ -42,7 +42,7 @@ impl ThreadPool {
                 } else {
                     println!("shutting down..");
                     break;
-                };
+                }
             });
             workers.push(Some(handle));
         }
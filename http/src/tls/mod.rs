@@ -0,0 +1,578 @@
+//! HTTPS, terminated with [rustls](https://docs.rs/rustls).
+//!
+//! ```no_run
+//! use http::response::{Headers, Response, Status};
+//! use http::server::Server;
+//! use http::tls::TlsConfig;
+//!
+//! let tls = TlsConfig::new("cert.pem", "key.pem")
+//!     .sni("api.example.com", "api.pem", "api.key");
+//! Server::new("0.0.0.0:443", |request: &mut http::request::Request| {
+//!     let text = format!("secure: {}", request.is_secure());
+//!     Response::new(Status::Ok, Headers::default(), text)
+//! })
+//! .tls(tls)
+//! .listen()
+//! .unwrap();
+//! ```
+//!
+//! Certificates are read when the server starts listening, and read again when their
+//! files change, so they can be renewed without a restart. The `h2` and `http/1.1`
+//! protocols are offered with ALPN, see [`Server::http2`](crate::server::Server::http2).
+//! RFC: <https://datatracker.ietf.org/doc/html/rfc9110#section-4.2.2>
+
+use crate::request::TlsInfo;
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use rustls::{RootCertStore, ServerConfig, ServerConnection};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often certificate files are checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Certificates and client authentication of an HTTPS [`Server`](crate::server::Server).
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    default: (PathBuf, PathBuf),
+    names: Vec<(String, PathBuf, PathBuf)>,
+    client_ca: Option<(PathBuf, bool)>,
+}
+
+impl TlsConfig {
+    /// Serve the PEM certificate chain in `cert` with the PEM private key in `key`, to
+    /// clients that don't ask for a name with another certificate.
+    #[must_use]
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        TlsConfig {
+            default: (cert.into(), key.into()),
+            names: Vec::new(),
+            client_ca: None,
+        }
+    }
+
+    /// Serve `cert` and `key` to clients asking for `name` with SNI. `name` may start
+    /// with `*.` to match any single label, exact names are preferred.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc6066#section-3>
+    #[must_use]
+    pub fn sni(
+        mut self,
+        name: &str,
+        cert: impl Into<PathBuf>,
+        key: impl Into<PathBuf>,
+    ) -> Self {
+        self.names
+            .push((name.to_ascii_lowercase(), cert.into(), key.into()));
+        self
+    }
+
+    /// Ask clients for a certificate issued by one of the PEM certificates in `ca`
+    /// (mutual TLS). When `required`, clients without one can't connect; otherwise
+    /// they can, and
+    /// [`Request::client_certificate`](crate::request::Request::client_certificate) is
+    /// `None`.
+    #[must_use]
+    pub fn client_ca(mut self, ca: impl Into<PathBuf>, required: bool) -> Self {
+        self.client_ca = Some((ca.into(), required));
+        self
+    }
+
+    /// Load the certificates, offering `h2` with ALPN when `http2` is set.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if a file can't be read or parsed, or a key doesn't match its
+    /// certificate.
+    pub(crate) fn server_config(&self, http2: bool) -> io::Result<Arc<ServerConfig>> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?;
+
+        let builder = match &self.client_ca {
+            Some((ca, required)) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(ca)? {
+                    roots.add(cert).map_err(|e| invalid(ca, e))?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    roots.into(),
+                    provider.clone(),
+                );
+                let verifier = if *required {
+                    verifier
+                } else {
+                    verifier.allow_unauthenticated()
+                };
+                builder.with_client_cert_verifier(
+                    verifier.build().map_err(|e| invalid(ca, e))?,
+                )
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let (cert, key) = &self.default;
+        let resolver = Certificates {
+            default: Reloading::load(cert, key, &provider)?,
+            names: self
+                .names
+                .iter()
+                .map(|(name, cert, key)| {
+                    Ok((name.clone(), Reloading::load(cert, key, &provider)?))
+                })
+                .collect::<io::Result<_>>()?,
+        };
+
+        let mut config = builder.with_cert_resolver(Arc::new(resolver));
+        config.alpn_protocols = if http2 {
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        } else {
+            vec![b"http/1.1".to_vec()]
+        };
+        Ok(Arc::new(config))
+    }
+}
+
+fn invalid(path: &Path, e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {e}", path.display()),
+    )
+}
+
+fn read_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|e| invalid(path, e))?;
+    if certs.is_empty() {
+        return Err(invalid(path, "no certificate"));
+    }
+    Ok(certs)
+}
+
+/// Picks the certificate for the name a client asked for.
+#[derive(Debug)]
+struct Certificates {
+    default: Reloading,
+    names: Vec<(String, Reloading)>,
+}
+
+impl ResolvesServerCert for Certificates {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let certificate = client_hello
+            .server_name()
+            .map(str::to_ascii_lowercase)
+            .and_then(|name| {
+                let wildcard = name
+                    .split_once('.')
+                    .map(|(_, parent)| format!("*.{parent}"));
+                self.find(&name)
+                    .or_else(|| wildcard.and_then(|wildcard| self.find(&wildcard)))
+            })
+            .unwrap_or(&self.default);
+        Some(certificate.current())
+    }
+}
+
+impl Certificates {
+    fn find(&self, name: &str) -> Option<&Reloading> {
+        self.names
+            .iter()
+            .find(|(listed, _)| listed == name)
+            .map(|(_, certificate)| certificate)
+    }
+}
+
+/// A certificate and key read from PEM files, read again after either file changes.
+#[derive(Debug)]
+struct Reloading {
+    cert: PathBuf,
+    key: PathBuf,
+    provider: Arc<CryptoProvider>,
+    loaded: RwLock<Loaded>,
+}
+
+#[derive(Debug)]
+struct Loaded {
+    key: Arc<CertifiedKey>,
+    modified: [Option<SystemTime>; 2],
+    checked: Instant,
+}
+
+impl Reloading {
+    fn load(cert: &Path, key: &Path, provider: &Arc<CryptoProvider>) -> io::Result<Self> {
+        let reloading = Reloading {
+            cert: cert.to_path_buf(),
+            key: key.to_path_buf(),
+            provider: provider.clone(),
+            loaded: RwLock::new(Loaded {
+                key: Arc::new(certified_key(cert, key, provider)?),
+                modified: [modified(cert), modified(key)],
+                checked: Instant::now(),
+            }),
+        };
+        Ok(reloading)
+    }
+
+    /// The certificate, read again first if the files changed. A certificate that can't
+    /// be read is logged and the previous one kept, e.g. while only one of the files
+    /// is replaced.
+    fn current(&self) -> Arc<CertifiedKey> {
+        {
+            let loaded = self.loaded.read().unwrap_or_else(PoisonError::into_inner);
+            if loaded.checked.elapsed() < RELOAD_INTERVAL {
+                return loaded.key.clone();
+            }
+        }
+
+        let mut loaded = self.loaded.write().unwrap_or_else(PoisonError::into_inner);
+        if loaded.checked.elapsed() >= RELOAD_INTERVAL {
+            loaded.checked = Instant::now();
+            let modified = [modified(&self.cert), modified(&self.key)];
+            if modified != loaded.modified {
+                loaded.modified = modified;
+                match certified_key(&self.cert, &self.key, &self.provider) {
+                    Ok(key) => loaded.key = Arc::new(key),
+                    Err(e) => eprintln!("Error reloading certificate: {e}"),
+                }
+            }
+        }
+        loaded.key.clone()
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn certified_key(
+    cert: &Path,
+    key: &Path,
+    provider: &CryptoProvider,
+) -> io::Result<CertifiedKey> {
+    let certs = read_certs(cert)?;
+    let der = PrivateKeyDer::from_pem_file(key).map_err(|e| invalid(key, e))?;
+    CertifiedKey::from_der(certs, der, provider).map_err(|e| invalid(key, e))
+}
+
+/// A TLS connection that can be read and written at the same time, like a `TcpStream`.
+///
+/// Reads wait for the socket without holding the connection, so responses can be written
+/// while waiting for the next request, as HTTP/2 does.
+pub(crate) struct TlsStream {
+    connection: Mutex<ServerConnection>,
+    socket: TcpStream,
+}
+
+/// Complete the handshake of a connection.
+///
+/// # Errors
+///
+/// Will return an error if the client breaks off the handshake or fails to authenticate.
+pub(crate) fn accept(
+    config: Arc<ServerConfig>,
+    mut socket: TcpStream,
+) -> io::Result<TlsStream> {
+    let mut connection = ServerConnection::new(config).map_err(io::Error::other)?;
+    while connection.is_handshaking() {
+        connection.complete_io(&mut socket)?;
+    }
+    Ok(TlsStream {
+        connection: Mutex::new(connection),
+        socket,
+    })
+}
+
+impl TlsStream {
+    fn lock(&self) -> MutexGuard<'_, ServerConnection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn info(&self) -> TlsInfo {
+        let connection = self.lock();
+        TlsInfo {
+            server_name: connection.server_name().map(str::to_string),
+            alpn_protocol: connection.alpn_protocol().map(<[u8]>::to_vec),
+            client_certificates: connection
+                .peer_certificates()
+                .unwrap_or_default()
+                .iter()
+                .map(|cert| cert.to_vec())
+                .collect(),
+        }
+    }
+
+    /// Shut the connection down like [`TcpStream::shutdown`], sending a `close_notify`
+    /// when writes are shut.
+    pub(crate) fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        if how != Shutdown::Read {
+            let mut connection = self.lock();
+            connection.send_close_notify();
+            self.flush_tls(&mut connection)?;
+        }
+        self.socket.shutdown(how)
+    }
+
+    /// Send whatever TLS records are pending.
+    fn flush_tls(&self, connection: &mut ServerConnection) -> io::Result<()> {
+        while connection.wants_write() {
+            connection.write_tls(&mut &self.socket)?;
+        }
+        Ok(())
+    }
+}
+
+impl Read for &TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut records = [0; 16 * 1024];
+        loop {
+            match self.lock().reader().read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+
+            let read = (&self.socket).read(&mut records)?;
+            let mut connection = self.lock();
+            let mut records = &records[..read];
+            // Reading nothing tells the connection the socket was closed.
+            loop {
+                connection.read_tls(&mut records)?;
+                let processed = connection.process_new_packets();
+                self.flush_tls(&mut connection)?;
+                processed.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if records.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Write for &TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut connection = self.lock();
+        let written = connection.writer().write(buf)?;
+        self.flush_tls(&mut connection)?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut connection = self.lock();
+        connection.writer().flush()?;
+        self.flush_tls(&mut connection)
+    }
+}
+
+impl Drop for TlsStream {
+    fn drop(&mut self) {
+        let mut connection = self.lock();
+        connection.send_close_notify();
+        let _ = self.flush_tls(&mut connection);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{accept, TlsConfig};
+    use crate::request::TlsInfo;
+    use rustls::crypto::ring;
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+    use rustls::{
+        ClientConfig, ClientConnection, RootCertStore, ServerConfig, StreamOwned,
+    };
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    const CA: &str = include_str!("testdata/ca.pem");
+    const LOCALHOST: (&str, &str) = (
+        include_str!("testdata/localhost.pem"),
+        include_str!("testdata/localhost.key"),
+    );
+    /// Valid for `localhost`, `other.test` and `*.wild.test`.
+    const OTHER: (&str, &str) = (
+        include_str!("testdata/other.pem"),
+        include_str!("testdata/other.key"),
+    );
+    const CLIENT: (&str, &str) = (
+        include_str!("testdata/client.pem"),
+        include_str!("testdata/client.key"),
+    );
+
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("http-tls-{name}-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("ca.pem"), CA).unwrap();
+            Dir(dir)
+        }
+
+        fn write(&self, name: &str, (cert, key): (&str, &str)) -> (PathBuf, PathBuf) {
+            let paths = (
+                self.0.join(format!("{name}.pem")),
+                self.0.join(format!("{name}.key")),
+            );
+            fs::write(&paths.0, cert).unwrap();
+            fs::write(&paths.1, key).unwrap();
+            paths
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn der(pem: &str) -> CertificateDer<'static> {
+        CertificateDer::from_pem_slice(pem.as_bytes()).unwrap()
+    }
+
+    /// Connect to a server with `config` as `name`, returning the certificate the server
+    /// sent and what the server saw.
+    fn connect(
+        config: &Arc<ServerConfig>,
+        name: &str,
+        client: Option<(&str, &str)>,
+    ) -> Option<(CertificateDer<'static>, TlsInfo)> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let config = config.clone();
+        let server = thread::spawn(move || {
+            let stream = accept(config, listener.accept().unwrap().0).ok()?;
+            let mut request = [0; 4];
+            (&stream).read_exact(&mut request).ok()?;
+            (&stream).write_all(b"pong").unwrap();
+            Some(stream.info())
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(der(CA)).unwrap();
+        let builder =
+            ClientConfig::builder_with_provider(ring::default_provider().into())
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots);
+        let mut config = match client {
+            Some((cert, key)) => builder
+                .with_client_auth_cert(
+                    vec![der(cert)],
+                    PrivateKeyDer::from_pem_slice(key.as_bytes()).unwrap(),
+                )
+                .unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let connection = ClientConnection::new(
+            config.into(),
+            ServerName::try_from(name.to_string()).unwrap(),
+        )
+        .unwrap();
+        let mut stream =
+            StreamOwned::new(connection, TcpStream::connect(address).unwrap());
+
+        let mut response = Vec::new();
+        let exchanged = stream
+            .write_all(b"ping")
+            .and_then(|()| stream.read_to_end(&mut response));
+        let info = server.join().unwrap();
+        exchanged.ok()?;
+        assert_eq!(b"pong", &response[..]);
+        let certificate = stream.conn.peer_certificates().unwrap()[0].clone();
+        Some((certificate, info?))
+    }
+
+    #[test]
+    fn it_selects_certificates_by_server_name() {
+        let dir = Dir::new("sni");
+        let (cert, key) = dir.write("localhost", LOCALHOST);
+        let (other_cert, other_key) = dir.write("other", OTHER);
+        let config = TlsConfig::new(cert, key)
+            .sni("Other.test", &other_cert, &other_key)
+            .sni("*.wild.test", &other_cert, &other_key)
+            .server_config(true)
+            .unwrap();
+
+        let (certificate, info) = connect(&config, "localhost", None).unwrap();
+        assert_eq!(der(LOCALHOST.0), certificate);
+        assert_eq!(Some("localhost"), info.server_name.as_deref());
+        assert_eq!(Some(&b"h2"[..]), info.alpn_protocol.as_deref());
+        assert!(info.client_certificates.is_empty());
+
+        assert_eq!(
+            der(OTHER.0),
+            connect(&config, "other.test", None).unwrap().0
+        );
+        assert_eq!(
+            der(OTHER.0),
+            connect(&config, "a.wild.test", None).unwrap().0
+        );
+
+        let config =
+            TlsConfig::new(dir.0.join("localhost.pem"), &other_key).server_config(true);
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn it_reloads_changed_certificates() {
+        let dir = Dir::new("reload");
+        let (cert, key) = dir.write("server", LOCALHOST);
+        let config = TlsConfig::new(cert, key).server_config(false).unwrap();
+        let (certificate, info) = connect(&config, "localhost", None).unwrap();
+        assert_eq!(der(LOCALHOST.0), certificate);
+        assert_eq!(Some(&b"http/1.1"[..]), info.alpn_protocol.as_deref());
+
+        // Until the key is replaced too, the old certificate is kept.
+        thread::sleep(Duration::from_millis(1100));
+        fs::write(dir.0.join("server.pem"), OTHER.0).unwrap();
+        assert_eq!(
+            der(LOCALHOST.0),
+            connect(&config, "localhost", None).unwrap().0
+        );
+
+        thread::sleep(Duration::from_millis(1100));
+        fs::write(dir.0.join("server.key"), OTHER.1).unwrap();
+        assert_eq!(der(OTHER.0), connect(&config, "localhost", None).unwrap().0);
+    }
+
+    #[test]
+    fn it_authenticates_clients() {
+        let dir = Dir::new("mtls");
+        let (cert, key) = dir.write("localhost", LOCALHOST);
+        let required = TlsConfig::new(&cert, &key)
+            .client_ca(dir.0.join("ca.pem"), true)
+            .server_config(true)
+            .unwrap();
+        assert!(connect(&required, "localhost", None).is_none());
+        let (_, info) = connect(&required, "localhost", Some(CLIENT)).unwrap();
+        assert_eq!(vec![der(CLIENT.0).to_vec()], info.client_certificates);
+
+        let optional = TlsConfig::new(&cert, &key)
+            .client_ca(dir.0.join("ca.pem"), false)
+            .server_config(true)
+            .unwrap();
+        let (_, info) = connect(&optional, "localhost", None).unwrap();
+        assert!(info.client_certificates.is_empty());
+        // A server certificate can't authenticate a client.
+        assert!(connect(&optional, "localhost", Some(LOCALHOST)).is_none());
+    }
+}
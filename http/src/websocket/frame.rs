@@ -0,0 +1,201 @@
+use super::{CloseCode, Error};
+use std::io::{self, Read, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(opcode: u8) -> Option<Self> {
+        Some(match opcode {
+            0x0 => Opcode::Continuation,
+            0x1 => Opcode::Text,
+            0x2 => Opcode::Binary,
+            0x8 => Opcode::Close,
+            0x9 => Opcode::Ping,
+            0xA => Opcode::Pong,
+            _ => return None,
+        })
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+        }
+    }
+
+    pub(crate) fn is_control(self) -> bool {
+        matches!(self, Opcode::Close | Opcode::Ping | Opcode::Pong)
+    }
+}
+
+/// The largest payload of a control frame.
+pub(crate) const MAX_CONTROL_PAYLOAD: usize = 125;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) fin: bool,
+    pub(crate) opcode: Opcode,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Read a frame sent by a client, unmasking its payload. Payloads longer than `max`
+    /// are refused before they're read.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-5.2>
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if reading fails, or the frame is unmasked, uses an
+    /// extension, has an unknown opcode or is too large.
+    pub(crate) fn read<R: Read>(reader: &mut R, max: usize) -> Result<Self, Error> {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        if head[0] & 0x70 != 0 {
+            return Err(Error::protocol("Reserved bits are set"));
+        }
+        let opcode =
+            Opcode::from_u8(head[0] & 0x0F).ok_or(Error::protocol("Unknown opcode"))?;
+        // RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-5.3>
+        if head[1] & 0x80 == 0 {
+            return Err(Error::protocol("Client frames must be masked"));
+        }
+
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut length = [0; 2];
+                reader.read_exact(&mut length)?;
+                u64::from(u16::from_be_bytes(length))
+            }
+            127 => {
+                let mut length = [0; 8];
+                reader.read_exact(&mut length)?;
+                let length = u64::from_be_bytes(length);
+                if length >> 63 != 0 {
+                    return Err(Error::protocol("Invalid payload length"));
+                }
+                length
+            }
+            length => u64::from(length),
+        };
+        if opcode.is_control() && (!fin || length > MAX_CONTROL_PAYLOAD as u64) {
+            return Err(Error::protocol(
+                "Control frames can't be fragmented or longer than 125",
+            ));
+        }
+        let length = usize::try_from(length)
+            .ok()
+            .filter(|length| *length <= max)
+            .ok_or(Error::Protocol {
+                code: CloseCode::TOO_BIG,
+                reason: "Message too big",
+            })?;
+
+        let mut mask = [0; 4];
+        reader.read_exact(&mut mask)?;
+        let mut payload = vec![0; length];
+        reader.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        Ok(Frame {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    /// Write the frame unmasked, as servers do.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing to `writer` fails.
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.payload.len() + 10);
+        bytes.push(u8::from(self.fin) << 7 | self.opcode.as_u8());
+        match self.payload.len() {
+            #[expect(clippy::cast_possible_truncation)]
+            length @ 0..=125 => bytes.push(length as u8),
+            #[expect(clippy::cast_possible_truncation)]
+            length @ 126..=0xFFFF => {
+                bytes.push(126);
+                bytes.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                bytes.push(127);
+                bytes.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        bytes.extend_from_slice(&self.payload);
+        writer.write_all(&bytes)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{Frame, Opcode};
+    use crate::websocket::{CloseCode, Error};
+    use std::io::Cursor;
+
+    #[test]
+    fn it_reads_masked_frames() {
+        // The masked "Hello" of RFC 6455 section 5.7.
+        let bytes = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = Frame::read(&mut Cursor::new(bytes), 1024).unwrap();
+        assert_eq!(
+            Frame {
+                fin: true,
+                opcode: Opcode::Text,
+                payload: b"Hello".to_vec()
+            },
+            frame
+        );
+
+        let mut written = Vec::new();
+        frame.write(&mut written).unwrap();
+        assert_eq!(b"\x81\x05Hello".to_vec(), written);
+
+        let long = Frame {
+            fin: false,
+            opcode: Opcode::Binary,
+            payload: vec![0; 256],
+        };
+        let mut written = Vec::new();
+        long.write(&mut written).unwrap();
+        assert_eq!([0x02, 126, 0x01, 0x00], written[..4]);
+    }
+
+    #[test]
+    fn it_rejects_invalid_frames() {
+        let code = |bytes: &[u8]| match Frame::read(&mut Cursor::new(bytes), 4) {
+            Err(Error::Protocol { code, .. }) => code,
+            _ => panic!("expected a protocol error"),
+        };
+        // Unmasked.
+        assert_eq!(CloseCode::PROTOCOL_ERROR, code(b"\x81\x05Hello"));
+        // Reserved bit.
+        assert_eq!(CloseCode::PROTOCOL_ERROR, code(b"\xc1\x80abcd"));
+        // Unknown opcode.
+        assert_eq!(CloseCode::PROTOCOL_ERROR, code(b"\x83\x80abcd"));
+        // Fragmented ping.
+        assert_eq!(CloseCode::PROTOCOL_ERROR, code(b"\x09\x80abcd"));
+        assert_eq!(CloseCode::TOO_BIG, code(b"\x82\x85abcd12345"));
+    }
+}
@@ -0,0 +1,322 @@
+//! WebSocket connections over HTTP/1.1.
+//!
+//! A handler answers the handshake with [`upgrade`], passing what to do with the
+//! [`WebSocket`] once the `101 Switching Protocols` response is sent. The socket runs in
+//! a thread of its own, in both single-threaded and threaded servers.
+//!
+//! Extensions like `permessage-deflate` aren't supported, and are never agreed on.
+//! RFC: <https://datatracker.ietf.org/doc/html/rfc6455>
+
+mod frame;
+mod socket;
+
+pub use socket::{CloseCode, CloseFrame, Error, Message, WebSocket};
+
+use crate::request::{HttpVersion, Method, Request};
+use crate::response::{Headers, Response, Status};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+/// Why a request isn't a WebSocket handshake the server can accept.
+#[derive(Debug, PartialEq, Eq)]
+pub enum HandshakeError {
+    BadRequest(&'static str),
+    /// The client asked for a `Sec-WebSocket-Version` other than 13.
+    UnsupportedVersion,
+}
+
+impl HandshakeError {
+    #[must_use]
+    pub fn status(&self) -> Status {
+        match self {
+            HandshakeError::BadRequest(_) => Status::BadRequest,
+            HandshakeError::UnsupportedVersion => Status::UpgradeRequired,
+        }
+    }
+}
+
+impl std::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandshakeError::BadRequest(reason) => write!(f, "{reason}"),
+            HandshakeError::UnsupportedVersion => {
+                write!(f, "Only WebSocket version 13 is supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+impl From<HandshakeError> for Response {
+    /// The response lists the supported version when the client's isn't.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-4.4>
+    fn from(error: HandshakeError) -> Self {
+        let mut response = Response::plain(error.status(), error.to_string());
+        if error == HandshakeError::UnsupportedVersion {
+            response.headers.insert("Sec-WebSocket-Version", "13");
+        }
+        response
+    }
+}
+
+/// Limits and subprotocols of the WebSocket connections a handler accepts.
+#[derive(Clone, Debug)]
+pub struct WebSocketConfig {
+    max_message_size: usize,
+    frame_size: usize,
+    protocols: Vec<String>,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        WebSocketConfig {
+            max_message_size: 16 * 1024 * 1024,
+            frame_size: 64 * 1024,
+            protocols: Vec::new(),
+        }
+    }
+}
+
+impl WebSocketConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages from the client longer than `bytes`, once put together, close the
+    /// connection with [`CloseCode::TOO_BIG`]. 16 MiB by default.
+    #[must_use]
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Messages longer than `bytes` are sent in fragments. 64 KiB by default.
+    #[must_use]
+    pub fn frame_size(mut self, bytes: usize) -> Self {
+        self.frame_size = bytes.max(1);
+        self
+    }
+
+    /// The subprotocols the handler speaks. The first one the client offers is agreed on,
+    /// see [`WebSocket::protocol`].
+    #[must_use]
+    pub fn protocols(mut self, protocols: &[&str]) -> Self {
+        self.protocols = protocols.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Accept the WebSocket handshake in `request`. The returned `101 Switching
+    /// Protocols` response must be returned by the handler, `on_open` then gets the
+    /// socket.
+    /// RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-4.2>
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if `request` isn't an HTTP/1.1 `GET` asking to upgrade to
+    /// `websocket` with a valid `Sec-WebSocket-Key`, or asks for a version other than 13.
+    pub fn upgrade<F>(
+        &self,
+        request: &Request,
+        on_open: F,
+    ) -> Result<Response, HandshakeError>
+    where
+        F: FnOnce(WebSocket) + Send + 'static,
+    {
+        if request.method != Method::Get || request.http_version != HttpVersion::V1_1 {
+            return Err(HandshakeError::BadRequest(
+                "WebSocket handshakes are HTTP/1.1 GET requests",
+            ));
+        }
+        if !request.header_lists("Upgrade", "websocket")
+            || !request.header_lists("Connection", "Upgrade")
+        {
+            return Err(HandshakeError::BadRequest("Not a WebSocket handshake"));
+        }
+        if request.header("Sec-WebSocket-Version") != Some("13") {
+            return Err(HandshakeError::UnsupportedVersion);
+        }
+        let key = request
+            .header("Sec-WebSocket-Key")
+            .filter(|key| is_key(key))
+            .ok_or(HandshakeError::BadRequest("Invalid Sec-WebSocket-Key"))?;
+        let protocol = request
+            .header("Sec-WebSocket-Protocol")
+            .and_then(|offered| {
+                offered
+                    .split(',')
+                    .map(str::trim)
+                    .find(|offered| {
+                        self.protocols.iter().any(|protocol| protocol == offered)
+                    })
+                    .map(ToString::to_string)
+            });
+
+        let mut headers = Headers::new("Upgrade: websocket\r\nConnection: Upgrade");
+        headers.insert("Sec-WebSocket-Accept", accept(key));
+        if let Some(protocol) = &protocol {
+            headers.insert("Sec-WebSocket-Protocol", protocol.clone());
+        }
+        let config = self.clone();
+        Ok(Response::upgrade(
+            Status::SwitchingProtocols,
+            headers,
+            move |stream| on_open(WebSocket::new(stream, &config, protocol)),
+        ))
+    }
+}
+
+/// Accept the WebSocket handshake in `request` with the default
+/// [`WebSocketConfig`].
+///
+/// # Errors
+///
+/// Will return an error if `request` isn't a valid handshake, see
+/// [`WebSocketConfig::upgrade`].
+pub fn upgrade<F>(request: &Request, on_open: F) -> Result<Response, HandshakeError>
+where
+    F: FnOnce(WebSocket) + Send + 'static,
+{
+    WebSocketConfig::default().upgrade(request, on_open)
+}
+
+/// Whether `key` is base64 for 16 bytes.
+fn is_key(key: &str) -> bool {
+    BASE64.decode(key).is_ok_and(|key| key.len() == 16)
+}
+
+/// The `Sec-WebSocket-Accept` answering `key`.
+fn accept(key: &str) -> String {
+    const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    BASE64.encode(sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+/// SHA-1, which the handshake uses to show the server understood it, not for security.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc3174>
+#[expect(clippy::many_single_char_names)]
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64).wrapping_mul(8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16])
+                .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let next = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            (a, b, c, d, e) = (next, a, b.rotate_left(30), c, d);
+        }
+        for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{accept, sha1, HandshakeError, WebSocketConfig, BASE64};
+    use crate::request::Request;
+    use crate::response::{Response, Status};
+    use base64::Engine;
+    use std::io::Cursor;
+
+    fn handshake(extra: &str) -> Request<'static> {
+        let raw = format!("GET /chat HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: keep-alive, Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n{extra}\r\n");
+        Request::from(Cursor::new(raw)).unwrap()
+    }
+
+    #[test]
+    fn it_accepts_handshakes() {
+        let config = WebSocketConfig::new().protocols(&["chat", "superchat"]);
+        let request = handshake("Sec-WebSocket-Version: 13\r\nSec-WebSocket-Protocol: other, superchat, chat\r\n");
+        let response = config.upgrade(&request, |_| {}).unwrap();
+        assert_eq!(Status::SwitchingProtocols, response.status);
+        assert_eq!(
+            Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="),
+            response.headers.get("Sec-WebSocket-Accept")
+        );
+        assert_eq!(Some("websocket"), response.headers.get("Upgrade"));
+        assert_eq!(
+            Some("superchat"),
+            response.headers.get("Sec-WebSocket-Protocol")
+        );
+        assert!(response.on_upgrade.is_some());
+    }
+
+    #[test]
+    fn it_rejects_invalid_handshakes() {
+        let error =
+            super::upgrade(&handshake("Sec-WebSocket-Version: 8\r\n"), |_| {}).err();
+        assert_eq!(Some(HandshakeError::UnsupportedVersion), error);
+        let response = Response::from(error.unwrap());
+        assert_eq!(Status::UpgradeRequired, response.status);
+        assert_eq!(Some("13"), response.headers.get("Sec-WebSocket-Version"));
+
+        let request = Request::from(Cursor::new(
+            "GET / HTTP/1.1\r\nHost: a\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: short==\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(matches!(
+            super::upgrade(&request, |_| {}),
+            Err(HandshakeError::BadRequest(_))
+        ));
+        let request =
+            Request::from(Cursor::new("GET / HTTP/1.0\r\nUpgrade: websocket\r\n\r\n"))
+                .unwrap();
+        assert!(matches!(
+            super::upgrade(&request, |_| {}),
+            Err(HandshakeError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn it_hashes_keys() {
+        assert_eq!("qZk+NkcGgWq6PiVxeFDCbJzQ2J0=", BASE64.encode(sha1(b"abc")));
+        assert_eq!("2jmj7l5rSw0yVb/vlWAYkK/YBwk=", BASE64.encode(sha1(b"")));
+        assert_eq!(
+            "f5AAJXpJGNcHJlXqRoVAzcvULgw=",
+            BASE64.encode(sha1(&[b'a'; 100]))
+        );
+        assert_eq!(
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+            accept("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+    }
+}
@@ -0,0 +1,512 @@
+use super::frame::{Frame, Opcode, MAX_CONTROL_PAYLOAD};
+use super::WebSocketConfig;
+use crate::server::Upgraded;
+use std::fmt;
+use std::io::{self, BufReader, Write};
+
+/// A message received from or sent to the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// Received pings are answered with a pong before they're returned.
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The closing handshake, without a frame when the client sent no code.
+    Close(Option<CloseFrame>),
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(data: Vec<u8>) -> Self {
+        Message::Binary(data)
+    }
+}
+
+/// Why a connection is closed. Codes 3000 to 4999 are left to applications.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-7.4>
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CloseCode(pub u16);
+
+impl CloseCode {
+    pub const NORMAL: CloseCode = CloseCode(1000);
+    pub const GOING_AWAY: CloseCode = CloseCode(1001);
+    pub const PROTOCOL_ERROR: CloseCode = CloseCode(1002);
+    pub const UNSUPPORTED_DATA: CloseCode = CloseCode(1003);
+    pub const INVALID_PAYLOAD: CloseCode = CloseCode(1007);
+    pub const POLICY_VIOLATION: CloseCode = CloseCode(1008);
+    pub const TOO_BIG: CloseCode = CloseCode(1009);
+    pub const MANDATORY_EXTENSION: CloseCode = CloseCode(1010);
+    pub const INTERNAL_ERROR: CloseCode = CloseCode(1011);
+
+    /// Whether the code may be sent in a Close frame. 1005, 1006 and 1015 stand for
+    /// closes without a code and must not.
+    #[must_use]
+    pub fn is_sendable(self) -> bool {
+        matches!(self.0, 1000..=1003 | 1007..=1011 | 3000..=4999)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+/// Why a message couldn't be read or sent.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The client broke the protocol, the connection was closed with `code`.
+    Protocol {
+        code: CloseCode,
+        reason: &'static str,
+    },
+    /// The closing handshake already happened.
+    Closed,
+}
+
+impl Error {
+    pub(crate) fn protocol(reason: &'static str) -> Self {
+        Error::Protocol {
+            code: CloseCode::PROTOCOL_ERROR,
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Protocol { code, reason } => write!(f, "{reason} ({})", code.0),
+            Error::Closed => write!(f, "The WebSocket is closed"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// A WebSocket connection, exchanging whole messages with the client.
+///
+/// ```no_run
+/// use http::request::Request;
+/// use http::response::Response;
+/// use http::websocket::{self, Message};
+///
+/// fn echo(request: &mut Request) -> Response {
+///     websocket::upgrade(request, |mut socket| {
+///         while let Ok(message) = socket.read() {
+///             if let Message::Text(_) | Message::Binary(_) = message {
+///                 let _ = socket.send(message);
+///             }
+///         }
+///     })
+///     .unwrap_or_else(Response::from)
+/// }
+/// ```
+pub struct WebSocket {
+    stream: BufReader<Upgraded>,
+    max_message_size: usize,
+    frame_size: usize,
+    protocol: Option<String>,
+    /// The opcode and data of a fragmented message being received.
+    partial: Option<(Opcode, Vec<u8>)>,
+    sent_close: bool,
+    received_close: bool,
+}
+
+impl WebSocket {
+    pub(crate) fn new(
+        stream: Upgraded,
+        config: &WebSocketConfig,
+        protocol: Option<String>,
+    ) -> Self {
+        WebSocket {
+            stream: BufReader::new(stream),
+            max_message_size: config.max_message_size,
+            frame_size: config.frame_size,
+            protocol,
+            partial: None,
+            sent_close: false,
+            received_close: false,
+        }
+    }
+
+    /// The subprotocol agreed on in the handshake.
+    #[must_use]
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Wait for the next message, putting fragmented messages together.
+    ///
+    /// A Close message from the client is answered, after which the connection is closed.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Closed`] after the closing handshake, and [`Error::Protocol`]
+    /// if the client sent an invalid frame, a message longer than the limit or
+    /// invalid UTF-8 in a text message. The connection is closed with the error's
+    /// code.
+    pub fn read(&mut self) -> Result<Message, Error> {
+        if self.received_close {
+            return Err(Error::Closed);
+        }
+        let result = self.receive();
+        match &result {
+            Err(Error::Protocol { code, reason }) => {
+                if !self.sent_close {
+                    let _ = self.send_close(Some(*code), reason);
+                }
+                self.received_close = true;
+            }
+            Err(Error::Io(_)) => {
+                self.sent_close = true;
+                self.received_close = true;
+            }
+            _ => {}
+        }
+        result
+    }
+
+    fn receive(&mut self) -> Result<Message, Error> {
+        loop {
+            let received = self.partial.as_ref().map_or(0, |(_, data)| data.len());
+            // Control frames may interleave with a message that's nearly at the limit.
+            let max = self
+                .max_message_size
+                .saturating_sub(received)
+                .max(MAX_CONTROL_PAYLOAD);
+            let frame = Frame::read(&mut self.stream, max)?;
+            if !frame.opcode.is_control()
+                && received + frame.payload.len() > self.max_message_size
+            {
+                return Err(Error::Protocol {
+                    code: CloseCode::TOO_BIG,
+                    reason: "Message too big",
+                });
+            }
+
+            match frame.opcode {
+                Opcode::Ping => {
+                    if !self.sent_close {
+                        self.write(Opcode::Pong, true, frame.payload.clone())?;
+                    }
+                    return Ok(Message::Ping(frame.payload));
+                }
+                Opcode::Pong => return Ok(Message::Pong(frame.payload)),
+                Opcode::Close => {
+                    let close = close_frame(&frame.payload)?;
+                    self.received_close = true;
+                    // RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.1>
+                    if !self.sent_close {
+                        self.send_close(close.as_ref().map(|close| close.code), "")?;
+                    }
+                    return Ok(Message::Close(close));
+                }
+                Opcode::Text | Opcode::Binary if self.partial.is_some() => {
+                    return Err(Error::protocol("Expected a continuation frame"));
+                }
+                Opcode::Text | Opcode::Binary if frame.fin => {
+                    return message(frame.opcode, frame.payload);
+                }
+                Opcode::Text | Opcode::Binary => {
+                    self.partial = Some((frame.opcode, frame.payload));
+                }
+                Opcode::Continuation => {
+                    let (opcode, mut data) = self
+                        .partial
+                        .take()
+                        .ok_or(Error::protocol("Unexpected continuation frame"))?;
+                    data.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return message(opcode, data);
+                    }
+                    self.partial = Some((opcode, data));
+                }
+            }
+        }
+    }
+
+    /// Send a message. Text and binary messages longer than the
+    /// [frame size](WebSocketConfig::frame_size) are sent in fragments, and sending a
+    /// Close message starts the closing handshake without waiting for the client's
+    /// answer.
+    ///
+    /// # Errors
+    ///
+    /// Will return [`Error::Closed`] once a Close message was sent, or an error if a ping
+    /// or pong is longer than 125 bytes or writing fails.
+    pub fn send(&mut self, message: impl Into<Message>) -> Result<(), Error> {
+        if self.sent_close {
+            return Err(Error::Closed);
+        }
+        let (opcode, data) = match message.into() {
+            Message::Text(text) => (Opcode::Text, text.into_bytes()),
+            Message::Binary(data) => (Opcode::Binary, data),
+            Message::Ping(data) => (Opcode::Ping, data),
+            Message::Pong(data) => (Opcode::Pong, data),
+            Message::Close(close) => {
+                return match close {
+                    Some(close) => self.send_close(Some(close.code), &close.reason),
+                    None => self.send_close(None, ""),
+                };
+            }
+        };
+        if opcode.is_control() {
+            if data.len() > MAX_CONTROL_PAYLOAD {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Pings and pongs carry at most 125 bytes",
+                )));
+            }
+            return self.write(opcode, true, data);
+        }
+
+        let frame_size = self.frame_size;
+        let fragments = data.len().div_ceil(frame_size).max(1);
+        for i in 0..fragments {
+            let fragment =
+                data[i * frame_size..((i + 1) * frame_size).min(data.len())].to_vec();
+            let opcode = if i == 0 { opcode } else { Opcode::Continuation };
+            self.write(opcode, i + 1 == fragments, fragment)?;
+        }
+        Ok(())
+    }
+
+    /// Close the connection with `code`, waiting for the client to answer. Messages the
+    /// client sends in the meantime are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if writing fails, or the client doesn't answer properly.
+    pub fn close(&mut self, code: CloseCode, reason: &str) -> Result<(), Error> {
+        if !self.sent_close {
+            self.send_close(Some(code), reason)?;
+        }
+        while !self.received_close {
+            self.read()?;
+        }
+        Ok(())
+    }
+
+    fn send_close(&mut self, code: Option<CloseCode>, reason: &str) -> Result<(), Error> {
+        self.sent_close = true;
+        let mut payload = Vec::new();
+        if let Some(code) = code {
+            payload.extend_from_slice(&code.0.to_be_bytes());
+            // The reason is cut short at a character boundary to fit a control frame.
+            let mut end = reason.len().min(MAX_CONTROL_PAYLOAD - 2);
+            while !reason.is_char_boundary(end) {
+                end -= 1;
+            }
+            payload.extend_from_slice(&reason.as_bytes()[..end]);
+        }
+        self.write(Opcode::Close, true, payload)
+    }
+
+    fn write(
+        &mut self,
+        opcode: Opcode,
+        fin: bool,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        let frame = Frame {
+            fin,
+            opcode,
+            payload,
+        };
+        frame.write(self.stream.get_mut())?;
+        Ok(())
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        if !self.sent_close {
+            let _ = self.send_close(Some(CloseCode::NORMAL), "");
+        }
+        let _ = self.stream.get_mut().flush();
+    }
+}
+
+fn message(opcode: Opcode, data: Vec<u8>) -> Result<Message, Error> {
+    if opcode == Opcode::Binary {
+        return Ok(Message::Binary(data));
+    }
+    String::from_utf8(data)
+        .map(Message::Text)
+        .map_err(|_| invalid_payload())
+}
+
+/// Parse the payload of a Close frame.
+/// RFC: <https://datatracker.ietf.org/doc/html/rfc6455#section-5.5.1>
+fn close_frame(payload: &[u8]) -> Result<Option<CloseFrame>, Error> {
+    let [high, low, reason @ ..] = payload else {
+        return if payload.is_empty() {
+            Ok(None)
+        } else {
+            Err(Error::protocol("Invalid close frame"))
+        };
+    };
+    let code = CloseCode(u16::from_be_bytes([*high, *low]));
+    if !code.is_sendable() {
+        return Err(Error::protocol("Invalid close code"));
+    }
+    let reason = String::from_utf8(reason.to_vec()).map_err(|_| invalid_payload())?;
+    Ok(Some(CloseFrame { code, reason }))
+}
+
+fn invalid_payload() -> Error {
+    Error::Protocol {
+        code: CloseCode::INVALID_PAYLOAD,
+        reason: "Invalid UTF-8",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::{CloseCode, CloseFrame, Error, Message, WebSocket};
+    use crate::server::Upgraded;
+    use crate::websocket::WebSocketConfig;
+    use std::io::{self, Cursor, Read, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// Reads what a client sent, keeps what the server wrote.
+    struct Client {
+        input: Cursor<Vec<u8>>,
+        output: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Read for Client {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Client {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A masked client frame.
+    fn frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut bytes = vec![first, 0x80 | u8::try_from(payload.len()).unwrap()];
+        bytes.extend_from_slice(&mask);
+        bytes.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        bytes
+    }
+
+    fn socket(
+        frames: &[Vec<u8>],
+        config: &WebSocketConfig,
+    ) -> (WebSocket, Arc<Mutex<Vec<u8>>>) {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let client = Client {
+            input: Cursor::new(frames.concat()),
+            output: output.clone(),
+        };
+        (WebSocket::new(Upgraded::new(client), config, None), output)
+    }
+
+    #[test]
+    fn it_reads_fragmented_messages_and_answers_pings() {
+        let (mut socket, output) = socket(
+            &[
+                frame(0x01, b"Hel"),
+                frame(0x89, b"ping"),
+                frame(0x80, b"lo"),
+                frame(0x82, &[0, 1, 2]),
+                frame(0x88, b"\x03\xe8bye"),
+            ],
+            &WebSocketConfig::new(),
+        );
+        assert_eq!(Message::Ping(b"ping".to_vec()), socket.read().unwrap());
+        assert_eq!(Message::Text("Hello".to_string()), socket.read().unwrap());
+        assert_eq!(Message::Binary(vec![0, 1, 2]), socket.read().unwrap());
+        assert_eq!(
+            Message::Close(Some(CloseFrame {
+                code: CloseCode::NORMAL,
+                reason: "bye".to_string()
+            })),
+            socket.read().unwrap()
+        );
+        assert!(matches!(socket.read(), Err(Error::Closed)));
+        assert!(matches!(socket.send("late"), Err(Error::Closed)));
+        // The pong, then the echoed close.
+        assert_eq!(
+            b"\x8a\x04ping\x88\x02\x03\xe8".to_vec(),
+            *output.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn it_closes_on_invalid_messages() {
+        let code = |frames: &[Vec<u8>]| {
+            let config = WebSocketConfig::new().max_message_size(8);
+            let (mut socket, output) = socket(frames, &config);
+            let Err(Error::Protocol { code, .. }) = socket.read() else {
+                panic!("expected a protocol error");
+            };
+            let output = output.lock().unwrap();
+            assert_eq!(0x88, output[0]);
+            assert_eq!(code.0.to_be_bytes(), output[2..4]);
+            code
+        };
+        assert_eq!(CloseCode::INVALID_PAYLOAD, code(&[frame(0x81, b"\xff")]));
+        assert_eq!(
+            CloseCode::TOO_BIG,
+            code(&[frame(0x01, b"12345"), frame(0x80, b"6789")])
+        );
+        assert_eq!(CloseCode::TOO_BIG, code(&[frame(0x81, b"123456789")]));
+        assert_eq!(
+            CloseCode::TOO_BIG,
+            code(&[frame(0x01, b"123456789"), frame(0x80, b"a")])
+        );
+        assert_eq!(CloseCode::PROTOCOL_ERROR, code(&[frame(0x80, b"a")]));
+        assert_eq!(
+            CloseCode::PROTOCOL_ERROR,
+            code(&[frame(0x01, b"a"), frame(0x81, b"b")])
+        );
+        assert_eq!(CloseCode::PROTOCOL_ERROR, code(&[frame(0x88, b"\x03\xed")]));
+    }
+
+    #[test]
+    fn it_sends_fragments() {
+        let config = WebSocketConfig::new().frame_size(4);
+        let (mut socket, output) = socket(&[frame(0x88, b"")], &config);
+        socket.send("hello").unwrap();
+        socket.send(Vec::new()).unwrap();
+        assert!(socket.send(Message::Ping(vec![0; 126])).is_err());
+        socket.close(CloseCode::GOING_AWAY, "").unwrap();
+        assert_eq!(
+            b"\x01\x04hell\x80\x01o\x82\x00\x88\x02\x03\xe9".to_vec(),
+            *output.lock().unwrap()
+        );
+    }
+}